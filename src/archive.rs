@@ -0,0 +1,80 @@
+use crate::WalkedError;
+use chrono::{Datelike, Local, Timelike};
+use std::{
+    fs::File,
+    io::{Seek, Write},
+    path::{Path, PathBuf},
+};
+use zip::{DateTime, ZipWriter, result::ZipResult, write::SimpleFileOptions};
+
+/// Writes `entries` into a new zip archive at `dest`, directories along with everything inside
+/// them. Paths in the archive start at the entries themselves, so extracting it next to them
+/// recreates the same structure. The archive is removed again if anything goes wrong.
+pub fn compress(entries: &[PathBuf], dest: &Path) -> Result<(), WalkedError> {
+    let res = File::create_new(dest).map_err(Into::into).and_then(|file| {
+        let mut zip = ZipWriter::new(file);
+        for entry in entries {
+            let Some(name) = entry.file_name() else {
+                continue;
+            };
+            add_entry(&mut zip, entry, Path::new(name))?;
+        }
+        zip.finish()
+    });
+    if let Err(err) = res {
+        let _ = std::fs::remove_file(dest);
+        return Err(WalkedError::Message(format!(
+            "Couldn't create archive '{}': {err}",
+            dest.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Zip archives store local times, and only ones between 1980 and 2107.
+fn zip_time(time: std::time::SystemTime) -> Option<DateTime> {
+    let time = chrono::DateTime::<Local>::from(time);
+    DateTime::from_date_and_time(
+        u16::try_from(time.year()).ok()?,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .ok()
+}
+
+/// Adds `path` to the archive as `name`. Symlinks are stored as they are instead of being
+/// followed, so a link pointing back up the tree can't make this run forever.
+fn add_entry<W: Write + Seek>(zip: &mut ZipWriter<W>, path: &Path, name: &Path) -> ZipResult<()> {
+    let metadata = path.symlink_metadata()?;
+    let mut options = SimpleFileOptions::default();
+    if let Some(modified) = metadata.modified().ok().and_then(zip_time) {
+        options = options.last_modified_time(modified);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        options = options.unix_permissions(metadata.permissions().mode());
+    }
+    if metadata.is_symlink() {
+        // `add_symlink_from_path` would drop the `..` components of relative targets
+        let name = name
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        zip.add_symlink(name, std::fs::read_link(path)?.to_string_lossy(), options)?;
+    } else if metadata.is_dir() {
+        zip.add_directory_from_path(name, options)?;
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            add_entry(zip, &entry.path(), &name.join(entry.file_name()))?;
+        }
+    } else {
+        zip.start_file_from_path(name, options)?;
+        std::io::copy(&mut File::open(path)?, zip)?;
+    }
+    Ok(())
+}
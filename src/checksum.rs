@@ -0,0 +1,91 @@
+use crate::{
+    PathKind, WalkedError,
+    config::ChecksumAlgorithm,
+    transfer::{BACKGROUND_BYTES, CHUNK_SIZE},
+};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, channel},
+};
+
+/// The digest of a file, computed on a worker thread if the file is big.
+pub struct Checksum {
+    pub path: PathBuf,
+    pub algorithm: ChecksumAlgorithm,
+    /// `None` while the digest is still being computed.
+    pub digest: Option<String>,
+    result: Option<Receiver<Result<String, WalkedError>>>,
+}
+
+impl Checksum {
+    /// Starts hashing `path`, a small file is hashed before this returns.
+    pub fn start(path: &Path, algorithm: ChecksumAlgorithm) -> Result<Self, WalkedError> {
+        let mut res = Self {
+            path: path.to_path_buf(),
+            algorithm,
+            digest: None,
+            result: None,
+        };
+        if path.metadata().is_ok_and(|m| m.len() < BACKGROUND_BYTES) {
+            res.digest = Some(hash_file(path, algorithm)?);
+        } else {
+            let (tx, result) = channel();
+            let path = path.to_path_buf();
+            std::thread::spawn(move || {
+                let _ = tx.send(hash_file(&path, algorithm));
+            });
+            res.result = Some(result);
+        }
+        Ok(res)
+    }
+
+    /// Takes in the digest once the worker is done, returns it (or why it couldn't be computed)
+    /// on the call it arrives.
+    pub fn receive(&mut self) -> Option<Result<String, WalkedError>> {
+        let res = self.result.as_ref()?.try_recv().ok()?;
+        self.result = None;
+        if let Ok(digest) = &res {
+            self.digest = Some(digest.clone());
+        }
+        Some(res)
+    }
+}
+
+/// Hashes the file at `path` in chunks, so it's never read into memory as a whole.
+pub fn hash_file(path: &Path, algorithm: ChecksumAlgorithm) -> Result<String, WalkedError> {
+    let hash = || -> std::io::Result<String> {
+        let file = File::open(path)?;
+        Ok(match algorithm {
+            ChecksumAlgorithm::Sha256 => hash_reader::<Sha256>(file)?,
+            ChecksumAlgorithm::Md5 => hash_reader::<md5::Md5>(file)?,
+        })
+    };
+    hash().map_err(|err| {
+        WalkedError::from_io(
+            &err,
+            path,
+            PathKind::File,
+            format!("Couldn't read '{}'", path.display()),
+        )
+    })
+}
+
+fn hash_reader<D: Digest>(mut reader: impl Read) -> std::io::Result<String> {
+    let mut hasher = D::new();
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
@@ -0,0 +1,83 @@
+use crate::config::expand_path;
+use std::path::Path;
+
+/// The paths a partly typed path in a prompt can be completed to, cycled through by pressing
+/// tab again.
+pub struct Completion {
+    /// Where the path being completed starts in the prompt's input.
+    pub start: usize,
+    /// The length of the directory part shared by all candidates, what follows it is the name
+    /// shown in the candidate list.
+    pub dir_len: usize,
+    pub candidates: Vec<String>,
+    /// The candidate in the prompt, `None` while it shows their longest common prefix.
+    pub current: Option<usize>,
+}
+
+impl Completion {
+    /// The names of the candidates, without the directory they are in.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.candidates.iter().map(|c| &c[self.dir_len..])
+    }
+}
+
+/// Entries in the directory `partial` points into whose names start like its last component,
+/// written the way `partial` is (with a `~` left as it is). Directories end with a separator.
+/// Relative paths are relative to `dir`. Hidden entries are left out unless `partial` asks for
+/// them with a leading dot. Returns the candidates along with the length of their directory part.
+pub fn candidates(partial: &str, dir: &Path, dirs_only: bool) -> (Vec<String>, usize) {
+    // a lone `~` is completed to the home directory itself
+    if partial.starts_with('~') && !partial.contains(['/', '\\']) {
+        let is_dir = expand_path(partial).is_ok_and(|path| Path::new(&path).is_dir());
+        return if is_dir {
+            (vec![format!("{partial}{}", std::path::MAIN_SEPARATOR)], 0)
+        } else {
+            (Vec::new(), 0)
+        };
+    }
+    let dir_len = partial.rfind(['/', '\\']).map_or(0, |i| i + 1);
+    let (head, prefix) = partial.split_at(dir_len);
+    let Ok(expanded) = expand_path(head) else {
+        return (Vec::new(), dir_len);
+    };
+    let Ok(read_dir) = std::fs::read_dir(dir.join(expanded)) else {
+        return (Vec::new(), dir_len);
+    };
+    let mut res = read_dir
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            // symlinks to directories count as directories
+            let is_dir = entry.path().is_dir();
+            if dirs_only && !is_dir {
+                return None;
+            }
+            Some(if is_dir {
+                format!("{head}{name}{}", std::path::MAIN_SEPARATOR)
+            } else {
+                format!("{head}{name}")
+            })
+        })
+        .collect::<Vec<_>>();
+    res.sort();
+    (res, dir_len)
+}
+
+/// The longest prefix all of `candidates` share, on a character boundary.
+pub fn common_prefix(candidates: &[String]) -> &str {
+    let Some(first) = candidates.first() else {
+        return "";
+    };
+    let len = candidates[1..].iter().fold(first.len(), |len, c| {
+        first[..len]
+            .char_indices()
+            .zip(c.chars())
+            .find(|((_, a), b)| a != b)
+            // without a difference, `c` is a prefix of what's left of `first`
+            .map_or(len.min(c.len()), |((i, _), _)| i)
+    });
+    &first[..len]
+}
@@ -1,10 +1,450 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
+use crossterm::event::{
+    KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers, MediaKeyCode,
+};
+use ratatui::style::{Color, Modifier, Style};
+use std::collections::HashMap;
 use toml::Value;
 
+/// Used when `date_format` isn't set or isn't valid.
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Added to names that are taken, `#` is replaced with a number.
+pub const DEFAULT_COLLISION_SUFFIX: &str = ".#";
+
+/// The characters the platform doesn't allow in names.
+#[cfg(windows)]
+pub const DISALLOWED_CHARACTERS: &str = "\\/:*?\"<>|";
+#[cfg(not(windows))]
+pub const DISALLOWED_CHARACTERS: &str = "/\0";
+
+/// What to do when pasting onto an existing entry of a different type (a file onto a directory
+/// or vice versa).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TypeConflictPolicy {
+    /// Paste under a new, non-conflicting name.
+    Rename,
+    /// Don't paste the entry and report the conflict.
+    Refuse,
+}
+
+impl TypeConflictPolicy {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "rename" => Some(TypeConflictPolicy::Rename),
+            "refuse" => Some(TypeConflictPolicy::Refuse),
+            _ => None,
+        }
+    }
+}
+
+/// What to do when pasting an entry whose name is already taken by an entry of the same type.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PasteConflictPolicy {
+    /// Ask for each entry.
+    Ask,
+    /// Paste under a new, non-conflicting name.
+    Rename,
+    /// Replace the existing entry.
+    Overwrite,
+    /// Don't paste the entry.
+    Skip,
+}
+
+impl PasteConflictPolicy {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "ask" => Some(PasteConflictPolicy::Ask),
+            "rename" => Some(PasteConflictPolicy::Rename),
+            "overwrite" => Some(PasteConflictPolicy::Overwrite),
+            "skip" => Some(PasteConflictPolicy::Skip),
+            _ => None,
+        }
+    }
+}
+
+/// How modification times are shown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TimeStyle {
+    /// Formatted with `date_format`.
+    Absolute,
+    /// Like "3 days ago".
+    Relative,
+}
+
+impl TimeStyle {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "absolute" => Some(TimeStyle::Absolute),
+            "relative" => Some(TimeStyle::Relative),
+            _ => None,
+        }
+    }
+}
+
+/// How sizes are shown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SizeFormat {
+    /// In powers of 1024, like "1.5 MiB".
+    Binary,
+    /// In powers of 1000, like "1.6 MB".
+    Decimal,
+    /// The exact number of bytes.
+    Bytes,
+}
+
+impl SizeFormat {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "binary" => Some(SizeFormat::Binary),
+            "decimal" => Some(SizeFormat::Decimal),
+            "bytes" => Some(SizeFormat::Bytes),
+            _ => None,
+        }
+    }
+
+    pub fn format(&self, bytes: u64) -> String {
+        let size = bytesize::ByteSize::b(bytes);
+        match self {
+            SizeFormat::Binary => size.display().iec().to_string(),
+            SizeFormat::Decimal => size.display().si().to_string(),
+            SizeFormat::Bytes => format!("{bytes} B"),
+        }
+    }
+}
+
+/// The hash computed with the `checksum` key.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Md5,
+}
+
+impl ChecksumAlgorithm {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "sha256" => Some(ChecksumAlgorithm::Sha256),
+            "md5" => Some(ChecksumAlgorithm::Md5),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Md5 => "md5",
+        }
+    }
+}
+
+/// How names wider than their column are shortened.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NameTruncation {
+    /// The end is cut off, like "my-very-long-rep…".
+    End,
+    /// The middle is cut out, so the extension stays visible, like "my-very-…-report.pdf".
+    Middle,
+    /// Names are clipped at the edge of the pane, `scroll_right` shows the rest.
+    None,
+}
+
+impl NameTruncation {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "end" => Some(NameTruncation::End),
+            "middle" => Some(NameTruncation::Middle),
+            "none" => Some(NameTruncation::None),
+            _ => None,
+        }
+    }
+}
+
+/// The program files with a certain extension are opened with, `[open_with]` in the config.
+#[derive(Clone)]
+pub struct OpenWith {
+    /// A program followed by optional arguments, `%s` is replaced with the path of the file. The
+    /// path is added as the last argument if there's no `%s`.
+    pub command: String,
+    /// Hand the terminal over to the program and wait for it to exit, for programs that run in
+    /// the terminal rather than opening a window.
+    pub suspend: bool,
+}
+
+/// Where the configuration file is looked for when none is passed on the command line, in order.
+pub fn default_config_paths() -> Vec<std::path::PathBuf> {
+    let mut res = Vec::new();
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        res.push(std::path::PathBuf::from(dir));
+    }
+    if let Some(home) = dirs::home_dir() {
+        res.push(home.join(".config"));
+    }
+    // the platform's own location, like `~/Library/Application Support` on macOS
+    if let Some(dir) = dirs::config_dir() {
+        res.push(dir);
+    }
+    res.dedup();
+    res.into_iter()
+        .map(|dir| dir.join("walked").join("config.toml"))
+        .collect()
+}
+
+/// The program used to open things with the system's default application.
+pub fn default_opener() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    }
+}
+
+/// The command used to open files with the application the system associates with them.
+pub fn default_open_command() -> &'static str {
+    if cfg!(target_os = "windows") {
+        // `start` treats its first quoted argument as the window title, so an empty one is passed
+        "cmd /C start \"\""
+    } else {
+        default_opener()
+    }
+}
+
+/// Nerd Font icons for common file extensions, `[icons]` in the config adds to and overrides these.
+fn default_icons() -> HashMap<String, String> {
+    [
+        ("rs", "\u{e7a8}"),
+        ("toml", "\u{e615}"),
+        ("yaml", "\u{e615}"),
+        ("yml", "\u{e615}"),
+        ("json", "\u{e60b}"),
+        ("md", "\u{e73e}"),
+        ("txt", "\u{f0f6}"),
+        ("lock", "\u{f023}"),
+        ("c", "\u{e61e}"),
+        ("h", "\u{e61e}"),
+        ("cpp", "\u{e61d}"),
+        ("go", "\u{e626}"),
+        ("java", "\u{e738}"),
+        ("py", "\u{e606}"),
+        ("js", "\u{e74e}"),
+        ("ts", "\u{e628}"),
+        ("html", "\u{e736}"),
+        ("css", "\u{e749}"),
+        ("sh", "\u{f489}"),
+        ("png", "\u{f1c5}"),
+        ("jpg", "\u{f1c5}"),
+        ("jpeg", "\u{f1c5}"),
+        ("gif", "\u{f1c5}"),
+        ("svg", "\u{f1c5}"),
+        ("pdf", "\u{f1c1}"),
+        ("zip", "\u{f1c6}"),
+        ("tar", "\u{f1c6}"),
+        ("gz", "\u{f1c6}"),
+        ("7z", "\u{f1c6}"),
+        ("mp3", "\u{f1c7}"),
+        ("flac", "\u{f1c7}"),
+        ("wav", "\u{f1c7}"),
+        ("mp4", "\u{f1c8}"),
+        ("mkv", "\u{f1c8}"),
+    ]
+    .into_iter()
+    .map(|(extension, icon)| (extension.to_string(), icon.to_string()))
+    .collect()
+}
+
+/// Colors and styles used to draw the interface, set in the `[theme]` table.
+pub struct Theme {
+    /// The row under the cursor.
+    pub highlight: Style,
+    /// The other entries of a selection.
+    pub selection: Style,
+    /// The name being edited in Insert mode.
+    pub insert: Style,
+    pub error: Style,
+    pub warning: Style,
+    pub directory: Color,
+    pub file: Color,
+    pub symlink: Color,
+    pub executable: Color,
+    pub broken_symlink: Color,
+    /// Borders of the preview pane and popups.
+    pub border: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            highlight: Style::new().add_modifier(Modifier::REVERSED),
+            selection: Style::new().add_modifier(Modifier::REVERSED),
+            insert: Style::new().add_modifier(Modifier::UNDERLINED),
+            error: Style::new().fg(Color::Red),
+            warning: Style::new().fg(Color::Yellow),
+            directory: Color::Blue,
+            file: Color::Reset,
+            symlink: Color::Cyan,
+            executable: Color::Green,
+            broken_symlink: Color::Red,
+            border: Color::Reset,
+        }
+    }
+}
+
+/// Colors for names of entries with common file extensions, `[extension_colors]` in the config
+/// adds to and overrides these.
+fn default_extension_colors() -> HashMap<String, Color> {
+    let archives = ["zip", "tar", "gz", "xz", "bz2", "7z", "rar"].map(|e| (e, Color::Yellow));
+    let media = [
+        "png", "jpg", "jpeg", "gif", "svg", "webp", "mp4", "mkv", "webm",
+    ]
+    .map(|e| (e, Color::Magenta));
+    let audio = ["mp3", "flac", "wav", "ogg"].map(|e| (e, Color::LightCyan));
+    archives
+        .into_iter()
+        .chain(media)
+        .chain(audio)
+        .map(|(extension, color)| (extension.to_string(), color))
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    Name,
+    Size,
+    Modified,
+    Extension,
+}
+
+impl SortMode {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "name" => Some(SortMode::Name),
+            "size" => Some(SortMode::Size),
+            "modified" => Some(SortMode::Modified),
+            "extension" => Some(SortMode::Extension),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortMode::Name => "name",
+            SortMode::Size => "size",
+            SortMode::Modified => "modified",
+            SortMode::Extension => "extension",
+        }
+    }
+
+    /// The mode that comes after this one when cycling through them.
+    pub fn next(&self) -> Self {
+        match self {
+            SortMode::Name => SortMode::Size,
+            SortMode::Size => SortMode::Modified,
+            SortMode::Modified => SortMode::Extension,
+            SortMode::Extension => SortMode::Name,
+        }
+    }
+}
+
+/// Replaces a leading `~` in `path` with the home directory.
+pub fn expand_tilde(path: &str) -> String {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            format!("{}{rest}", home.display())
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Expands a leading `~` or `~user` to the home directory and substitutes environment variables
+/// written as `$VAR`, `${VAR}` or, on Windows, `%VAR%`. Unknown users and variables that aren't
+/// set are errors, so they don't end up in the path as they are.
+pub fn expand_path(path: &str) -> Result<String, crate::WalkedError> {
+    let error = |message: String| crate::WalkedError::Message(message);
+    let mut res = String::new();
+    let mut rest = path;
+    if let Some(after) = path.strip_prefix('~') {
+        let end = after.find(['/', '\\']).unwrap_or(after.len());
+        let (user, after) = after.split_at(end);
+        let home = if user.is_empty() {
+            dirs::home_dir().ok_or_else(|| error("Couldn't find the home directory".to_string()))?
+        } else {
+            user_home(user).ok_or_else(|| error(format!("There's no user called '{user}'")))?
+        };
+        res.push_str(&home.to_string_lossy());
+        rest = after;
+    }
+    let var = |name: &str| {
+        std::env::var(name).map_err(|_| error(format!("The variable '{name}' isn't set")))
+    };
+    while let Some(i) = rest.find(['$', '%']) {
+        res.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        if rest[i..].starts_with('%') {
+            // `%` only starts a variable on Windows, and only if it's closed again
+            match after.find('%').filter(|_| cfg!(windows)) {
+                Some(end) if end > 0 && after[..end].chars().all(is_name) => {
+                    res.push_str(&var(&after[..end])?);
+                    rest = &after[end + 1..];
+                }
+                _ => {
+                    res.push('%');
+                    rest = after;
+                }
+            }
+        } else if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| error(format!("'${{' isn't closed in '{path}'")))?;
+            res.push_str(&var(&braced[..end])?);
+            rest = &braced[end + 1..];
+        } else {
+            let end = after.find(|c| !is_name(c)).unwrap_or(after.len());
+            if end == 0 {
+                // a `$` that isn't followed by a name is just a character
+                res.push('$');
+            } else {
+                res.push_str(&var(&after[..end])?);
+            }
+            rest = &after[end..];
+        }
+    }
+    res.push_str(rest);
+    Ok(res)
+}
+
+/// The home directory of `user`, looked up in `/etc/passwd`.
+#[cfg(unix)]
+fn user_home(user: &str) -> Option<std::path::PathBuf> {
+    std::fs::read_to_string("/etc/passwd")
+        .ok()?
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.len() >= 6 && fields[0] == user)
+        .map(|fields| std::path::PathBuf::from(fields[5]))
+}
+
+/// The home directory of `user`, assuming it's next to the current user's.
+#[cfg(not(unix))]
+fn user_home(user: &str) -> Option<std::path::PathBuf> {
+    dirs::home_dir()?
+        .parent()
+        .map(|dir| dir.join(user))
+        .filter(|dir| dir.is_dir())
+}
+
+/// Sorts directories matching `pattern` by `sort` instead of the default.
+pub struct SortRule {
+    pub pattern: glob::Pattern,
+    pub sort: SortMode,
+    pub reverse: bool,
+}
+
 pub struct Config {
     pub normal_mode_text: String,
     pub search_mode_text: String,
     pub insert_mode_text: String,
+    pub visual_mode_text: String,
     pub show_entry_number: bool,
     pub show_entry_type: bool,
     pub show_working_directory: bool,
@@ -13,6 +453,97 @@ pub struct Config {
     pub file_text: String,
     pub symlink_text: String,
     pub other_text: String,
+    pub normal_mode_cancels: bool,
+    pub type_conflict: TypeConflictPolicy,
+    pub show_type_indicator: bool,
+    pub file_manager_command: String,
+    pub stop_on_first_error: bool,
+    pub sort_rules: Vec<SortRule>,
+    pub use_trash_by_default: bool,
+    pub confirm_delete: bool,
+    pub default_sort: SortMode,
+    pub sort_reverse: bool,
+    pub directories_first: bool,
+    pub open_command: String,
+    /// Programs used instead of `open_command` by file extension (in lowercase).
+    pub open_with: HashMap<String, OpenWith>,
+    pub show_dir_size: bool,
+    pub show_preview: bool,
+    pub preview_theme: String,
+    pub show_git_status: bool,
+    pub show_modified: bool,
+    pub date_format: String,
+    pub size_format: SizeFormat,
+    pub time_style: TimeStyle,
+    pub show_permissions: bool,
+    pub use_icons: bool,
+    pub file_icon: String,
+    pub directory_icon: String,
+    pub symlink_icon: String,
+    pub icons: HashMap<String, String>,
+    pub colorize_entries: bool,
+    pub theme: Theme,
+    pub extension_colors: HashMap<String, Color>,
+    /// Problems found while parsing the configuration file, shown once it is loaded.
+    pub warnings: Vec<String>,
+    /// Actions bound to two keys pressed one after the other.
+    pub key_sequences: Vec<KeySequence>,
+    pub paste_conflict: PasteConflictPolicy,
+    /// Whether the search of `pattern_rename` is a regex, `$1` in the replacement is its first
+    /// group.
+    pub pattern_rename_regex: bool,
+    /// Create new entries with a placeholder name and rename them in place instead of asking for
+    /// their name first.
+    pub inline_new_entries: bool,
+    /// Put the cursor before the extension of a file when renaming it, instead of where it was.
+    pub rename_select_stem: bool,
+    /// Drawn in front of the row under the cursor.
+    pub highlight_symbol: String,
+    /// Show the size of the selection and of the whole directory under each pane.
+    pub show_status_bar: bool,
+    /// Show the free space of the filesystem the working directory is on under each pane.
+    pub show_disk_usage: bool,
+    /// Show where the visible entries are in the listing on the right edge of each pane.
+    pub show_scrollbar: bool,
+    /// Compare runs of digits in names by their value, so `file2` comes before `file10`.
+    pub natural_sort: bool,
+    /// Ignore case when sorting by name, names that only differ in case are still ordered by it.
+    pub case_insensitive_sort: bool,
+    /// Show where symlinks point after their names, like `ls -l` does.
+    pub show_symlink_target: bool,
+    /// Walk into symlinks to directories, otherwise they are treated like files.
+    pub follow_symlinks: bool,
+    /// Characters that can't be used when naming entries, path separators never can.
+    pub disallowed_characters: String,
+    /// Copy files as reflinks sharing their data with the original where the filesystem supports
+    /// it (btrfs, XFS, APFS), which is instant even for big files.
+    pub use_reflink: bool,
+    /// Splits that would make panes narrower or shorter than this (in cells) are refused.
+    pub min_pane_width: u16,
+    pub min_pane_height: u16,
+    /// Start in the directory walked was left in last time, unless one is given on the command
+    /// line.
+    pub restore_last_dir: bool,
+    /// Put the digest computed with the `checksum` key on the system clipboard as well.
+    pub copy_checksum: bool,
+    pub checksum_algorithm: ChecksumAlgorithm,
+    /// Compares the two files selected when pressing `diff`, their paths are added as the last
+    /// two arguments.
+    pub diff_command: String,
+    /// How many directories deep `find` searches below the working directory, 1 only searches
+    /// the working directory itself.
+    pub find_max_depth: u16,
+    /// The placeholders created by `new_file` and `new_directory` with `inline_new_entries`.
+    pub new_file_name: String,
+    pub new_dir_name: String,
+    /// Added to the name of a new entry whose name is taken, `#` is replaced with the lowest
+    /// number that makes it free (the number is added at the end if there's no `#`).
+    pub collision_suffix: String,
+    /// Put the collision suffix in front of a file's extension, `photo.1.png` rather than
+    /// `photo.png.1`.
+    pub collision_before_extension: bool,
+    /// How names that don't fit are shortened while they aren't scrolled sideways.
+    pub name_truncation: NameTruncation,
     pub new_file: KeyEvent,
     pub new_directory: KeyEvent,
     pub duplicate: KeyEvent,
@@ -41,9 +572,96 @@ pub struct Config {
     pub insert_mode: KeyEvent,
     pub normal_mode: KeyEvent,
     pub close_active_pane: KeyEvent,
+    pub undo: KeyEvent,
+    pub redo: KeyEvent,
+    pub operation_log: KeyEvent,
+    pub help: KeyEvent,
+    pub jump: KeyEvent,
+    pub open_file_manager: KeyEvent,
+    pub cut: KeyEvent,
+    pub trash: KeyEvent,
+    pub remove_permanently: KeyEvent,
+    pub symlink: KeyEvent,
+    pub cycle_sort: KeyEvent,
+    pub reverse_sort: KeyEvent,
+    pub toggle_directories_first: KeyEvent,
+    pub filter: KeyEvent,
+    pub goto: KeyEvent,
+    pub back: KeyEvent,
+    pub forward: KeyEvent,
+    pub page_up: KeyEvent,
+    pub page_down: KeyEvent,
+    pub half_page_up: KeyEvent,
+    pub half_page_down: KeyEvent,
+    pub goto_top: KeyEvent,
+    pub goto_bottom: KeyEvent,
+    pub open: KeyEvent,
+    pub shell: KeyEvent,
+    pub dir_size: KeyEvent,
+    pub chmod: KeyEvent,
+    pub compress: KeyEvent,
+    pub select_all: KeyEvent,
+    pub invert_selection: KeyEvent,
+    pub clear_selection: KeyEvent,
+    pub toggle_select: KeyEvent,
+    pub bulk_rename: KeyEvent,
+    pub pattern_rename: KeyEvent,
+    pub number_rename: KeyEvent,
+    pub yank_path: KeyEvent,
+    pub yank_path_relative: KeyEvent,
+    pub quit_with_selection: KeyEvent,
+    pub toggle_case_insensitive_sort: KeyEvent,
+    pub swap_pane_up: KeyEvent,
+    pub swap_pane_down: KeyEvent,
+    pub swap_pane_left: KeyEvent,
+    pub swap_pane_right: KeyEvent,
+    pub copy_to_other_pane: KeyEvent,
+    pub move_to_other_pane: KeyEvent,
+    pub zoom_pane: KeyEvent,
+    pub save_session: KeyEvent,
+    pub checksum: KeyEvent,
+    pub diff: KeyEvent,
+    pub select_glob: KeyEvent,
+    pub find: KeyEvent,
+    pub repeat: KeyEvent,
+    pub cancel: KeyEvent,
+    pub scroll_left: KeyEvent,
+    pub scroll_right: KeyEvent,
+    pub error_log: KeyEvent,
+    pub visual_mode: KeyEvent,
     pub quit: KeyEvent,
 }
 
+/// Two keys bound to an action, the second pressed within [`KEY_SEQUENCE_TIMEOUT`] of the first.
+///
+/// [`KEY_SEQUENCE_TIMEOUT`]: crate::window::KEY_SEQUENCE_TIMEOUT
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct KeySequence {
+    pub keys: [KeyEvent; 2],
+    /// What the action's field is set to, a key that's never pressed and only stands for the
+    /// sequence. It's handled like a pressed key once the sequence is finished.
+    pub action: KeyEvent,
+}
+
+impl KeySequence {
+    /// A key no other sequence in `sequences` stands for.
+    fn placeholder(sequences: &[KeySequence]) -> KeyEvent {
+        // characters from a private use area aren't typed, so they can't be mistaken for a key
+        let n = sequences
+            .iter()
+            .filter_map(|s| match s.action.code {
+                KeyCode::Char(c) => Some(c as u32 + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0xF0000);
+        KeyEvent::new(
+            KeyCode::Char(char::from_u32(n).unwrap_or(char::REPLACEMENT_CHARACTER)),
+            KeyModifiers::NONE,
+        )
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
@@ -54,10 +672,69 @@ impl Default for Config {
             normal_mode_text: String::from("NORMAL"),
             search_mode_text: String::from("SEARCH"),
             insert_mode_text: String::from("INSERT"),
+            visual_mode_text: String::from("VISUAL"),
             directory_text: String::from("D"),
             file_text: String::from("F"),
             symlink_text: String::from("S"),
             other_text: String::from("O"),
+            normal_mode_cancels: false,
+            type_conflict: TypeConflictPolicy::Rename,
+            show_type_indicator: false,
+            file_manager_command: default_opener().to_string(),
+            stop_on_first_error: false,
+            sort_rules: Vec::new(),
+            use_trash_by_default: false,
+            confirm_delete: true,
+            default_sort: SortMode::Name,
+            sort_reverse: false,
+            directories_first: false,
+            open_command: default_open_command().to_string(),
+            open_with: HashMap::new(),
+            show_dir_size: false,
+            show_preview: false,
+            preview_theme: String::from("base16-ocean.dark"),
+            show_git_status: false,
+            show_modified: false,
+            date_format: String::from(DEFAULT_DATE_FORMAT),
+            size_format: SizeFormat::Binary,
+            time_style: TimeStyle::Absolute,
+            show_permissions: false,
+            use_icons: false,
+            file_icon: String::from("\u{f15b}"),
+            directory_icon: String::from("\u{f07b}"),
+            symlink_icon: String::from("\u{f0c1}"),
+            icons: default_icons(),
+            colorize_entries: true,
+            theme: Theme::default(),
+            extension_colors: default_extension_colors(),
+            warnings: Vec::new(),
+            key_sequences: Vec::new(),
+            paste_conflict: PasteConflictPolicy::Ask,
+            pattern_rename_regex: false,
+            inline_new_entries: false,
+            rename_select_stem: false,
+            highlight_symbol: String::from(">>"),
+            show_status_bar: false,
+            show_disk_usage: false,
+            show_scrollbar: false,
+            natural_sort: false,
+            case_insensitive_sort: false,
+            show_symlink_target: false,
+            follow_symlinks: true,
+            disallowed_characters: String::from(DISALLOWED_CHARACTERS),
+            use_reflink: false,
+            min_pane_width: 20,
+            min_pane_height: 5,
+            restore_last_dir: false,
+            copy_checksum: false,
+            checksum_algorithm: ChecksumAlgorithm::Sha256,
+            diff_command: String::from("diff -u"),
+            find_max_depth: 10,
+            new_file_name: String::from(crate::window::NEW_FILE_TEXT),
+            new_dir_name: String::from(crate::window::NEW_DIRECTORY_TEXT),
+            collision_suffix: String::from(DEFAULT_COLLISION_SUFFIX),
+            collision_before_extension: true,
+            name_truncation: NameTruncation::None,
             new_file: KeyEvent {
                 code: KeyCode::Char('n'),
                 modifiers: KeyModifiers::CONTROL,
@@ -214,250 +891,1717 @@ impl Default for Config {
                 kind: KeyEventKind::Press,
                 state: KeyEventState::NONE,
             },
-            quit: KeyEvent {
-                code: KeyCode::Char('q'),
+            undo: KeyEvent {
+                code: KeyCode::Char('u'),
                 modifiers: KeyModifiers::NONE,
                 kind: KeyEventKind::Press,
                 state: KeyEventState::NONE,
             },
-            dir_walk: KeyEvent {
-                code: KeyCode::Char(' '),
+            redo: KeyEvent {
+                code: KeyCode::Char('r'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            operation_log: KeyEvent {
+                code: KeyCode::Char('U'),
                 modifiers: KeyModifiers::NONE,
                 kind: KeyEventKind::Press,
                 state: KeyEventState::NONE,
             },
-            dir_up: KeyEvent {
+            help: KeyEvent {
+                code: KeyCode::Char('?'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            jump: KeyEvent {
+                code: KeyCode::Char('z'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            open_file_manager: KeyEvent {
+                code: KeyCode::Char('O'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            cut: KeyEvent {
+                code: KeyCode::Char('d'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            trash: KeyEvent {
+                code: KeyCode::Char('t'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            remove_permanently: KeyEvent {
                 code: KeyCode::Char('x'),
+                modifiers: KeyModifiers::ALT,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            symlink: KeyEvent {
+                code: KeyCode::Char('S'),
                 modifiers: KeyModifiers::NONE,
                 kind: KeyEventKind::Press,
                 state: KeyEventState::NONE,
             },
-        }
-    }
-}
-
-impl Config {
-    fn key_event_from_toml(key_event: &mut KeyEvent, toml: &Value) {
-        if let Some(v) = toml.as_str() {
-            let split = v.split_once("-");
-            let (code, modifiers) = {
-                let mut modifiers = KeyModifiers::NONE;
-                (
-                    {
-                        if let Some((mod_str, code)) = split {
-                            if mod_str.contains('c') || mod_str.contains('C') {
-                                modifiers = modifiers.union(KeyModifiers::CONTROL);
-                            }
-                            if mod_str.contains('s') || mod_str.contains('S') {
-                                modifiers = modifiers.union(KeyModifiers::SHIFT);
-                            }
-                            if mod_str.contains('a') || mod_str.contains('A') {
-                                modifiers = modifiers.union(KeyModifiers::ALT);
-                            }
-                            Self::key_code_from_str(code)
-                        } else {
-                            Self::key_code_from_str(v)
-                        }
-                    },
-                    modifiers,
-                )
-            };
-            if let Some(code) = code {
-                key_event.code = code;
-                key_event.modifiers = modifiers;
-            }
-        }
-    }
-
-    fn key_code_from_str(s: &str) -> Option<KeyCode> {
-        if s.chars().count() == 1 {
-            return Some(KeyCode::Char(s.chars().nth(0).unwrap()));
-        }
-
-        if s.starts_with('F') && s.len() > 1 {
-            if let Ok(n) = s[1..].parse() {
-                return Some(KeyCode::F(n));
-            }
-        }
-
-        if s == "Backspace" {
-            Some(KeyCode::Backspace)
-        } else if s == "Enter" {
-            Some(KeyCode::Enter)
-        } else if s == "Left" {
-            Some(KeyCode::Left)
-        } else if s == "Right" {
-            Some(KeyCode::Right)
-        } else if s == "Up" {
-            Some(KeyCode::Up)
-        } else if s == "Down" {
-            Some(KeyCode::Down)
-        } else if s == "Home" {
-            Some(KeyCode::Home)
-        } else if s == "End" {
-            Some(KeyCode::End)
-        } else if s == "PageUp" {
-            Some(KeyCode::PageUp)
-        } else if s == "PageDown" {
-            Some(KeyCode::PageDown)
-        } else if s == "Tab" {
-            Some(KeyCode::Tab)
-        } else if s == "BackTab" {
-            Some(KeyCode::BackTab)
-        } else if s == "Delete" {
-            Some(KeyCode::Delete)
-        } else if s == "Insert" {
-            Some(KeyCode::Insert)
-        } else if s == "Esc" {
-            Some(KeyCode::Esc)
-        } else {
-            // TODO: maybe support more keycodes?
-            None
-        }
-    }
-
-    pub fn from_toml(&mut self, toml: Value) {
-        if let Some(v) = toml.get("normal_mode_text") {
-            if let Some(v) = v.as_str() {
-                self.normal_mode_text = v.to_string();
-            }
-        }
-        if let Some(v) = toml.get("search_mode_text") {
-            if let Some(v) = v.as_str() {
-                self.search_mode_text = v.to_string();
-            }
-        }
-        if let Some(v) = toml.get("insert_mode_text") {
-            if let Some(v) = v.as_str() {
-                self.insert_mode_text = v.to_string();
-            }
-        }
-        if let Some(v) = toml.get("show_entry_number") {
-            if let Some(v) = v.as_bool() {
-                self.show_entry_number = v;
-            }
-        }
-        if let Some(v) = toml.get("show_entry_type") {
-            if let Some(v) = v.as_bool() {
-                self.show_entry_type = v;
-            }
-        }
-        if let Some(v) = toml.get("show_working_directory") {
-            if let Some(v) = v.as_bool() {
-                self.show_working_directory = v;
-            }
-        }
-        if let Some(v) = toml.get("simple_working_directory") {
-            if let Some(v) = v.as_bool() {
-                self.simple_working_directory = v;
-            }
-        }
-        if let Some(v) = toml.get("directory_text") {
-            if let Some(v) = v.as_str() {
-                self.directory_text = v.to_string();
-            }
-        }
-        if let Some(v) = toml.get("file_text") {
-            if let Some(v) = v.as_str() {
-                self.file_text = v.to_string();
-            }
-        }
-        if let Some(v) = toml.get("symlink_text") {
-            if let Some(v) = v.as_str() {
-                self.symlink_text = v.to_string();
-            }
-        }
-        if let Some(v) = toml.get("other_text") {
-            if let Some(v) = v.as_str() {
-                self.other_text = v.to_string();
-            }
-        }
-        if let Some(v) = toml.get("new_file") {
-            Self::key_event_from_toml(&mut self.new_file, v)
-        }
-        if let Some(v) = toml.get("new_directory") {
-            Self::key_event_from_toml(&mut self.new_directory, v);
-        }
-        if let Some(v) = toml.get("duplicate") {
-            Self::key_event_from_toml(&mut self.duplicate, v);
-        }
-        if let Some(v) = toml.get("remove") {
-            Self::key_event_from_toml(&mut self.remove, v);
-        }
-        if let Some(v) = toml.get("copy") {
-            Self::key_event_from_toml(&mut self.copy, v);
-        }
-        if let Some(v) = toml.get("incremental_search") {
-            Self::key_event_from_toml(&mut self.paste, v);
-        }
-        if let Some(v) = toml.get("next_search_result") {
-            Self::key_event_from_toml(&mut self.paste, v);
-        }
-        if let Some(v) = toml.get("prev_search_result") {
-            Self::key_event_from_toml(&mut self.paste, v);
-        }
-        if let Some(v) = toml.get("paste") {
-            Self::key_event_from_toml(&mut self.paste, v);
-        }
-        if let Some(v) = toml.get("up") {
-            Self::key_event_from_toml(&mut self.up, v);
-        }
-        if let Some(v) = toml.get("select_up") {
-            Self::key_event_from_toml(&mut self.select_up, v);
-        }
-        if let Some(v) = toml.get("pane_up") {
-            Self::key_event_from_toml(&mut self.pane_up, v);
-        }
-        if let Some(v) = toml.get("split_pane_up") {
-            Self::key_event_from_toml(&mut self.split_pane_up, v);
-        }
-        if let Some(v) = toml.get("down") {
-            Self::key_event_from_toml(&mut self.down, v);
-        }
-        if let Some(v) = toml.get("select_down") {
-            Self::key_event_from_toml(&mut self.select_down, v);
-        }
-        if let Some(v) = toml.get("pane_down") {
-            Self::key_event_from_toml(&mut self.pane_down, v);
-        }
-        if let Some(v) = toml.get("split_pane_down") {
-            Self::key_event_from_toml(&mut self.split_pane_down, v);
-        }
-        if let Some(v) = toml.get("left") {
-            Self::key_event_from_toml(&mut self.left, v);
-        }
-        if let Some(v) = toml.get("pane_left") {
-            Self::key_event_from_toml(&mut self.pane_left, v);
-        }
-        if let Some(v) = toml.get("split_pane_left") {
-            Self::key_event_from_toml(&mut self.split_pane_left, v);
-        }
+            cycle_sort: KeyEvent {
+                code: KeyCode::Char('s'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            reverse_sort: KeyEvent {
+                code: KeyCode::Char('r'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            toggle_directories_first: KeyEvent {
+                code: KeyCode::Char('D'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            filter: KeyEvent {
+                code: KeyCode::Char('f'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            goto: KeyEvent {
+                code: KeyCode::Char('g'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            back: KeyEvent {
+                code: KeyCode::Char('H'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            forward: KeyEvent {
+                code: KeyCode::Char('L'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            page_up: KeyEvent {
+                code: KeyCode::PageUp,
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            page_down: KeyEvent {
+                code: KeyCode::PageDown,
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            half_page_up: KeyEvent {
+                code: KeyCode::Char('u'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            half_page_down: KeyEvent {
+                code: KeyCode::Char('f'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            goto_top: KeyEvent {
+                code: KeyCode::Home,
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            goto_bottom: KeyEvent {
+                code: KeyCode::Char('G'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            open: KeyEvent {
+                code: KeyCode::Char('o'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            shell: KeyEvent {
+                code: KeyCode::Char('!'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            dir_size: KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            chmod: KeyEvent {
+                code: KeyCode::Char('M'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            compress: KeyEvent {
+                code: KeyCode::Char('Z'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            select_all: KeyEvent {
+                code: KeyCode::Char('A'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            invert_selection: KeyEvent {
+                code: KeyCode::Char('I'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            clear_selection: KeyEvent {
+                code: KeyCode::Char('C'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            toggle_select: KeyEvent {
+                code: KeyCode::Char('v'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            bulk_rename: KeyEvent {
+                code: KeyCode::Char('R'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            pattern_rename: KeyEvent {
+                code: KeyCode::Char('E'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            number_rename: KeyEvent {
+                code: KeyCode::Char('#'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            yank_path: KeyEvent {
+                code: KeyCode::Char('y'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            yank_path_relative: KeyEvent {
+                code: KeyCode::Char('Y'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            quit_with_selection: KeyEvent {
+                code: KeyCode::Char('Q'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            toggle_case_insensitive_sort: KeyEvent {
+                code: KeyCode::Char('T'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            swap_pane_up: KeyEvent {
+                code: KeyCode::Char('k'),
+                modifiers: KeyModifiers::CONTROL.union(KeyModifiers::ALT),
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            swap_pane_down: KeyEvent {
+                code: KeyCode::Char('j'),
+                modifiers: KeyModifiers::CONTROL.union(KeyModifiers::ALT),
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            swap_pane_left: KeyEvent {
+                code: KeyCode::Char('h'),
+                modifiers: KeyModifiers::CONTROL.union(KeyModifiers::ALT),
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            swap_pane_right: KeyEvent {
+                code: KeyCode::Char('l'),
+                modifiers: KeyModifiers::CONTROL.union(KeyModifiers::ALT),
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            copy_to_other_pane: KeyEvent {
+                code: KeyCode::F(5),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            move_to_other_pane: KeyEvent {
+                code: KeyCode::F(6),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            zoom_pane: KeyEvent {
+                code: KeyCode::Char('z'),
+                modifiers: KeyModifiers::ALT,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            save_session: KeyEvent {
+                code: KeyCode::Char('W'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            checksum: KeyEvent {
+                code: KeyCode::Char('V'),
+                modifiers: KeyModifiers::SHIFT,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            diff: KeyEvent {
+                code: KeyCode::Char('X'),
+                modifiers: KeyModifiers::SHIFT,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            select_glob: KeyEvent {
+                code: KeyCode::Char('*'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            find: KeyEvent {
+                code: KeyCode::Char('F'),
+                modifiers: KeyModifiers::SHIFT,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            repeat: KeyEvent {
+                code: KeyCode::Char('.'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            cancel: KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            scroll_left: KeyEvent {
+                code: KeyCode::Char('<'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            scroll_right: KeyEvent {
+                code: KeyCode::Char('>'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            error_log: KeyEvent {
+                code: KeyCode::Char('e'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            visual_mode: KeyEvent {
+                code: KeyCode::Char('m'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            quit: KeyEvent {
+                code: KeyCode::Char('q'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            dir_walk: KeyEvent {
+                code: KeyCode::Char(' '),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+            dir_up: KeyEvent {
+                code: KeyCode::Char('x'),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            },
+        }
+    }
+}
+
+impl Config {
+    /// The sort mode and whether it's reversed for `dir`, the first matching sort rule wins.
+    pub fn sort_for(&self, dir: &std::path::Path) -> (SortMode, bool) {
+        for rule in self.sort_rules.iter() {
+            if rule.pattern.matches_path(dir) {
+                return (rule.sort, rule.reverse);
+            }
+        }
+        (self.default_sort, self.sort_reverse)
+    }
+
+    /// The icon shown for an entry when `use_icons` is set.
+    pub fn icon_for(
+        &self,
+        path: &std::path::Path,
+        metadata: Option<&std::fs::Metadata>,
+        is_symlink: bool,
+    ) -> &str {
+        if metadata.is_some_and(|m| m.is_dir()) {
+            &self.directory_icon
+        } else if is_symlink {
+            &self.symlink_icon
+        } else {
+            path.extension()
+                .and_then(|e| self.icons.get(&e.to_string_lossy().to_lowercase()))
+                .unwrap_or(&self.file_icon)
+        }
+    }
+
+    /// The program `path` is opened with if one is configured for its extension.
+    pub fn open_with_for(&self, path: &std::path::Path) -> Option<&OpenWith> {
+        path.extension()
+            .and_then(|e| self.open_with.get(&e.to_string_lossy().to_lowercase()))
+    }
+
+    /// The color of an entry's name, by its type or its extension.
+    pub fn color_for(
+        &self,
+        path: &std::path::Path,
+        metadata: Option<&std::fs::Metadata>,
+        is_symlink: bool,
+    ) -> Color {
+        match metadata {
+            // `metadata` follows symlinks, so it's missing for broken ones
+            None if is_symlink => self.theme.broken_symlink,
+            Some(m) if m.is_dir() => self.theme.directory,
+            _ if is_symlink => self.theme.symlink,
+            Some(m) if crate::window::mode(m).is_some_and(|mode| mode & 0o111 != 0) => {
+                self.theme.executable
+            }
+            _ => path
+                .extension()
+                .and_then(|e| {
+                    self.extension_colors
+                        .get(&e.to_string_lossy().to_lowercase())
+                })
+                .copied()
+                .unwrap_or(self.theme.file),
+        }
+    }
+
+    /// Every configurable action along with the key it is currently bound to, grouped by what
+    /// they're for.
+    pub fn keybindings(&self) -> Vec<(&'static str, Vec<(&'static str, &KeyEvent)>)> {
+        vec![
+            (
+                "Navigation",
+                vec![
+                    ("up", &self.up),
+                    ("down", &self.down),
+                    ("left", &self.left),
+                    ("right", &self.right),
+                    ("scroll_left", &self.scroll_left),
+                    ("scroll_right", &self.scroll_right),
+                    ("page_up", &self.page_up),
+                    ("page_down", &self.page_down),
+                    ("half_page_up", &self.half_page_up),
+                    ("half_page_down", &self.half_page_down),
+                    ("goto_top", &self.goto_top),
+                    ("goto_bottom", &self.goto_bottom),
+                    ("dir_walk", &self.dir_walk),
+                    ("dir_up", &self.dir_up),
+                    ("back", &self.back),
+                    ("forward", &self.forward),
+                    ("goto", &self.goto),
+                    ("jump", &self.jump),
+                    ("incremental_search", &self.incremental_search),
+                    ("find", &self.find),
+                    ("next_search_result", &self.next_search_result),
+                    ("prev_search_result", &self.prev_search_result),
+                    ("filter", &self.filter),
+                ],
+            ),
+            (
+                "File operations",
+                vec![
+                    ("select_up", &self.select_up),
+                    ("select_down", &self.select_down),
+                    ("toggle_select", &self.toggle_select),
+                    ("visual_mode", &self.visual_mode),
+                    ("select_all", &self.select_all),
+                    ("invert_selection", &self.invert_selection),
+                    ("clear_selection", &self.clear_selection),
+                    ("select_glob", &self.select_glob),
+                    ("new_file", &self.new_file),
+                    ("new_directory", &self.new_directory),
+                    ("insert_mode", &self.insert_mode),
+                    ("normal_mode", &self.normal_mode),
+                    ("duplicate", &self.duplicate),
+                    ("copy", &self.copy),
+                    ("cut", &self.cut),
+                    ("paste", &self.paste),
+                    ("copy_to_other_pane", &self.copy_to_other_pane),
+                    ("move_to_other_pane", &self.move_to_other_pane),
+                    ("yank_path", &self.yank_path),
+                    ("yank_path_relative", &self.yank_path_relative),
+                    ("symlink", &self.symlink),
+                    ("remove", &self.remove),
+                    ("trash", &self.trash),
+                    ("remove_permanently", &self.remove_permanently),
+                    ("chmod", &self.chmod),
+                    ("compress", &self.compress),
+                    ("bulk_rename", &self.bulk_rename),
+                    ("pattern_rename", &self.pattern_rename),
+                    ("number_rename", &self.number_rename),
+                    ("repeat", &self.repeat),
+                    ("undo", &self.undo),
+                    ("redo", &self.redo),
+                    ("operation_log", &self.operation_log),
+                    ("error_log", &self.error_log),
+                    ("open", &self.open),
+                    ("checksum", &self.checksum),
+                    ("diff", &self.diff),
+                    ("open_file_manager", &self.open_file_manager),
+                    ("shell", &self.shell),
+                ],
+            ),
+            (
+                "View",
+                vec![
+                    ("cycle_sort", &self.cycle_sort),
+                    ("reverse_sort", &self.reverse_sort),
+                    ("toggle_directories_first", &self.toggle_directories_first),
+                    (
+                        "toggle_case_insensitive_sort",
+                        &self.toggle_case_insensitive_sort,
+                    ),
+                    ("dir_size", &self.dir_size),
+                    ("help", &self.help),
+                    ("quit", &self.quit),
+                    ("quit_with_selection", &self.quit_with_selection),
+                    ("cancel", &self.cancel),
+                ],
+            ),
+            (
+                "Panes",
+                vec![
+                    ("pane_up", &self.pane_up),
+                    ("pane_down", &self.pane_down),
+                    ("pane_left", &self.pane_left),
+                    ("pane_right", &self.pane_right),
+                    ("split_pane_up", &self.split_pane_up),
+                    ("split_pane_down", &self.split_pane_down),
+                    ("split_pane_left", &self.split_pane_left),
+                    ("split_pane_right", &self.split_pane_right),
+                    ("swap_pane_up", &self.swap_pane_up),
+                    ("swap_pane_down", &self.swap_pane_down),
+                    ("swap_pane_left", &self.swap_pane_left),
+                    ("swap_pane_right", &self.swap_pane_right),
+                    ("zoom_pane", &self.zoom_pane),
+                    ("save_session", &self.save_session),
+                    ("close_active_pane", &self.close_active_pane),
+                ],
+            ),
+        ]
+    }
+
+    /// Formats what an action bound to `key_event` is pressed with, both keys if it stands for a
+    /// sequence.
+    pub fn binding_to_string(&self, key_event: &KeyEvent) -> String {
+        match self.key_sequences.iter().find(|s| s.action == *key_event) {
+            Some(sequence) => format!(
+                "{} {}",
+                Self::key_event_to_string(&sequence.keys[0]),
+                Self::key_event_to_string(&sequence.keys[1])
+            ),
+            None => Self::key_event_to_string(key_event),
+        }
+    }
+
+    /// Formats `key_event` the same way keybindings are written in the config file.
+    pub fn key_event_to_string(key_event: &KeyEvent) -> String {
+        let mut res = String::new();
+        if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+            res.push('C');
+        }
+        if key_event.modifiers.contains(KeyModifiers::SHIFT) {
+            res.push('S');
+        }
+        if key_event.modifiers.contains(KeyModifiers::ALT) {
+            res.push('A');
+        }
+        if key_event.modifiers.contains(KeyModifiers::SUPER) {
+            res.push('D');
+        }
+        if key_event.modifiers.contains(KeyModifiers::META) {
+            res.push('M');
+        }
+        if !res.is_empty() {
+            res.push('-');
+        }
+        match key_event.code {
+            KeyCode::Char(' ') => res.push_str("Space"),
+            KeyCode::Char(c) => res.push(c),
+            KeyCode::F(n) => res.push_str(&format!("F{n}")),
+            KeyCode::Media(media) => res.push_str(&format!("Media{media:?}")),
+            code => res.push_str(&format!("{code:?}")),
+        }
+        res
+    }
+
+    /// Accepts color names ("red", "light-blue", "reset" to keep the terminal's color), `#rrggbb`
+    /// and indices into the terminal's 256 color palette.
+    fn color_from_toml(color: &mut Color, toml: &Value) {
+        if let Some(v) = toml.as_str().and_then(|v| v.parse().ok()) {
+            *color = v;
+        }
+    }
+
+    /// Styles are tables like `{ fg = "black", bg = "yellow", modifiers = ["bold"] }`, they replace
+    /// the default style completely.
+    fn style_from_toml(style: &mut Style, toml: &Value) {
+        if let Some(table) = toml.as_table() {
+            let mut res = Style::new();
+            if let Some(v) = table.get("fg") {
+                let mut fg = Color::Reset;
+                Self::color_from_toml(&mut fg, v);
+                res = res.fg(fg);
+            }
+            if let Some(v) = table.get("bg") {
+                let mut bg = Color::Reset;
+                Self::color_from_toml(&mut bg, v);
+                res = res.bg(bg);
+            }
+            if let Some(modifiers) = table.get("modifiers").and_then(|m| m.as_array()) {
+                for modifier in modifiers.iter().filter_map(|m| m.as_str()) {
+                    res = res.add_modifier(match modifier {
+                        "bold" => Modifier::BOLD,
+                        "dim" => Modifier::DIM,
+                        "italic" => Modifier::ITALIC,
+                        "underlined" => Modifier::UNDERLINED,
+                        "reversed" => Modifier::REVERSED,
+                        "crossed_out" => Modifier::CROSSED_OUT,
+                        _ => Modifier::empty(),
+                    });
+                }
+            }
+            *style = res;
+        }
+    }
+
+    fn key_event_from_toml(
+        key_event: &mut KeyEvent,
+        toml: &Value,
+        warnings: &mut Vec<String>,
+        sequences: &mut Vec<KeySequence>,
+    ) {
+        if let Some(v) = toml.as_str() {
+            // a space with a key on both sides separates the keys of a sequence
+            let keys = match v.split_once(' ') {
+                Some((first, second)) if !first.is_empty() && !second.is_empty() => [first, second]
+                    .into_iter()
+                    .map(Self::key_event_from_str)
+                    .collect::<Option<Vec<_>>>(),
+                _ => Self::key_event_from_str(v).map(|key| vec![key]),
+            };
+            let Some(keys) = keys else {
+                warnings.push(format!("Unknown key in keybinding '{v}'"));
+                return;
+            };
+            // the action isn't bound to the sequence it might have had anymore
+            sequences.retain(|s| s.action != *key_event);
+            *key_event = match keys[..] {
+                [first, second] => {
+                    let action = KeySequence::placeholder(sequences);
+                    sequences.push(KeySequence {
+                        keys: [first, second],
+                        action,
+                    });
+                    action
+                }
+                _ => keys[0],
+            };
+        } else {
+            warnings.push(format!("Keybindings have to be strings, found {toml}"));
+        }
+    }
+
+    fn key_event_from_str(v: &str) -> Option<KeyEvent> {
+        // modifiers are separated from the key by the last '-', unless the key itself is '-'
+        let split = match v.strip_suffix('-') {
+            Some("") => None,
+            Some(mod_str) => Some((mod_str.strip_suffix('-').unwrap_or(mod_str), "-")),
+            None => v.rsplit_once('-'),
+        };
+        let (code, modifiers) = {
+            let mut modifiers = KeyModifiers::NONE;
+            (
+                {
+                    if let Some((mod_str, code)) = split {
+                        if mod_str.contains('c') || mod_str.contains('C') {
+                            modifiers = modifiers.union(KeyModifiers::CONTROL);
+                        }
+                        if mod_str.contains('s') || mod_str.contains('S') {
+                            modifiers = modifiers.union(KeyModifiers::SHIFT);
+                        }
+                        if mod_str.contains('a') || mod_str.contains('A') {
+                            modifiers = modifiers.union(KeyModifiers::ALT);
+                        }
+                        if mod_str.contains('d') || mod_str.contains('D') {
+                            modifiers = modifiers.union(KeyModifiers::SUPER);
+                        }
+                        if mod_str.contains('m') || mod_str.contains('M') {
+                            modifiers = modifiers.union(KeyModifiers::META);
+                        }
+                        Self::key_code_from_str(code)
+                    } else {
+                        Self::key_code_from_str(v)
+                    }
+                },
+                modifiers,
+            )
+        };
+        code.map(|code| KeyEvent::new(code, modifiers))
+    }
+
+    /// Media keys are only reported by terminals that support the kitty keyboard protocol.
+    fn media_key_code_from_str(s: &str) -> Option<MediaKeyCode> {
+        match s {
+            "Play" => Some(MediaKeyCode::Play),
+            "Pause" => Some(MediaKeyCode::Pause),
+            "PlayPause" => Some(MediaKeyCode::PlayPause),
+            "Reverse" => Some(MediaKeyCode::Reverse),
+            "Stop" => Some(MediaKeyCode::Stop),
+            "FastForward" => Some(MediaKeyCode::FastForward),
+            "Rewind" => Some(MediaKeyCode::Rewind),
+            "TrackNext" => Some(MediaKeyCode::TrackNext),
+            "TrackPrevious" => Some(MediaKeyCode::TrackPrevious),
+            "Record" => Some(MediaKeyCode::Record),
+            "LowerVolume" => Some(MediaKeyCode::LowerVolume),
+            "RaiseVolume" => Some(MediaKeyCode::RaiseVolume),
+            "MuteVolume" => Some(MediaKeyCode::MuteVolume),
+            _ => None,
+        }
+    }
+
+    fn key_code_from_str(s: &str) -> Option<KeyCode> {
+        if s.chars().count() == 1 {
+            return Some(KeyCode::Char(s.chars().nth(0).unwrap()));
+        }
+
+        if s.starts_with('F')
+            && s.len() > 1
+            && let Ok(n) = s[1..].parse()
+        {
+            return Some(KeyCode::F(n));
+        }
+
+        if s == "Backspace" {
+            Some(KeyCode::Backspace)
+        } else if s == "Enter" {
+            Some(KeyCode::Enter)
+        } else if s == "Left" {
+            Some(KeyCode::Left)
+        } else if s == "Right" {
+            Some(KeyCode::Right)
+        } else if s == "Up" {
+            Some(KeyCode::Up)
+        } else if s == "Down" {
+            Some(KeyCode::Down)
+        } else if s == "Home" {
+            Some(KeyCode::Home)
+        } else if s == "End" {
+            Some(KeyCode::End)
+        } else if s == "PageUp" {
+            Some(KeyCode::PageUp)
+        } else if s == "PageDown" {
+            Some(KeyCode::PageDown)
+        } else if s == "Tab" {
+            Some(KeyCode::Tab)
+        } else if s == "BackTab" {
+            Some(KeyCode::BackTab)
+        } else if s == "Delete" {
+            Some(KeyCode::Delete)
+        } else if s == "Insert" {
+            Some(KeyCode::Insert)
+        } else if s == "Esc" {
+            Some(KeyCode::Esc)
+        } else if s == "Space" {
+            Some(KeyCode::Char(' '))
+        } else if s == "Null" {
+            Some(KeyCode::Null)
+        } else if s == "CapsLock" {
+            Some(KeyCode::CapsLock)
+        } else if s == "ScrollLock" {
+            Some(KeyCode::ScrollLock)
+        } else if s == "NumLock" {
+            Some(KeyCode::NumLock)
+        } else if s == "PrintScreen" {
+            Some(KeyCode::PrintScreen)
+        } else if s == "Pause" {
+            Some(KeyCode::Pause)
+        } else if s == "Menu" {
+            Some(KeyCode::Menu)
+        } else if let Some(media) = s.strip_prefix("Media") {
+            Self::media_key_code_from_str(media).map(KeyCode::Media)
+        } else {
+            None
+        }
+    }
+
+    /// Reads the configuration file at `path`, options it doesn't specify keep their defaults.
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let config_str = std::fs::read_to_string(path)
+            .map_err(|err| format!("Couldn't read '{}': {err}", path.display()))?;
+        let toml = toml::from_str(&config_str)
+            .map_err(|err| format!("Invalid config '{}': {}", path.display(), err.message()))?;
+        let mut config = Self::default();
+        config.apply_toml(toml);
+        Ok(config)
+    }
+
+    /// Sets the options `toml` specifies, the rest are left as they are.
+    pub fn apply_toml(&mut self, toml: Value) {
+        if let Some(v) = toml.get("normal_mode_text")
+            && let Some(v) = v.as_str()
+        {
+            self.normal_mode_text = v.to_string();
+        }
+        if let Some(v) = toml.get("search_mode_text")
+            && let Some(v) = v.as_str()
+        {
+            self.search_mode_text = v.to_string();
+        }
+        if let Some(v) = toml.get("insert_mode_text")
+            && let Some(v) = v.as_str()
+        {
+            self.insert_mode_text = v.to_string();
+        }
+        if let Some(v) = toml.get("visual_mode_text")
+            && let Some(v) = v.as_str()
+        {
+            self.visual_mode_text = v.to_string();
+        }
+        if let Some(v) = toml.get("show_entry_number")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_entry_number = v;
+        }
+        if let Some(v) = toml.get("show_entry_type")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_entry_type = v;
+        }
+        if let Some(v) = toml.get("show_working_directory")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_working_directory = v;
+        }
+        if let Some(v) = toml.get("simple_working_directory")
+            && let Some(v) = v.as_bool()
+        {
+            self.simple_working_directory = v;
+        }
+        if let Some(v) = toml.get("directory_text")
+            && let Some(v) = v.as_str()
+        {
+            self.directory_text = v.to_string();
+        }
+        if let Some(v) = toml.get("file_text")
+            && let Some(v) = v.as_str()
+        {
+            self.file_text = v.to_string();
+        }
+        if let Some(v) = toml.get("symlink_text")
+            && let Some(v) = v.as_str()
+        {
+            self.symlink_text = v.to_string();
+        }
+        if let Some(v) = toml.get("other_text")
+            && let Some(v) = v.as_str()
+        {
+            self.other_text = v.to_string();
+        }
+        if let Some(v) = toml.get("normal_mode_cancels")
+            && let Some(v) = v.as_bool()
+        {
+            self.normal_mode_cancels = v;
+        }
+        if let Some(v) = toml.get("type_conflict")
+            && let Some(v) = v.as_str().and_then(TypeConflictPolicy::from_str)
+        {
+            self.type_conflict = v;
+        }
+        if let Some(v) = toml.get("paste_conflict")
+            && let Some(v) = v.as_str().and_then(PasteConflictPolicy::from_str)
+        {
+            self.paste_conflict = v;
+        }
+        if let Some(v) = toml.get("time_style")
+            && let Some(v) = v.as_str().and_then(TimeStyle::from_str)
+        {
+            self.time_style = v;
+        }
+        if let Some(v) = toml.get("default_sort")
+            && let Some(v) = v.as_str().and_then(SortMode::from_str)
+        {
+            self.default_sort = v;
+        }
+        if let Some(v) = toml.get("show_type_indicator")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_type_indicator = v;
+        }
+        if let Some(v) = toml.get("file_manager_command")
+            && let Some(v) = v.as_str()
+        {
+            self.file_manager_command = v.to_string();
+        }
+        if let Some(v) = toml.get("stop_on_first_error")
+            && let Some(v) = v.as_bool()
+        {
+            self.stop_on_first_error = v;
+        }
+        if let Some(v) = toml.get("sort_rules")
+            && let Some(rules) = v.as_array()
+        {
+            self.sort_rules.clear();
+            for rule in rules.iter() {
+                let pattern = rule.get("pattern").and_then(|p| p.as_str());
+                let sort = rule
+                    .get("sort")
+                    .and_then(|s| s.as_str())
+                    .and_then(SortMode::from_str);
+                if let (Some(pattern), Some(sort)) = (pattern, sort)
+                    && let Ok(pattern) = glob::Pattern::new(&expand_tilde(pattern))
+                {
+                    self.sort_rules.push(SortRule {
+                        pattern,
+                        sort,
+                        reverse: rule
+                            .get("reverse")
+                            .and_then(|r| r.as_bool())
+                            .unwrap_or(false),
+                    });
+                }
+            }
+        }
+        if let Some(v) = toml.get("use_trash_by_default")
+            && let Some(v) = v.as_bool()
+        {
+            self.use_trash_by_default = v;
+        }
+        if let Some(v) = toml.get("confirm_delete")
+            && let Some(v) = v.as_bool()
+        {
+            self.confirm_delete = v;
+        }
+        if let Some(v) = toml.get("sort_reverse")
+            && let Some(v) = v.as_bool()
+        {
+            self.sort_reverse = v;
+        }
+        if let Some(v) = toml.get("directories_first")
+            && let Some(v) = v.as_bool()
+        {
+            self.directories_first = v;
+        }
+        if let Some(v) = toml.get("open_command")
+            && let Some(v) = v.as_str()
+        {
+            self.open_command = v.to_string();
+        }
+        if let Some(v) = toml.get("show_dir_size")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_dir_size = v;
+        }
+        if let Some(v) = toml.get("show_preview")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_preview = v;
+        }
+        if let Some(v) = toml.get("preview_theme")
+            && let Some(v) = v.as_str()
+        {
+            self.preview_theme = v.to_string();
+        }
+        if let Some(v) = toml.get("show_git_status")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_git_status = v;
+        }
+        if let Some(v) = toml.get("show_modified")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_modified = v;
+        }
+        if let Some(v) = toml.get("date_format")
+            && let Some(v) = v.as_str()
+        {
+            // chrono only notices invalid formats while formatting, so they're checked here
+            if chrono::format::StrftimeItems::new(v)
+                .any(|item| matches!(item, chrono::format::Item::Error))
+            {
+                self.warnings.push(format!(
+                    "'{v}' isn't a valid date_format, using '{DEFAULT_DATE_FORMAT}'"
+                ));
+                self.date_format = String::from(DEFAULT_DATE_FORMAT);
+            } else {
+                self.date_format = v.to_string();
+            }
+        }
+        if let Some(v) = toml.get("size_format")
+            && let Some(v) = v.as_str().and_then(SizeFormat::from_str)
+        {
+            self.size_format = v;
+        }
+        if let Some(v) = toml.get("name_truncation")
+            && let Some(v) = v.as_str().and_then(NameTruncation::from_str)
+        {
+            self.name_truncation = v;
+        }
+        if let Some(v) = toml.get("checksum_algorithm")
+            && let Some(v) = v.as_str().and_then(ChecksumAlgorithm::from_str)
+        {
+            self.checksum_algorithm = v;
+        }
+        if let Some(v) = toml.get("show_permissions")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_permissions = v;
+        }
+        if let Some(theme) = toml.get("theme") {
+            for (name, style) in [
+                ("highlight", &mut self.theme.highlight),
+                ("selection", &mut self.theme.selection),
+                ("insert", &mut self.theme.insert),
+                ("error", &mut self.theme.error),
+                ("warning", &mut self.theme.warning),
+            ] {
+                if let Some(v) = theme.get(name) {
+                    Self::style_from_toml(style, v);
+                }
+            }
+            for (name, color) in [
+                ("directory", &mut self.theme.directory),
+                ("file", &mut self.theme.file),
+                ("symlink", &mut self.theme.symlink),
+                ("executable", &mut self.theme.executable),
+                ("broken_symlink", &mut self.theme.broken_symlink),
+                ("border", &mut self.theme.border),
+            ] {
+                if let Some(v) = theme.get(name) {
+                    Self::color_from_toml(color, v);
+                }
+            }
+        }
+        if let Some(v) = toml.get("extension_colors")
+            && let Some(colors) = v.as_table()
+        {
+            for (extension, v) in colors.iter() {
+                let mut color = Color::Reset;
+                Self::color_from_toml(&mut color, v);
+                self.extension_colors
+                    .insert(extension.to_lowercase(), color);
+            }
+        }
+        if let Some(v) = toml.get("open_with")
+            && let Some(programs) = v.as_table()
+        {
+            for (extension, v) in programs.iter() {
+                // either just the command or a table that can also ask for the terminal
+                let open_with = match v {
+                    Value::String(command) => Some(OpenWith {
+                        command: command.to_string(),
+                        suspend: false,
+                    }),
+                    Value::Table(table) => {
+                        table
+                            .get("command")
+                            .and_then(|v| v.as_str())
+                            .map(|command| OpenWith {
+                                command: command.to_string(),
+                                suspend: table
+                                    .get("suspend")
+                                    .and_then(|v| v.as_bool())
+                                    .unwrap_or(false),
+                            })
+                    }
+                    _ => None,
+                };
+                match open_with {
+                    Some(open_with) => {
+                        self.open_with.insert(extension.to_lowercase(), open_with);
+                    }
+                    None => self.warnings.push(format!(
+                        "open_with for '{extension}' has no command, it's ignored"
+                    )),
+                }
+            }
+        }
+        if let Some(v) = toml.get("icons")
+            && let Some(icons) = v.as_table()
+        {
+            for (extension, icon) in icons.iter() {
+                if let Some(icon) = icon.as_str() {
+                    self.icons
+                        .insert(extension.to_lowercase(), icon.to_string());
+                }
+            }
+        }
+        if let Some(v) = toml.get("use_icons")
+            && let Some(v) = v.as_bool()
+        {
+            self.use_icons = v;
+        }
+        if let Some(v) = toml.get("file_icon")
+            && let Some(v) = v.as_str()
+        {
+            self.file_icon = v.to_string();
+        }
+        if let Some(v) = toml.get("directory_icon")
+            && let Some(v) = v.as_str()
+        {
+            self.directory_icon = v.to_string();
+        }
+        if let Some(v) = toml.get("symlink_icon")
+            && let Some(v) = v.as_str()
+        {
+            self.symlink_icon = v.to_string();
+        }
+        if let Some(v) = toml.get("colorize_entries")
+            && let Some(v) = v.as_bool()
+        {
+            self.colorize_entries = v;
+        }
+        if let Some(v) = toml.get("pattern_rename_regex")
+            && let Some(v) = v.as_bool()
+        {
+            self.pattern_rename_regex = v;
+        }
+        if let Some(v) = toml.get("inline_new_entries")
+            && let Some(v) = v.as_bool()
+        {
+            self.inline_new_entries = v;
+        }
+        if let Some(v) = toml.get("rename_select_stem")
+            && let Some(v) = v.as_bool()
+        {
+            self.rename_select_stem = v;
+        }
+        if let Some(v) = toml.get("highlight_symbol")
+            && let Some(v) = v.as_str()
+        {
+            self.highlight_symbol = v.to_string();
+        }
+        if let Some(v) = toml.get("show_status_bar")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_status_bar = v;
+        }
+        if let Some(v) = toml.get("show_disk_usage")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_disk_usage = v;
+        }
+        if let Some(v) = toml.get("show_scrollbar")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_scrollbar = v;
+        }
+        if let Some(v) = toml.get("natural_sort")
+            && let Some(v) = v.as_bool()
+        {
+            self.natural_sort = v;
+        }
+        if let Some(v) = toml.get("case_insensitive_sort")
+            && let Some(v) = v.as_bool()
+        {
+            self.case_insensitive_sort = v;
+        }
+        if let Some(v) = toml.get("show_symlink_target")
+            && let Some(v) = v.as_bool()
+        {
+            self.show_symlink_target = v;
+        }
+        if let Some(v) = toml.get("follow_symlinks")
+            && let Some(v) = v.as_bool()
+        {
+            self.follow_symlinks = v;
+        }
+        if let Some(v) = toml.get("disallowed_characters")
+            && let Some(v) = v.as_str()
+        {
+            self.disallowed_characters = v.to_string();
+        }
+        if let Some(v) = toml.get("use_reflink")
+            && let Some(v) = v.as_bool()
+        {
+            self.use_reflink = v;
+        }
+        if let Some(v) = toml.get("min_pane_width")
+            && let Some(v) = v.as_integer().and_then(|v| u16::try_from(v).ok())
+        {
+            self.min_pane_width = v;
+        }
+        if let Some(v) = toml.get("min_pane_height")
+            && let Some(v) = v.as_integer().and_then(|v| u16::try_from(v).ok())
+        {
+            self.min_pane_height = v;
+        }
+        if let Some(v) = toml.get("restore_last_dir")
+            && let Some(v) = v.as_bool()
+        {
+            self.restore_last_dir = v;
+        }
+        if let Some(v) = toml.get("copy_checksum")
+            && let Some(v) = v.as_bool()
+        {
+            self.copy_checksum = v;
+        }
+        if let Some(v) = toml.get("diff_command")
+            && let Some(v) = v.as_str()
+        {
+            self.diff_command = v.to_string();
+        }
+        if let Some(v) = toml.get("find_max_depth")
+            && let Some(v) = v.as_integer().and_then(|v| u16::try_from(v).ok())
+        {
+            self.find_max_depth = v;
+        }
+        if let Some(v) = toml.get("new_file_name")
+            && let Some(v) = v.as_str()
+        {
+            self.new_file_name = v.to_string();
+        }
+        if let Some(v) = toml.get("new_dir_name")
+            && let Some(v) = v.as_str()
+        {
+            self.new_dir_name = v.to_string();
+        }
+        if let Some(v) = toml.get("collision_suffix")
+            && let Some(v) = v.as_str()
+        {
+            if v.contains(std::path::is_separator) {
+                self.warnings.push(format!(
+                        "collision_suffix '{v}' can't contain path separators, using '{DEFAULT_COLLISION_SUFFIX}'"
+                    ));
+            } else {
+                self.collision_suffix = v.to_string();
+            }
+        }
+        if let Some(v) = toml.get("collision_before_extension")
+            && let Some(v) = v.as_bool()
+        {
+            self.collision_before_extension = v;
+        }
+        let mut bind = |key_event: &mut KeyEvent, v: &Value| {
+            Self::key_event_from_toml(key_event, v, &mut self.warnings, &mut self.key_sequences)
+        };
+        if let Some(v) = toml.get("new_file") {
+            bind(&mut self.new_file, v)
+        }
+        if let Some(v) = toml.get("new_directory") {
+            bind(&mut self.new_directory, v);
+        }
+        if let Some(v) = toml.get("duplicate") {
+            bind(&mut self.duplicate, v);
+        }
+        if let Some(v) = toml.get("remove") {
+            bind(&mut self.remove, v);
+        }
+        if let Some(v) = toml.get("copy") {
+            bind(&mut self.copy, v);
+        }
+        if let Some(v) = toml.get("incremental_search") {
+            bind(&mut self.incremental_search, v);
+        }
+        if let Some(v) = toml.get("next_search_result") {
+            bind(&mut self.next_search_result, v);
+        }
+        if let Some(v) = toml.get("prev_search_result") {
+            bind(&mut self.prev_search_result, v);
+        }
+        if let Some(v) = toml.get("paste") {
+            bind(&mut self.paste, v);
+        }
+        if let Some(v) = toml.get("up") {
+            bind(&mut self.up, v);
+        }
+        if let Some(v) = toml.get("select_up") {
+            bind(&mut self.select_up, v);
+        }
+        if let Some(v) = toml.get("pane_up") {
+            bind(&mut self.pane_up, v);
+        }
+        if let Some(v) = toml.get("split_pane_up") {
+            bind(&mut self.split_pane_up, v);
+        }
+        if let Some(v) = toml.get("down") {
+            bind(&mut self.down, v);
+        }
+        if let Some(v) = toml.get("select_down") {
+            bind(&mut self.select_down, v);
+        }
+        if let Some(v) = toml.get("pane_down") {
+            bind(&mut self.pane_down, v);
+        }
+        if let Some(v) = toml.get("split_pane_down") {
+            bind(&mut self.split_pane_down, v);
+        }
+        if let Some(v) = toml.get("left") {
+            bind(&mut self.left, v);
+        }
+        if let Some(v) = toml.get("pane_left") {
+            bind(&mut self.pane_left, v);
+        }
+        if let Some(v) = toml.get("split_pane_left") {
+            bind(&mut self.split_pane_left, v);
+        }
         if let Some(v) = toml.get("right") {
-            Self::key_event_from_toml(&mut self.right, v);
+            bind(&mut self.right, v);
         }
         if let Some(v) = toml.get("pane_right") {
-            Self::key_event_from_toml(&mut self.pane_right, v);
+            bind(&mut self.pane_right, v);
         }
         if let Some(v) = toml.get("split_pane_right") {
-            Self::key_event_from_toml(&mut self.split_pane_right, v);
+            bind(&mut self.split_pane_right, v);
         }
         if let Some(v) = toml.get("dir_walk") {
-            Self::key_event_from_toml(&mut self.dir_walk, v);
+            bind(&mut self.dir_walk, v);
         }
         if let Some(v) = toml.get("dir_up") {
-            Self::key_event_from_toml(&mut self.dir_up, v);
+            bind(&mut self.dir_up, v);
         }
         if let Some(v) = toml.get("insert_mode") {
-            Self::key_event_from_toml(&mut self.insert_mode, v);
+            bind(&mut self.insert_mode, v);
         }
         if let Some(v) = toml.get("normal_mode") {
-            Self::key_event_from_toml(&mut self.normal_mode, v);
+            bind(&mut self.normal_mode, v);
         }
         if let Some(v) = toml.get("close_active_pane") {
-            Self::key_event_from_toml(&mut self.close_active_pane, v);
+            bind(&mut self.close_active_pane, v);
         }
         if let Some(v) = toml.get("quit") {
-            Self::key_event_from_toml(&mut self.quit, v);
+            bind(&mut self.quit, v);
+        }
+        if let Some(v) = toml.get("undo") {
+            bind(&mut self.undo, v);
+        }
+        if let Some(v) = toml.get("redo") {
+            bind(&mut self.redo, v);
+        }
+        if let Some(v) = toml.get("operation_log") {
+            bind(&mut self.operation_log, v);
+        }
+        if let Some(v) = toml.get("help") {
+            bind(&mut self.help, v);
+        }
+        if let Some(v) = toml.get("jump") {
+            bind(&mut self.jump, v);
+        }
+        if let Some(v) = toml.get("open_file_manager") {
+            bind(&mut self.open_file_manager, v);
+        }
+        if let Some(v) = toml.get("cut") {
+            bind(&mut self.cut, v);
+        }
+        if let Some(v) = toml.get("trash") {
+            bind(&mut self.trash, v);
+        }
+        if let Some(v) = toml.get("remove_permanently") {
+            bind(&mut self.remove_permanently, v);
+        }
+        if let Some(v) = toml.get("symlink") {
+            bind(&mut self.symlink, v);
+        }
+        if let Some(v) = toml.get("cycle_sort") {
+            bind(&mut self.cycle_sort, v);
+        }
+        if let Some(v) = toml.get("reverse_sort") {
+            bind(&mut self.reverse_sort, v);
+        }
+        if let Some(v) = toml.get("toggle_directories_first") {
+            bind(&mut self.toggle_directories_first, v);
+        }
+        if let Some(v) = toml.get("filter") {
+            bind(&mut self.filter, v);
+        }
+        if let Some(v) = toml.get("goto") {
+            bind(&mut self.goto, v);
+        }
+        if let Some(v) = toml.get("back") {
+            bind(&mut self.back, v);
+        }
+        if let Some(v) = toml.get("forward") {
+            bind(&mut self.forward, v);
+        }
+        if let Some(v) = toml.get("page_up") {
+            bind(&mut self.page_up, v);
+        }
+        if let Some(v) = toml.get("page_down") {
+            bind(&mut self.page_down, v);
+        }
+        if let Some(v) = toml.get("half_page_up") {
+            bind(&mut self.half_page_up, v);
+        }
+        if let Some(v) = toml.get("half_page_down") {
+            bind(&mut self.half_page_down, v);
+        }
+        if let Some(v) = toml.get("goto_top") {
+            bind(&mut self.goto_top, v);
+        }
+        if let Some(v) = toml.get("goto_bottom") {
+            bind(&mut self.goto_bottom, v);
+        }
+        if let Some(v) = toml.get("open") {
+            bind(&mut self.open, v);
+        }
+        if let Some(v) = toml.get("shell") {
+            bind(&mut self.shell, v);
+        }
+        if let Some(v) = toml.get("dir_size") {
+            bind(&mut self.dir_size, v);
+        }
+        if let Some(v) = toml.get("chmod") {
+            bind(&mut self.chmod, v);
+        }
+        if let Some(v) = toml.get("compress") {
+            bind(&mut self.compress, v);
+        }
+        if let Some(v) = toml.get("select_all") {
+            bind(&mut self.select_all, v);
+        }
+        if let Some(v) = toml.get("invert_selection") {
+            bind(&mut self.invert_selection, v);
+        }
+        if let Some(v) = toml.get("clear_selection") {
+            bind(&mut self.clear_selection, v);
+        }
+        if let Some(v) = toml.get("toggle_select") {
+            bind(&mut self.toggle_select, v);
+        }
+        if let Some(v) = toml.get("bulk_rename") {
+            bind(&mut self.bulk_rename, v);
+        }
+        if let Some(v) = toml.get("pattern_rename") {
+            bind(&mut self.pattern_rename, v);
+        }
+        if let Some(v) = toml.get("number_rename") {
+            bind(&mut self.number_rename, v);
+        }
+        if let Some(v) = toml.get("yank_path") {
+            bind(&mut self.yank_path, v);
+        }
+        if let Some(v) = toml.get("yank_path_relative") {
+            bind(&mut self.yank_path_relative, v);
+        }
+        if let Some(v) = toml.get("quit_with_selection") {
+            bind(&mut self.quit_with_selection, v);
+        }
+        if let Some(v) = toml.get("toggle_case_insensitive_sort") {
+            bind(&mut self.toggle_case_insensitive_sort, v);
+        }
+        if let Some(v) = toml.get("swap_pane_up") {
+            bind(&mut self.swap_pane_up, v);
+        }
+        if let Some(v) = toml.get("swap_pane_down") {
+            bind(&mut self.swap_pane_down, v);
+        }
+        if let Some(v) = toml.get("swap_pane_left") {
+            bind(&mut self.swap_pane_left, v);
+        }
+        if let Some(v) = toml.get("swap_pane_right") {
+            bind(&mut self.swap_pane_right, v);
+        }
+        if let Some(v) = toml.get("copy_to_other_pane") {
+            bind(&mut self.copy_to_other_pane, v);
+        }
+        if let Some(v) = toml.get("move_to_other_pane") {
+            bind(&mut self.move_to_other_pane, v);
+        }
+        if let Some(v) = toml.get("zoom_pane") {
+            bind(&mut self.zoom_pane, v);
+        }
+        if let Some(v) = toml.get("save_session") {
+            bind(&mut self.save_session, v);
+        }
+        if let Some(v) = toml.get("checksum") {
+            bind(&mut self.checksum, v);
+        }
+        if let Some(v) = toml.get("diff") {
+            bind(&mut self.diff, v);
+        }
+        if let Some(v) = toml.get("select_glob") {
+            bind(&mut self.select_glob, v);
+        }
+        if let Some(v) = toml.get("find") {
+            bind(&mut self.find, v);
+        }
+        if let Some(v) = toml.get("repeat") {
+            bind(&mut self.repeat, v);
+        }
+        if let Some(v) = toml.get("cancel") {
+            bind(&mut self.cancel, v);
+        }
+        if let Some(v) = toml.get("scroll_left") {
+            bind(&mut self.scroll_left, v);
+        }
+        if let Some(v) = toml.get("scroll_right") {
+            bind(&mut self.scroll_right, v);
+        }
+        if let Some(v) = toml.get("error_log") {
+            bind(&mut self.error_log, v);
+        }
+        if let Some(v) = toml.get("visual_mode") {
+            bind(&mut self.visual_mode, v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expanded(path: &str) -> String {
+        expand_path(path).unwrap()
+    }
+
+    #[test]
+    fn tilde_is_expanded_to_the_home_directory() {
+        let home = dirs::home_dir().unwrap().to_string_lossy().to_string();
+        assert_eq!(expanded("~"), home);
+        assert_eq!(expanded("~/a/b"), format!("{home}/a/b"));
+        // only a leading tilde is expanded
+        assert_eq!(expanded("a/~/b"), "a/~/b");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn tilde_user_is_expanded_to_their_home_directory() {
+        assert_eq!(expanded("~root/a"), "/root/a");
+        assert!(expand_path("~no-such-user-here/a").is_err());
+    }
+
+    #[test]
+    fn variables_are_expanded() {
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(expanded("$PATH"), path);
+        assert_eq!(expanded("a/${PATH}/b"), format!("a/{path}/b"));
+        assert_eq!(expanded("$PATH.x"), format!("{path}.x"));
+        assert!(expand_path("$WALKED_TEST_UNSET_VARIABLE").is_err());
+        assert!(expand_path("${PATH").is_err());
+    }
+
+    #[test]
+    fn lone_dollars_and_percents_are_kept() {
+        assert_eq!(expanded("a$"), "a$");
+        assert_eq!(expanded("a$/b"), "a$/b");
+        assert_eq!(expanded("100%"), "100%");
+        if !cfg!(windows) {
+            assert_eq!(expanded("%PATH%"), "%PATH%");
+        }
+    }
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Option<KeyEvent> {
+        Some(KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn modifiers_are_parsed() {
+        let parse = Config::key_event_from_str;
+        assert_eq!(parse("x"), key(KeyCode::Char('x'), KeyModifiers::NONE));
+        assert_eq!(parse("C-x"), key(KeyCode::Char('x'), KeyModifiers::CONTROL));
+        assert_eq!(parse("A-x"), key(KeyCode::Char('x'), KeyModifiers::ALT));
+        assert_eq!(parse("a-x"), key(KeyCode::Char('x'), KeyModifiers::ALT));
+        assert_eq!(
+            parse("CA-h"),
+            key(
+                KeyCode::Char('h'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+        assert_eq!(
+            parse("CS-c"),
+            key(
+                KeyCode::Char('c'),
+                KeyModifiers::CONTROL | KeyModifiers::SHIFT
+            )
+        );
+        assert_eq!(
+            parse("c-a-x"),
+            key(
+                KeyCode::Char('x'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+        assert_eq!(parse("s-F5"), key(KeyCode::F(5), KeyModifiers::SHIFT));
+        assert_eq!(parse("a-Enter"), key(KeyCode::Enter, KeyModifiers::ALT));
+        // shift doesn't imply alt
+        assert_eq!(parse("S-x"), key(KeyCode::Char('x'), KeyModifiers::SHIFT));
+    }
+
+    #[test]
+    fn minus_can_be_bound() {
+        let parse = Config::key_event_from_str;
+        assert_eq!(parse("-"), key(KeyCode::Char('-'), KeyModifiers::NONE));
+        assert_eq!(parse("C--"), key(KeyCode::Char('-'), KeyModifiers::CONTROL));
+        assert_eq!(parse("A--"), key(KeyCode::Char('-'), KeyModifiers::ALT));
+    }
+
+    #[test]
+    fn named_keys_are_parsed() {
+        let parse = Config::key_event_from_str;
+        for (name, code) in [
+            ("Space", KeyCode::Char(' ')),
+            ("Null", KeyCode::Null),
+            ("Backspace", KeyCode::Backspace),
+            ("Enter", KeyCode::Enter),
+            ("Left", KeyCode::Left),
+            ("Right", KeyCode::Right),
+            ("Up", KeyCode::Up),
+            ("Down", KeyCode::Down),
+            ("Home", KeyCode::Home),
+            ("End", KeyCode::End),
+            ("PageUp", KeyCode::PageUp),
+            ("PageDown", KeyCode::PageDown),
+            ("Tab", KeyCode::Tab),
+            ("BackTab", KeyCode::BackTab),
+            ("Delete", KeyCode::Delete),
+            ("Insert", KeyCode::Insert),
+            ("Esc", KeyCode::Esc),
+            ("CapsLock", KeyCode::CapsLock),
+            ("ScrollLock", KeyCode::ScrollLock),
+            ("NumLock", KeyCode::NumLock),
+            ("PrintScreen", KeyCode::PrintScreen),
+            ("Pause", KeyCode::Pause),
+            ("Menu", KeyCode::Menu),
+            ("F1", KeyCode::F(1)),
+            ("F12", KeyCode::F(12)),
+            ("MediaPlayPause", KeyCode::Media(MediaKeyCode::PlayPause)),
+            ("MediaMuteVolume", KeyCode::Media(MediaKeyCode::MuteVolume)),
+        ] {
+            assert_eq!(parse(name), key(code, KeyModifiers::NONE), "{name}");
+        }
+    }
+
+    #[test]
+    fn super_and_meta_are_parsed() {
+        let parse = Config::key_event_from_str;
+        assert_eq!(parse("D-x"), key(KeyCode::Char('x'), KeyModifiers::SUPER));
+        assert_eq!(parse("M-x"), key(KeyCode::Char('x'), KeyModifiers::META));
+        assert_eq!(
+            parse("CD-Space"),
+            key(
+                KeyCode::Char(' '),
+                KeyModifiers::CONTROL | KeyModifiers::SUPER
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let parse = Config::key_event_from_str;
+        for name in ["", "Foo", "C-Foo", "Fx", "MediaFoo", "space"] {
+            assert_eq!(parse(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn unknown_keys_are_warned_about() {
+        let mut config = Config::default();
+        let quit = config.quit;
+        config.apply_toml(toml::from_str("quit = \"C-Foo\"\nundo = 1").unwrap());
+        assert_eq!(config.quit, quit);
+        assert_eq!(
+            config.warnings,
+            [
+                "Unknown key in keybinding 'C-Foo'",
+                "Keybindings have to be strings, found 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn sequences_are_parsed() {
+        let mut config = Config::default();
+        config.apply_toml(
+            toml::from_str("goto_bottom = \"g e\"\ntoggle_directories_first = \"z Space\"")
+                .unwrap(),
+        );
+        assert!(config.warnings.is_empty());
+        let keys = |action: KeyEvent| {
+            let sequence = config.key_sequences.iter().find(|s| s.action == action);
+            sequence.map(|s| s.keys.map(|key| key.code))
+        };
+        assert_eq!(config.key_sequences.len(), 2);
+        assert_eq!(
+            keys(config.goto_bottom),
+            Some([KeyCode::Char('g'), KeyCode::Char('e')])
+        );
+        assert_eq!(
+            keys(config.toggle_directories_first),
+            Some([KeyCode::Char('z'), KeyCode::Char(' ')])
+        );
+        assert_ne!(config.goto_bottom, config.toggle_directories_first);
+    }
+
+    #[test]
+    fn rebinding_an_action_drops_its_sequence() {
+        let mut config = Config::default();
+        config.apply_toml(toml::from_str("goto_bottom = \"g e\"").unwrap());
+        config.apply_toml(toml::from_str("goto_bottom = \"End\"").unwrap());
+        assert!(config.key_sequences.is_empty());
+        assert_eq!(config.goto_bottom.code, KeyCode::End);
+    }
+
+    #[test]
+    fn invalid_sequences_are_warned_about() {
+        let mut config = Config::default();
+        let goto_bottom = config.goto_bottom;
+        for sequence in ["g nope", "g e x", "g space"] {
+            config.apply_toml(toml::Value::Table(toml::Table::from_iter([(
+                "goto_bottom".to_string(),
+                toml::Value::from(sequence),
+            )])));
         }
+        assert_eq!(config.goto_bottom, goto_bottom);
+        assert!(config.key_sequences.is_empty());
+        assert_eq!(
+            config.warnings,
+            [
+                "Unknown key in keybinding 'g nope'",
+                "Unknown key in keybinding 'g e x'",
+                "Unknown key in keybinding 'g space'"
+            ]
+        );
     }
 }
@@ -0,0 +1,70 @@
+use std::{
+    fs::{Metadata, ReadDir},
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, TryRecvError, channel},
+    },
+};
+
+/// Directories with more entries than this are read in the background, in batches of this size.
+pub const READ_BATCH: usize = 2048;
+
+/// A path with its metadata (following symlinks, so it's missing for broken ones) and where it
+/// points if it's a symlink. Both are read once so drawing doesn't have to touch the filesystem.
+pub type Entry = (PathBuf, Option<Metadata>, Option<PathBuf>);
+
+pub fn read_entry(path: PathBuf) -> Entry {
+    let metadata = std::fs::metadata(&path).ok();
+    let link_target = std::fs::read_link(&path).ok();
+    (path, metadata, link_target)
+}
+
+/// Reads the rest of a huge directory on a background thread, so the entries that were already
+/// read can be used in the meantime. Dropping it stops the thread.
+pub struct DirReader {
+    batches: Receiver<Vec<Entry>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl DirReader {
+    pub fn start(dir: ReadDir) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, batches) = channel();
+        let worker_cancel = cancel.clone();
+        std::thread::spawn(move || {
+            let mut batch = Vec::with_capacity(READ_BATCH);
+            for entry in dir.flatten() {
+                if worker_cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                batch.push(read_entry(entry.path()));
+                if batch.len() == READ_BATCH && tx.send(std::mem::take(&mut batch)).is_err() {
+                    return;
+                }
+            }
+            let _ = tx.send(batch);
+        });
+        Self { batches, cancel }
+    }
+
+    /// Returns the entries that were read since the last call, and whether the whole directory
+    /// was read.
+    pub fn receive(&self) -> (Vec<Entry>, bool) {
+        let mut entries = Vec::new();
+        loop {
+            match self.batches.try_recv() {
+                Ok(batch) => entries.extend(batch),
+                Err(TryRecvError::Empty) => return (entries, false),
+                Err(TryRecvError::Disconnected) => return (entries, true),
+            }
+        }
+    }
+}
+
+impl Drop for DirReader {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, Sender, channel},
+};
+
+/// Recursive sizes of directories, computed one at a time on a background thread since walking
+/// a big tree can take a long while.
+pub struct DirSizes {
+    /// `None` while the size of the directory is still being computed.
+    sizes: HashMap<PathBuf, Option<u64>>,
+    jobs: Sender<PathBuf>,
+    results: Receiver<(PathBuf, u64)>,
+}
+
+impl Default for DirSizes {
+    fn default() -> Self {
+        let (jobs, job_receiver) = channel::<PathBuf>();
+        let (result_sender, results) = channel();
+        // the thread exits once `jobs` is dropped along with the panel
+        std::thread::spawn(move || {
+            for path in job_receiver {
+                let size = dir_size(&path);
+                if result_sender.send((path, size)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            sizes: HashMap::new(),
+            jobs,
+            results,
+        }
+    }
+}
+
+impl DirSizes {
+    /// `Some(None)` means the size is being computed.
+    pub fn get(&self, path: &Path) -> Option<Option<u64>> {
+        self.sizes.get(path).copied()
+    }
+
+    /// Starts computing the size of `path` unless it is already known or being computed.
+    pub fn request(&mut self, path: &Path) {
+        if !self.sizes.contains_key(path) {
+            self.compute(path);
+        }
+    }
+
+    /// Computes the size of `path` again, even if it is already known.
+    pub fn compute(&mut self, path: &Path) {
+        if self.sizes.get(path).is_some_and(Option::is_none) {
+            return;
+        }
+        if self.jobs.send(path.to_path_buf()).is_ok() {
+            self.sizes.insert(path.to_path_buf(), None);
+        }
+    }
+
+    /// Stores the sizes that were computed since the last call, returns whether there were any.
+    pub fn receive(&mut self) -> bool {
+        let mut received = false;
+        while let Ok((path, size)) = self.results.try_recv() {
+            self.sizes.insert(path, Some(size));
+            received = true;
+        }
+        received
+    }
+}
+
+/// Sums the sizes of everything inside `path`.
+/// `path` itself may be a symlink, but symlinks inside it aren't followed and directories that
+/// were already visited (through bind mounts, for example) aren't counted again, so this always
+/// terminates.
+fn dir_size(path: &Path) -> u64 {
+    let mut visited = HashSet::new();
+    let mut size = 0;
+    let mut stack = vec![path.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(metadata) = dir.metadata() else {
+            continue;
+        };
+        if !visited.insert(dir_id(&dir, &metadata)) {
+            continue;
+        }
+        let Ok(read_dir) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in read_dir.flatten() {
+            let Ok(metadata) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                stack.push(entry.path());
+            } else {
+                size += metadata.len();
+            }
+        }
+    }
+    size
+}
+
+#[cfg(unix)]
+fn dir_id(_: &Path, metadata: &std::fs::Metadata) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.dev(), metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn dir_id(path: &Path, _: &std::fs::Metadata) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
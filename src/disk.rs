@@ -0,0 +1,49 @@
+use std::path::{Path, PathBuf};
+use sysinfo::Disks;
+
+/// Space on the filesystem a directory is on.
+pub struct DiskUsage {
+    pub available: u64,
+    pub total: u64,
+}
+
+impl DiskUsage {
+    /// Looks up the filesystem mounted closest to `dir`, returns `None` if there is none or it
+    /// doesn't report its size.
+    pub fn of(dir: &Path) -> Option<Self> {
+        let dir = dir.canonicalize().ok()?;
+        let disks = Disks::new_with_refreshed_list();
+        disks
+            .list()
+            .iter()
+            .filter(|disk| dir.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len())
+            .filter(|disk| disk.total_space() > 0)
+            .map(|disk| Self {
+                available: disk.available_space(),
+                total: disk.total_space(),
+            })
+    }
+
+    pub fn used_percent(&self) -> u64 {
+        self.total.saturating_sub(self.available) * 100 / self.total
+    }
+}
+
+/// The disk usage of the last directory it was looked up for, so it isn't looked up every frame.
+#[derive(Default)]
+pub struct DiskUsageCache {
+    dir: PathBuf,
+    usage: Option<DiskUsage>,
+}
+
+impl DiskUsageCache {
+    /// Looks up the disk usage again if `dir` isn't the directory it was looked up for last.
+    pub fn get(&mut self, dir: &Path) -> Option<&DiskUsage> {
+        if self.dir != dir {
+            self.dir = dir.to_path_buf();
+            self.usage = DiskUsage::of(dir);
+        }
+        self.usage.as_ref()
+    }
+}
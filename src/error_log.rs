@@ -0,0 +1,50 @@
+use crate::WalkedError;
+use std::{collections::VecDeque, time::SystemTime};
+
+/// Only this many of the latest messages are kept.
+pub const MAX_ENTRIES: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    /// Something that didn't stop anything from working, like an invalid config value that was
+    /// ignored.
+    Warning,
+}
+
+pub struct LoggedError {
+    pub time: SystemTime,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Every error and warning shown in a pane's title, kept so they can be read again after the
+/// title was cleared.
+#[derive(Default)]
+pub struct ErrorLog {
+    entries: VecDeque<LoggedError>,
+    /// How many of the entries were added since the log was last looked at.
+    pub unread: usize,
+}
+
+impl ErrorLog {
+    pub fn push(&mut self, err: &WalkedError) {
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(LoggedError {
+            time: SystemTime::now(),
+            severity: match err {
+                WalkedError::Warning(_) => Severity::Warning,
+                _ => Severity::Error,
+            },
+            message: err.to_string(),
+        });
+        self.unread = (self.unread + 1).min(MAX_ENTRIES);
+    }
+
+    /// The entries from oldest to newest.
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &LoggedError> + ExactSizeIterator {
+        self.entries.iter()
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, Sender, TryRecvError, channel},
+    },
+};
+
+/// The search stops after this many results, a pattern matching nearly everything isn't useful
+/// anyway.
+const MAX_RESULTS: usize = 10_000;
+
+/// What the paths found are matched against.
+enum Matcher {
+    /// Patterns with glob characters in them are matched as globs.
+    Glob(glob::Pattern),
+    /// Anything else is looked for in the paths, ignoring case.
+    Substring(String),
+}
+
+impl Matcher {
+    fn new(pattern: &str) -> Self {
+        if pattern.contains(['*', '?', '['])
+            && let Ok(glob) = glob::Pattern::new(pattern)
+        {
+            return Matcher::Glob(glob);
+        }
+        Matcher::Substring(pattern.to_lowercase())
+    }
+
+    /// `path` is relative to the directory being searched.
+    fn matches(&self, path: &Path) -> bool {
+        match self {
+            Matcher::Glob(glob) => glob.matches_path(path),
+            Matcher::Substring(s) => path.to_string_lossy().to_lowercase().contains(s),
+        }
+    }
+}
+
+/// Entries below `root` whose paths match a pattern, found on a worker thread and taken in as
+/// they come.
+pub struct Find {
+    pub root: PathBuf,
+    pub pattern: String,
+    pub results: Vec<PathBuf>,
+    /// The result under the cursor.
+    pub cursor: usize,
+    /// The whole tree was searched (or enough results were found).
+    pub done: bool,
+    /// The results are directories to go into, rather than entries to show in their directory.
+    pub directories: bool,
+    cancel: Arc<AtomicBool>,
+    updates: Receiver<Vec<PathBuf>>,
+}
+
+impl Find {
+    /// Starts searching `root`, directories deeper than `max_depth` below it aren't entered.
+    pub fn start(root: PathBuf, pattern: &str, max_depth: usize) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, updates) = channel();
+        let worker_cancel = cancel.clone();
+        let worker_root = root.clone();
+        let matcher = Matcher::new(pattern);
+        std::thread::spawn(move || work(&worker_root, &matcher, max_depth, &worker_cancel, &tx));
+        Self {
+            root,
+            pattern: pattern.to_string(),
+            results: Vec::new(),
+            cursor: 0,
+            done: false,
+            directories: false,
+            cancel,
+            updates,
+        }
+    }
+
+    /// Lets the user pick one of `dirs`, which matched `pattern` below `root`.
+    pub fn pick_directory(root: PathBuf, pattern: &str, dirs: Vec<PathBuf>) -> Self {
+        // nothing is sent, so the search is over right away
+        let (_, updates) = channel();
+        Self {
+            root,
+            pattern: pattern.to_string(),
+            results: dirs,
+            cursor: 0,
+            done: true,
+            directories: true,
+            cancel: Arc::new(AtomicBool::new(false)),
+            updates,
+        }
+    }
+
+    /// Takes in the results found since the last call, returns whether anything changed.
+    pub fn receive(&mut self) -> bool {
+        let mut changed = false;
+        loop {
+            match self.updates.try_recv() {
+                Ok(results) => {
+                    self.results.extend(results);
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    changed |= !self.done;
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        changed
+    }
+
+    pub fn selected(&self) -> Option<&PathBuf> {
+        self.results.get(self.cursor)
+    }
+}
+
+impl Drop for Find {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Searches the tree breadth first, so results close to `root` come first. Symlinks aren't
+/// followed.
+fn work(
+    root: &Path,
+    matcher: &Matcher,
+    max_depth: usize,
+    cancel: &AtomicBool,
+    tx: &Sender<Vec<PathBuf>>,
+) {
+    let mut found = 0;
+    let mut queue = VecDeque::from([(root.to_path_buf(), 1)]);
+    while let Some((dir, depth)) = queue.pop_front() {
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(read_dir) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut results = Vec::new();
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            if depth < max_depth && entry.file_type().is_ok_and(|t| t.is_dir()) {
+                queue.push_back((path.clone(), depth + 1));
+            }
+            if path
+                .strip_prefix(root)
+                .is_ok_and(|rel| matcher.matches(rel))
+            {
+                results.push(path);
+            }
+        }
+        if results.is_empty() {
+            continue;
+        }
+        results.sort();
+        found += results.len();
+        if tx.send(results).is_err() || found >= MAX_RESULTS {
+            return;
+        }
+    }
+}
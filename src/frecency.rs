@@ -0,0 +1,160 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Once the ranks of all directories add up to more than this, every rank is scaled down so
+/// that directories that aren't visited anymore eventually get forgotten.
+const MAX_TOTAL_RANK: f64 = 10000.0;
+/// Directories whose rank drops below this while aging are forgotten.
+const MIN_RANK: f64 = 1.0;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+pub struct FrecencyEntry {
+    pub path: PathBuf,
+    pub rank: f64,
+    /// Seconds since the Unix epoch.
+    pub last_access: u64,
+}
+
+impl FrecencyEntry {
+    /// Ranks recently visited directories higher, like zoxide does.
+    fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_access);
+        if age < HOUR {
+            self.rank * 4.0
+        } else if age < DAY {
+            self.rank * 2.0
+        } else if age < WEEK {
+            self.rank * 0.5
+        } else {
+            self.rank * 0.25
+        }
+    }
+}
+
+/// A database of visited directories ranked by how frequently and how recently they were visited.
+#[derive(Default)]
+pub struct Frecency {
+    pub entries: Vec<FrecencyEntry>,
+    /// Where the database is saved, `None` if it shouldn't be persisted.
+    pub file: Option<PathBuf>,
+}
+
+impl Frecency {
+    /// Loads the database from `file`, starting out empty if it can't be read.
+    pub fn load(file: PathBuf) -> Self {
+        let mut entries = Vec::new();
+        if let Ok(content) = std::fs::read_to_string(&file) {
+            for line in content.lines() {
+                let mut parts = line.splitn(3, '\t');
+                if let (Some(rank), Some(last_access), Some(path)) =
+                    (parts.next(), parts.next(), parts.next())
+                    && let (Ok(rank), Ok(last_access)) = (rank.parse(), last_access.parse())
+                {
+                    entries.push(FrecencyEntry {
+                        path: PathBuf::from(path),
+                        rank,
+                        last_access,
+                    });
+                }
+            }
+        }
+        Self {
+            entries,
+            file: Some(file),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        if let Some(file) = &self.file {
+            let mut content = String::new();
+            for entry in self.entries.iter() {
+                // the format is line based, so paths we can't write back faithfully are skipped
+                if let Some(path) = entry.path.to_str()
+                    && !path.contains('\n')
+                {
+                    content.push_str(&format!(
+                        "{}\t{}\t{}\n",
+                        entry.rank, entry.last_access, path
+                    ));
+                }
+            }
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(file, content)?;
+        }
+        Ok(())
+    }
+
+    pub fn visit(&mut self, dir: &Path) {
+        let now = now();
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.path == dir) {
+            entry.rank += 1.0;
+            entry.last_access = now;
+        } else {
+            self.entries.push(FrecencyEntry {
+                path: dir.to_path_buf(),
+                rank: 1.0,
+                last_access: now,
+            });
+        }
+
+        let total: f64 = self.entries.iter().map(|e| e.rank).sum();
+        if total > MAX_TOTAL_RANK {
+            for entry in self.entries.iter_mut() {
+                entry.rank *= 0.9;
+            }
+            self.entries.retain(|e| e.rank >= MIN_RANK);
+        }
+    }
+
+    /// Returns the highest scoring directory that matches every whitespace separated fragment of
+    /// `query`, in order. The last fragment has to match the final component of the path.
+    /// Directories that don't exist anymore are forgotten along the way.
+    pub fn best_match(&mut self, query: &str) -> Option<PathBuf> {
+        self.entries.retain(|e| e.path.is_dir());
+        let fragments = query
+            .split_whitespace()
+            .map(|f| f.to_lowercase())
+            .collect::<Vec<_>>();
+        let now = now();
+        self.entries
+            .iter()
+            .filter(|e| matches(&e.path, &fragments))
+            .max_by(|a, b| a.score(now).total_cmp(&b.score(now)))
+            .map(|e| e.path.clone())
+    }
+}
+
+fn matches(path: &Path, fragments: &[String]) -> bool {
+    let path_str = path.to_string_lossy().to_lowercase();
+    let mut rest = path_str.as_str();
+    for fragment in fragments.iter() {
+        if let Some(i) = rest.find(fragment.as_str()) {
+            rest = &rest[i + fragment.len()..];
+        } else {
+            return false;
+        }
+    }
+    if let Some(last) = fragments.last()
+        && let Some(name) = path.file_name()
+    {
+        return name
+            .to_string_lossy()
+            .to_lowercase()
+            .contains(last.as_str());
+    }
+    true
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
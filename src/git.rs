@@ -0,0 +1,95 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// The state of an entry in a git working tree, ordered by how much attention it needs.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GitStatus {
+    Untracked,
+    Staged,
+    Modified,
+    Conflicted,
+}
+
+impl GitStatus {
+    /// Parses the `XY` code of a `git status --porcelain` line, ignored entries are skipped.
+    fn from_code(x: u8, y: u8) -> Option<Self> {
+        match (x, y) {
+            (b'!', b'!') => None,
+            (b'?', b'?') => Some(Self::Untracked),
+            (b'U', _) | (_, b'U') | (b'A', b'A') | (b'D', b'D') => Some(Self::Conflicted),
+            (_, b' ') => Some(Self::Staged),
+            _ => Some(Self::Modified),
+        }
+    }
+
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Self::Untracked => "?",
+            Self::Staged => "+",
+            Self::Modified => "~",
+            Self::Conflicted => "!",
+        }
+    }
+}
+
+/// Runs `git status` for `dir` and returns the status of each of its entries by name, a directory
+/// gets the status of the most important change inside it.
+/// Returns `None` if `dir` isn't inside a working tree (or git isn't installed).
+pub fn status(dir: &Path) -> Option<HashMap<OsString, GitStatus>> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| output.stdout)
+    };
+    let root = git(&["rev-parse", "--show-toplevel"])?;
+    let root = PathBuf::from(String::from_utf8_lossy(&root).trim_end());
+    // paths in the output are relative to the root of the working tree
+    let output = git(&[
+        "status",
+        "--porcelain",
+        "-z",
+        "--untracked-files=normal",
+        "--",
+        ".",
+    ])?;
+    let dir = dir.canonicalize().ok()?;
+    let root = root.canonicalize().ok()?;
+
+    let mut res = HashMap::new();
+    let mut records = output.split(|b| *b == 0);
+    while let Some(record) = records.next() {
+        if record.len() < 4 {
+            continue;
+        }
+        if matches!(record[0], b'R' | b'C') {
+            // renames and copies are followed by the path they came from
+            records.next();
+        }
+        let Some(status) = GitStatus::from_code(record[0], record[1]) else {
+            continue;
+        };
+        let path = root.join(String::from_utf8_lossy(&record[3..]).trim_end_matches('/'));
+        let Some(child) = path
+            .strip_prefix(&dir)
+            .ok()
+            .and_then(|rel| rel.components().next())
+        else {
+            continue;
+        };
+        let entry = res
+            .entry(child.as_os_str().to_os_string())
+            .or_insert(status);
+        *entry = status.max(*entry);
+    }
+    Some(res)
+}
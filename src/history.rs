@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+/// Only this many of the latest entries are kept.
+pub const MAX_ENTRIES: usize = 500;
+
+/// What was entered into prompts, recalled with up and down like in a shell.
+#[derive(Default)]
+pub struct History {
+    /// Oldest first.
+    pub entries: Vec<String>,
+    /// Where the history is saved, `None` if it shouldn't be persisted.
+    pub file: Option<PathBuf>,
+    /// The entry being shown in the prompt, `None` while the user's own input is.
+    position: Option<usize>,
+    /// What was typed before going back in the history, shown again past its newest entry.
+    draft: String,
+}
+
+impl History {
+    /// Loads the history from `file`, starting out empty if it can't be read.
+    pub fn load(file: PathBuf) -> Self {
+        let mut entries = std::fs::read_to_string(&file)
+            .map(|content| content.lines().map(str::to_string).collect::<Vec<_>>())
+            .unwrap_or_default();
+        entries.drain(..entries.len().saturating_sub(MAX_ENTRIES));
+        Self {
+            entries,
+            file: Some(file),
+            ..Default::default()
+        }
+    }
+
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        if let Some(file) = &self.file {
+            let mut content = String::new();
+            for entry in self.entries.iter() {
+                content.push_str(entry);
+                content.push('\n');
+            }
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(file, content)?;
+        }
+        Ok(())
+    }
+
+    /// Adds `entry` unless it's empty or the same as the newest one, and starts over from the
+    /// user's own input.
+    pub fn push(&mut self, entry: &str) {
+        self.reset();
+        // the format is line based
+        if entry.is_empty()
+            || entry.contains('\n')
+            || self.entries.last().is_some_and(|e| e == entry)
+        {
+            return;
+        }
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.entries.push(entry.to_string());
+    }
+
+    /// Forgets where the prompt was in the history, for when a new prompt is opened.
+    pub fn reset(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+
+    /// The entry before the one shown, `input` is what the prompt shows right now. Returns
+    /// `None` if there's nothing older.
+    pub fn older(&mut self, input: &str) -> Option<&str> {
+        let position = match self.position {
+            Some(0) => return None,
+            Some(position) => position - 1,
+            None => {
+                self.draft = input.to_string();
+                self.entries.len().checked_sub(1)?
+            }
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// The entry after the one shown, or what was typed before going back once the newest one
+    /// is passed. Returns `None` if the prompt already shows the user's own input.
+    pub fn newer(&mut self) -> Option<&str> {
+        let position = self.position? + 1;
+        if position < self.entries.len() {
+            self.position = Some(position);
+            Some(&self.entries[position])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! The file browser behind the `walked` binary, so it can be embedded into other TUIs.
+//!
+//! A [`Window`] holds the panes and everything they share. Key events are handed to
+//! [`Window::process_key_event`], [`Window::tick`] picks up work that finished in the background
+//! and [`render`] draws the window into a ratatui [`Frame`](ratatui::Frame).
+
+pub mod archive;
+pub mod checksum;
+pub mod completion;
+pub mod config;
+pub mod dir_reader;
+pub mod dir_size;
+pub mod disk;
+pub mod error_log;
+pub mod find;
+pub mod frecency;
+pub mod git;
+pub mod history;
+pub mod preview;
+mod render;
+pub mod session;
+pub mod transfer;
+pub mod trash;
+pub mod undo;
+pub mod watch;
+pub mod window;
+
+use std::path::{Path, PathBuf};
+
+pub use config::Config;
+pub use render::render;
+pub use window::{Panel, PanelFrameData, PanelMode, Window};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathKind {
+    File,
+    Dir,
+    Ambigious,
+}
+
+#[derive(Debug)]
+pub enum WalkedError {
+    PathNotFound {
+        path: PathBuf,
+        path_kind: PathKind,
+    },
+    PermissionDenied {
+        path: PathBuf,
+        path_kind: PathKind,
+    },
+    Message(String),
+    /// Shown like the other errors, but only points out something that was ignored.
+    Warning(String),
+}
+
+impl std::fmt::Display for WalkedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalkedError::PathNotFound { path, path_kind } => write!(
+                f,
+                "Couldn't find {} '{}'",
+                match path_kind {
+                    PathKind::File => "file",
+                    PathKind::Dir => "directory",
+                    PathKind::Ambigious => "entry",
+                },
+                path.display()
+            ),
+            WalkedError::PermissionDenied { path, path_kind } => write!(
+                f,
+                "Couldn't access {} '{}'",
+                match path_kind {
+                    PathKind::File => "file",
+                    PathKind::Dir => "directory",
+                    PathKind::Ambigious => "entry",
+                },
+                path.display()
+            ),
+            WalkedError::Message(msg) | WalkedError::Warning(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for WalkedError {}
+
+impl WalkedError {
+    /// Maps an IO error that happened on `path` to the matching `WalkedError`, using `message` for
+    /// errors that don't have their own variant.
+    pub fn from_io(
+        err: &std::io::Error,
+        path: &Path,
+        path_kind: PathKind,
+        message: String,
+    ) -> WalkedError {
+        match err.kind() {
+            std::io::ErrorKind::NotFound => WalkedError::PathNotFound {
+                path: path.to_path_buf(),
+                path_kind,
+            },
+            std::io::ErrorKind::PermissionDenied => WalkedError::PermissionDenied {
+                path: path.to_path_buf(),
+                path_kind,
+            },
+            _ => WalkedError::Message(message),
+        }
+    }
+}
+
+/// Where walked keeps the state that should survive restarts.
+pub fn state_dir() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("walked"))
+}
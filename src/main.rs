@@ -1,118 +1,311 @@
-mod config;
-mod window;
-
-use std::{io::BufWriter, path::PathBuf};
+use std::{
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
-use config::Config;
 use crossterm::event::{self, Event};
-use ratatui::{
-    Terminal,
-    layout::Constraint,
-    prelude::CrosstermBackend,
-    style::{Style, Stylize},
-    text::Line,
-    widgets::{Block, Padding, Row, Table},
+use ratatui::{Terminal, prelude::CrosstermBackend};
+use walked::{
+    Config, PathKind, WalkedError, Window, config,
+    frecency::Frecency,
+    history::History,
+    render,
+    session::Session,
+    state_dir,
+    watch::{DirWatcher, WATCH_TICK},
+    window::{normalize, run_in_terminal},
 };
-use window::{Panel, PanelMode, Window};
 
-#[derive(Debug)]
-pub enum PathKind {
-    File,
-    Dir,
-    Ambigious,
+/// Command line arguments.
+struct Args {
+    alternate_screen: bool,
+    /// Paths are printed followed by NUL characters instead of newlines.
+    nul_terminated: bool,
+    /// Where the paths are written instead of stdout.
+    output: Option<PathBuf>,
+    config: Option<String>,
+    /// Where the session is loaded from and saved to instead of the state directory.
+    session: Option<PathBuf>,
+    /// The directory to start in instead of the current one.
+    directory: Option<String>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut res = Args {
+            alternate_screen: true,
+            nul_terminated: false,
+            output: None,
+            config: None,
+            session: None,
+            directory: None,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--no-alt-screen" => res.alternate_screen = false,
+                "-0" => res.nul_terminated = true,
+                "--output" => {
+                    let path = args.next().ok_or("--output needs a path")?;
+                    res.output = Some(PathBuf::from(path));
+                }
+                _ if arg.starts_with("--output=") => {
+                    res.output = Some(PathBuf::from(&arg["--output=".len()..]));
+                }
+                "--config" => res.config = Some(args.next().ok_or("--config needs a path")?),
+                _ if arg.starts_with("--config=") => {
+                    res.config = Some(arg["--config=".len()..].to_string());
+                }
+                "--session" => {
+                    let path = args.next().ok_or("--session needs a path")?;
+                    res.session = Some(PathBuf::from(path));
+                }
+                _ if arg.starts_with("--session=") => {
+                    res.session = Some(PathBuf::from(&arg["--session=".len()..]));
+                }
+                _ if arg.starts_with('-') => return Err(format!("Unknown option '{arg}'")),
+                _ if res.directory.is_some() => {
+                    return Err(format!("Unexpected argument '{arg}'"));
+                }
+                _ => res.directory = Some(arg),
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// What is printed once walked is left.
+enum Exit {
+    /// The working directory, for the shell to `cd` into.
+    Directory(PathBuf),
+    /// The entries that were selected when quitting with `quit_with_selection`.
+    Selection(Vec<PathBuf>),
+    /// Left with `cancel`, nothing is printed and the exit status is 1.
+    Cancelled,
 }
 
-#[derive(Debug)]
-pub enum WalkedError {
-    PathNotFound { path: PathBuf, path_kind: PathKind },
-    PermissionDenied { path: PathBuf, path_kind: PathKind },
-    Message(String),
+/// Enables raw mode (and the alternate screen if requested) and restores the terminal when
+/// dropped, so that early returns and panics don't leave the user's terminal garbled.
+struct TerminalGuard {
+    alternate_screen: bool,
 }
 
-impl std::fmt::Display for WalkedError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            WalkedError::PathNotFound { path, path_kind } => write!(
-                f,
-                "Couldn't find {} '{}'",
-                match path_kind {
-                    PathKind::File => "file",
-                    PathKind::Dir => "directory",
-                    PathKind::Ambigious => "entry",
-                },
-                path.display()
-            ),
-            WalkedError::PermissionDenied { path, path_kind } => write!(
-                f,
-                "Couldn't access {} '{}'",
-                match path_kind {
-                    PathKind::File => "file",
-                    PathKind::Dir => "directory",
-                    PathKind::Ambigious => "entry",
-                },
-                path.display()
-            ),
-            WalkedError::Message(msg) => write!(f, "{msg}"),
+impl TerminalGuard {
+    fn new(alternate_screen: bool) -> Result<Self, std::io::Error> {
+        crossterm::terminal::enable_raw_mode()?;
+        // construct the guard before anything else can fail so raw mode gets disabled either way
+        let guard = TerminalGuard { alternate_screen };
+        if alternate_screen {
+            crossterm::execute!(std::io::stderr(), crossterm::terminal::EnterAlternateScreen)?;
         }
+        Ok(guard)
     }
 }
 
-impl std::error::Error for WalkedError {}
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal(self.alternate_screen);
+    }
+}
+
+/// Best effort, errors are ignored since this also runs while panicking.
+fn restore_terminal(alternate_screen: bool) {
+    let _ = crossterm::terminal::disable_raw_mode();
+    if alternate_screen {
+        let _ = crossterm::execute!(std::io::stderr(), crossterm::terminal::LeaveAlternateScreen);
+    }
+    let _ = crossterm::execute!(std::io::stderr(), crossterm::cursor::Show);
+}
 
-const HIGHLIGHT_SYMBOL: &str = ">>";
 fn main() -> Result<(), std::io::Error> {
-    crossterm::terminal::enable_raw_mode()?;
-    crossterm::execute!(std::io::stderr(), crossterm::terminal::EnterAlternateScreen)?;
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("walked: {err}");
+            std::process::exit(2);
+        }
+    };
+    let alternate_screen = args.alternate_screen;
+    // paths may contain newlines, a NUL can't be part of them
+    let terminator = if args.nul_terminated { b'\0' } else { b'\n' };
+
+    let default_panic_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // restore the terminal first, otherwise the panic message ends up on the alternate screen
+        restore_terminal(alternate_screen);
+        default_panic_hook(info);
+    }));
+    let terminal_guard = TerminalGuard::new(alternate_screen)?;
     let mut terminal = Terminal::new(CrosstermBackend::new(BufWriter::new(std::io::stderr())))?;
-    let current_dir = std::path::absolute(".").expect("Can't parse current working directory");
-    let mut config = Config::default();
-
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        if let Ok(config_str) = std::fs::read_to_string(&args[1]) {
-            if let Ok(val) = toml::from_str(&config_str) {
-                config.from_toml(val);
-            }
+    let mut errors = Vec::new();
+    let mut current_dir = std::path::absolute(".").expect("Can't parse current working directory");
+    if let Some(dir) = &args.directory {
+        let dir = normalize(&current_dir.join(config::expand_tilde(dir)));
+        if dir.is_dir() {
+            current_dir = dir;
+        } else if dir.exists() {
+            errors.push(WalkedError::Message(format!(
+                "'{}' is not a directory",
+                dir.display()
+            )));
+        } else {
+            errors.push(WalkedError::PathNotFound {
+                path: dir,
+                path_kind: PathKind::Dir,
+            });
         }
     }
-
-    let result = run(&mut terminal, config, current_dir);
-    crossterm::terminal::disable_raw_mode()?;
-    crossterm::execute!(std::io::stderr(), crossterm::terminal::LeaveAlternateScreen)?;
-    match result {
-        Ok(wd) => {
-            println!("{}", wd.to_str().unwrap());
-            Ok(())
+    let config_path = match &args.config {
+        Some(path) => Some(std::path::absolute(path).unwrap_or_else(|_| PathBuf::from(path))),
+        None => {
+            // without an existing file, the preferred location is still watched so a config
+            // created there later gets picked up
+            let paths = config::default_config_paths();
+            paths
+                .iter()
+                .find(|path| path.is_file())
+                .or(paths.first())
+                .cloned()
+        }
+    };
+    // a default location without a file just means there's no config yet
+    let config = match config_path
+        .as_deref()
+        .filter(|path| args.config.is_some() || path.exists())
+        .map(Config::load)
+    {
+        Some(Ok(config)) => config,
+        Some(Err(err)) => {
+            errors.push(WalkedError::Message(err));
+            Config::default()
         }
-        Err(e) => Err(e),
+        None => Config::default(),
+    };
+    // a directory given on the command line wins
+    if let Some(dir) = last_dir_file()
+        .filter(|_| config.restore_last_dir && args.directory.is_none())
+        .and_then(|file| std::fs::read(file).ok())
+        .and_then(|bytes| read_path(bytes, b'\n'))
+        .filter(|dir| dir.is_dir())
+    {
+        current_dir = dir;
+    }
+
+    let result = run(
+        &mut terminal,
+        config,
+        config_path,
+        current_dir,
+        errors,
+        &args,
+    );
+    if !alternate_screen {
+        // Without the alternate screen our frames are drawn over the normal buffer, so clean them
+        // up and put the cursor back where the shell expects it.
+        terminal.clear()?;
+        terminal.set_cursor_position((0, 0))?;
+    }
+    drop(terminal_guard);
+    let paths = match result? {
+        Exit::Directory(wd) => vec![wd],
+        Exit::Selection(paths) => paths,
+        Exit::Cancelled => std::process::exit(1),
+    };
+    match &args.output {
+        Some(output) => write_paths(&mut std::fs::File::create(output)?, &paths, terminator),
+        None => write_paths(&mut std::io::stdout().lock(), &paths, terminator),
     }
 }
 
-impl PanelMode {
-    fn to_string(&self, config: &Config) -> String {
-        match *self {
-            PanelMode::Normal => config.normal_mode_text.clone(),
-            PanelMode::Prompt => config.normal_mode_text.clone(),
-            PanelMode::Search => config.search_mode_text.clone(),
-            PanelMode::Insert => config.insert_mode_text.clone(),
+/// Writes `paths` to `out`, each followed by `terminator`. Paths on Unix don't have to be valid
+/// UTF-8, so the raw bytes are written there.
+fn write_paths(out: &mut impl Write, paths: &[PathBuf], terminator: u8) -> std::io::Result<()> {
+    for path in paths {
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            out.write_all(path.as_os_str().as_bytes())?;
         }
+        #[cfg(not(unix))]
+        out.write_all(path.to_string_lossy().as_bytes())?;
+        out.write_all(&[terminator])?;
+    }
+    out.flush()
+}
+
+/// Reads back a path written by `write_paths`, followed by `terminator`.
+fn read_path(mut bytes: Vec<u8>, terminator: u8) -> Option<PathBuf> {
+    if bytes.last() == Some(&terminator) {
+        bytes.pop();
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        Some(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
     }
+    #[cfg(not(unix))]
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// Where the directory walked was left in is kept for `restore_last_dir`.
+fn last_dir_file() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("last_dir"))
+}
+
+fn save_last_dir(dir: &Path) -> std::io::Result<()> {
+    let Some(file) = last_dir_file() else {
+        return Ok(());
+    };
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    write_paths(
+        &mut std::fs::File::create(file)?,
+        &[dir.to_path_buf()],
+        b'\n',
+    )
 }
 
 fn run<W: ratatui::prelude::Backend>(
     terminal: &mut Terminal<W>,
     config: Config,
+    config_path: Option<PathBuf>,
     current_dir: PathBuf,
-) -> Result<PathBuf, std::io::Error> {
-    let mut window = Window {
-        panels: vec![vec![Panel::new(current_dir)]],
-        panel_focus_i: 0,
-        panel_focus_j: 0,
-        clipboard: Vec::new(),
-        config,
+    errors: Vec<WalkedError>,
+    args: &Args,
+) -> Result<Exit, std::io::Error> {
+    let alternate_screen = args.alternate_screen;
+    let frecency = state_dir()
+        .map(|dir| Frecency::load(dir.join("frecency")))
+        .unwrap_or_default();
+    let mut window = Window::new(config, current_dir.clone(), frecency);
+    if let Some(dir) = state_dir() {
+        window.history = History::load(dir.join("history"));
+    }
+    window.session_file = match &args.session {
+        Some(path) => Some(std::path::absolute(path).unwrap_or_else(|_| path.clone())),
+        None => state_dir().map(|dir| dir.join("session.toml")),
     };
+    // a directory given on the command line wins over the saved session, unless the session was
+    // asked for explicitly
+    if let Some(file) = window
+        .session_file
+        .clone()
+        .filter(|file| args.session.is_some() || (args.directory.is_none() && file.is_file()))
+    {
+        match Session::load(&file) {
+            Ok(session) => window.restore_session(session, &current_dir),
+            Err(err) => window.panels[0][0].errors.push(err),
+        }
+    }
+    window.panels[window.panel_focus_i][window.panel_focus_j]
+        .errors
+        .extend(errors);
+    let mut watcher = DirWatcher::new();
 
     let mut start = true;
+    // set whenever the window may look different than it was last drawn
+    let mut dirty = true;
     loop {
         // needed because otherwise the applications hangs until you press a key on startup.
         // i could just change the order of event processing and drawing, but i am pretty sure that
@@ -123,291 +316,281 @@ fn run<W: ratatui::prelude::Backend>(
                 start = false;
                 Event::FocusGained
             } else {
-                event::read()?
-            }
-        };
-
-        if let Event::Key(key_event) = event {
-            if key_event == window.config.pane_up {
-                window.pane_up();
-            } else if key_event == window.config.pane_down {
-                window.pane_down();
-            } else if key_event == window.config.pane_left {
-                window.pane_left();
-            } else if key_event == window.config.pane_right {
-                window.pane_right();
-            } else if key_event == window.config.split_pane_up {
-                window.split_up();
-            } else if key_event == window.config.split_pane_down {
-                window.split_down();
-            } else if key_event == window.config.split_pane_left {
-                window.split_left();
-            } else if key_event == window.config.split_pane_right {
-                window.split_right();
-            } else if key_event == window.config.close_active_pane {
-                window.close_active();
-            } else {
-                let mut res = window.panels[window.panel_focus_i][window.panel_focus_j].update(
-                    key_event,
-                    &mut window.clipboard,
-                    &window.config,
-                );
-
-                window.panels[window.panel_focus_i][window.panel_focus_j]
-                    .process_command_queue(&mut res);
-                if res.quit {
-                    return Ok(window.panel().working_directory.clone());
+                if let Some(watcher) = &mut watcher {
+                    // keeps up with directory changes made through any key, however the panel got
+                    // there
+                    // the config file's directory is watched since editors often replace the file
+                    watcher.watch(
+                        window
+                            .panels
+                            .iter()
+                            .flatten()
+                            .map(|p| p.working_directory.as_path())
+                            .chain(config_path.as_deref().and_then(Path::parent)),
+                    );
                 }
-                if res.should_refresh {
-                    for i in 0..window.panels.len() {
-                        for j in 0..window.panels[i].len() {
-                            if i == window.panel_focus_i && j == window.panel_focus_j {
-                                continue;
+                let panel = window.panel();
+                let timeout = [panel.number_prefix_deadline(), panel.pending_key_deadline()]
+                    .into_iter()
+                    .flatten()
+                    .min()
+                    .map_or(WATCH_TICK, |deadline| {
+                        deadline
+                            .saturating_duration_since(Instant::now())
+                            .min(WATCH_TICK)
+                    });
+                if event::poll(timeout)? {
+                    event::read()?
+                } else if let Some(key_event) = window.panel().expired_key() {
+                    Event::Key(key_event)
+                } else {
+                    let mut redraw = window.tick();
+                    if let Some(changed) = watcher.as_mut().and_then(DirWatcher::changes) {
+                        if let Some(path) = config_path.as_ref().filter(|p| changed.contains(*p)) {
+                            window.reload_config(path);
+                            redraw = true;
+                        }
+                        for panel in window.panels.iter_mut().flatten() {
+                            if changed.contains(&panel.working_directory) {
+                                panel.read_working_dir();
+                                panel.refresh_cursor();
+                                redraw = true;
                             }
-                            window.panels[i][j].read_working_dir();
-                            window.panels[i][j].refresh_cursor();
                         }
                     }
+                    if !redraw {
+                        continue;
+                    }
+                    Event::FocusGained
                 }
             }
+        };
+
+        match event {
+            Event::FocusGained | Event::Resize(..) => dirty = true,
+            Event::Key(key_event) => dirty |= !window.ignores(key_event),
+            _ => (),
         }
+        if let Event::Key(key_event) = event {
+            let mut res = window.process_key_event(key_event);
+            if let Some(command) = res.shell_command.take() {
+                let dir = window.panel().working_directory.clone();
+                let status = suspended(terminal, alternate_screen, || run_shell(&command, &dir))?;
 
-        terminal.draw(|f| {
-            let mut area = f.area();
-            let width = area.width;
-            let height_per_pane = area.height / window.panels.len() as u16; // NOTE: assumes that there are panels
-            area.height = height_per_pane;
-            let start = area.x;
-            for i in 0..window.panels.len() {
-                area.x = start;
-                let width_per_pane = width / window.panels[i].len() as u16;
-                area.width = width_per_pane;
-                for j in 0..window.panels[i].len() {
-                    let panel = &mut window.panels[i][j];
-                    let view = Block::new()
-                        .padding(Padding::new(panel.left, 0, panel.top, panel.bottom))
-                        .title(if panel.errors.len() > 0 {
-                            {
-                                let mut res = String::new();
-                                for err in panel.errors.iter() {
-                                    res.push_str(&format!("{err} "));
-                                }
-                                res
-                            }
-                            .into_left_aligned_line()
-                            .red()
-                        } else {
-                            panel
-                                .working_directory
-                                .to_str()
-                                .unwrap()
-                                .to_string()
-                                .into_centered_line()
-                        })
-                        .title_bottom(panel.mode.to_string(&window.config).into_centered_line());
-
-                    let content = panel
-                        .entries
-                        .iter()
-                        .enumerate()
-                        .map(|(i, p)| {
-                            let mut header = String::new();
-                            if window.config.show_entry_number {
-                                header.push_str(&format!(
-                                    "{:w$}",
-                                    i,
-                                    w = (panel.entries.len() - 1).to_string().chars().count()
-                                ))
-                            }
-                            if window.config.show_entry_type {
-                                let entry_type = {
-                                    if panel.entries[i].is_file() {
-                                        &window.config.file_text
-                                    } else if panel.entries[i].is_dir() {
-                                        &window.config.directory_text
-                                    } else if panel.entries[i].is_symlink() {
-                                        &window.config.symlink_text
-                                    } else {
-                                        &window.config.other_text
-                                    }
-                                };
-                                if window.config.show_entry_number {
-                                    header.push(':');
-                                }
-                                header.push_str(entry_type);
-                            }
-                            if let Ok(metadata) = std::fs::metadata(&panel.entries[i]) {
-                                if panel.entries[i].is_file() {
-                                    let size = bytesize::ByteSize::b(metadata.len());
-                                    header.push_str(&format!(" {}", size));
-                                } else {
-                                    header.push_str(" - ");
-                                }
-                            }
-                            panel.header_width =
-                                (header.chars().count() as u16).max(panel.header_width);
-                            let last = {
-                                if let Some(l) = p.file_name() {
-                                    l.to_os_string()
-                                } else {
-                                    std::ffi::OsString::from("..")
-                                }
-                            };
-                            if panel.mode == PanelMode::Insert {
-                                if let Some(selected) = panel.table_state.selected() {
-                                    if selected == i {
-                                        return Row::new([header, panel.edit_buffer.clone()]);
-                                    }
-                                }
-                            }
-                            let is_in_selection = {
-                                if let Some(selection_start) = panel.selection_start {
-                                    if let Some(cur) = panel.table_state.selected() {
-                                        if cur > selection_start {
-                                            i < cur && i >= selection_start
-                                        } else if cur < selection_start {
-                                            i > cur && i <= selection_start
-                                        } else {
-                                            false
-                                        }
-                                    } else {
-                                        false
-                                    }
-                                } else {
-                                    false
-                                }
-                            };
-                            let line = last.to_str().unwrap().to_string();
-                            Row::new([
-                                header.into_line(),
-                                if is_in_selection {
-                                    line.reversed().into_line()
-                                } else {
-                                    line.into_line()
-                                },
-                            ])
-                        })
-                        .collect::<Vec<Row>>();
-                    if i == window.panel_focus_i && j == window.panel_focus_j {
-                        if let Some(i) = panel.table_state.selected() {
-                            let row_offset = {
-                                if i < panel.table_state.offset() {
-                                    0
-                                } else if panel.entries.len() > 0 {
-                                    (i - panel.table_state.offset()).min(
-                                        (panel.entries.len() - 1)
-                                            .min(view.inner(area).height as usize - 1),
-                                    ) as u16
-                                } else {
-                                    0
-                                }
-                            };
-                            f.set_cursor_position((
-                                area.x
-                                    + panel.left
-                                    + panel.header_width
-                                    + 1
-                                    + panel.cursor_offset
-                                    + if panel.mode == PanelMode::Normal {
-                                        HIGHLIGHT_SYMBOL.chars().count() as u16
-                                    } else {
-                                        0
-                                    },
-                                area.y + panel.top + 1 + row_offset,
-                            ));
-                        }
+                let panel = &mut window.panels[window.panel_focus_i][window.panel_focus_j];
+                match status {
+                    Ok(status) if !status.success() => panel.errors.push(WalkedError::Message(
+                        format!("'{command}' failed ({status})"),
+                    )),
+                    Ok(_) => (),
+                    Err(err) => panel.errors.push(WalkedError::Message(format!(
+                        "Couldn't run '{command}': {err}"
+                    ))),
+                }
+                // the command could have done anything, so everything is read again
+                window.refresh_panels();
+            }
+            if let Some((a, b)) = res.diff_files.take() {
+                let command = window.config.diff_command.clone();
+                let status = suspended(terminal, alternate_screen, || run_diff(&command, &a, &b))?;
+                let panel = &mut window.panels[window.panel_focus_i][window.panel_focus_j];
+                match status {
+                    // diff tools exit with 1 when the files differ, which is what's expected here
+                    Ok(status) if status.code().is_none_or(|code| code > 1) => panel.errors.push(
+                        WalkedError::Message(format!("'{command}' failed ({status})")),
+                    ),
+                    Ok(_) => (),
+                    Err(err) => panel.errors.push(WalkedError::Message(format!(
+                        "Couldn't run '{command}': {err}"
+                    ))),
+                }
+            }
+            if let Some((command, file)) = res.open_suspended.take() {
+                let status = suspended(terminal, alternate_screen, || {
+                    run_in_terminal(&command, &file)
+                })?;
+                let panel = &mut window.panels[window.panel_focus_i][window.panel_focus_j];
+                match status {
+                    Ok(status) if !status.success() => panel.errors.push(WalkedError::Message(
+                        format!("'{command}' failed ({status})"),
+                    )),
+                    Ok(_) => (),
+                    Err(err) => panel.errors.push(err),
+                }
+            }
+            if let Some(file) = res.edit_file.take() {
+                let editor = editor();
+                let status = suspended(terminal, alternate_screen, || run_editor(&editor, &file))?;
+                let edited = match status {
+                    Ok(status) if status.success() => true,
+                    Ok(status) => {
+                        window.panels[window.panel_focus_i][window.panel_focus_j]
+                            .errors
+                            .push(WalkedError::Message(format!(
+                                "'{editor}' failed ({status}), nothing was renamed"
+                            )));
+                        false
                     }
-
-                    match panel.mode {
-                        PanelMode::Prompt => {
-                            let mut top_area = area;
-                            top_area.height -= 2;
-                            let mut bottom_area = top_area;
-                            bottom_area.y += top_area.height;
-                            bottom_area.height = 2;
-                            f.render_stateful_widget(
-                                Table::default()
-                                    .widths([
-                                        Constraint::Length(panel.header_width),
-                                        Constraint::Min(0),
-                                    ])
-                                    .rows(content)
-                                    .block(view)
-                                    .row_highlight_style(Style::new().reversed())
-                                    .highlight_symbol(HIGHLIGHT_SYMBOL),
-                                top_area,
-                                &mut panel.table_state,
-                            );
-                            if let Some(cmd) = &panel.command_prompt {
-                                f.render_widget(
-                                    format!("({}) >{}_", cmd.to_string(), panel.edit_buffer),
-                                    bottom_area,
-                                );
-                            } else {
-                                f.render_widget(format!(">{}_", panel.edit_buffer), bottom_area);
-                            }
-                        }
-                        PanelMode::Normal | PanelMode::Search => {
-                            f.render_stateful_widget(
-                                Table::default()
-                                    .widths([
-                                        Constraint::Length(panel.header_width),
-                                        Constraint::Min(0),
-                                    ])
-                                    .rows(content)
-                                    .block(view)
-                                    .row_highlight_style(Style::new().reversed())
-                                    .highlight_symbol(HIGHLIGHT_SYMBOL),
-                                area,
-                                &mut panel.table_state,
-                            );
-                        }
-                        PanelMode::Insert => {
-                            f.render_stateful_widget(
-                                Table::default()
-                                    .widths([
-                                        Constraint::Length(panel.header_width),
-                                        Constraint::Min(0),
-                                    ])
-                                    .rows(content)
-                                    .block(view)
-                                    .cell_highlight_style(Style::new().underlined()),
-                                area,
-                                &mut panel.table_state,
-                            );
-                        }
+                    Err(err) => {
+                        window.panels[window.panel_focus_i][window.panel_focus_j]
+                            .errors
+                            .push(WalkedError::Message(format!(
+                                "Couldn't run '{editor}': {err}"
+                            )));
+                        false
                     }
-                    area.x += width_per_pane;
+                };
+                window.finish_editing(edited);
+            }
+            if res.cancelled {
+                return Ok(Exit::Cancelled);
+            }
+            if res.quit {
+                if window.config.restore_last_dir {
+                    // best effort, not being able to remember it is no reason to fail
+                    let _ = save_last_dir(&window.panel().working_directory);
                 }
-                area.y += height_per_pane;
+                return Ok(match res.quit_selection {
+                    Some(paths) => Exit::Selection(paths),
+                    None => Exit::Directory(window.panel().working_directory.clone()),
+                });
             }
-        })?;
+        }
+
+        if dirty {
+            terminal.draw(|f| render(f, &mut window))?;
+            dirty = false;
+        }
     }
 }
 
-pub trait IntoLine<'a> {
-    fn into_line(self) -> Line<'a>;
-    fn into_centered_line(self) -> Line<'a>;
-    fn into_right_aligned_line(self) -> Line<'a>;
-    fn into_left_aligned_line(self) -> Line<'a>;
+/// Hands the terminal back to the shell while `f` runs and takes it over again afterwards.
+fn suspended<W: ratatui::prelude::Backend, T>(
+    terminal: &mut Terminal<W>,
+    alternate_screen: bool,
+    f: impl FnOnce() -> T,
+) -> Result<T, std::io::Error> {
+    restore_terminal(alternate_screen);
+    let res = f();
+    crossterm::terminal::enable_raw_mode()?;
+    if alternate_screen {
+        crossterm::execute!(std::io::stderr(), crossterm::terminal::EnterAlternateScreen)?;
+    }
+    terminal.clear()?;
+    Ok(res)
+}
+
+/// The user's editor, which may come with arguments of its own.
+fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string())
 }
-impl<'a, T> IntoLine<'a> for T
-where
-    T: Into<Line<'a>>,
-{
-    fn into_line(self) -> Line<'a> {
-        self.into()
+
+/// Opens `file` in `editor` and waits for it to be closed.
+fn run_editor(editor: &str, file: &Path) -> Result<std::process::ExitStatus, std::io::Error> {
+    let mut args = editor.split_whitespace();
+    let program = args.next().unwrap_or_default();
+    std::process::Command::new(program)
+        .args(args)
+        .arg(file)
+        .status()
+}
+
+/// Compares `a` and `b` with `command` and waits for the user to read its output, which goes to
+/// stderr like the output of shell commands.
+fn run_diff(command: &str, a: &Path, b: &Path) -> Result<std::process::ExitStatus, std::io::Error> {
+    let mut args = command.split_whitespace();
+    let program = args.next().unwrap_or_default();
+    let status = std::process::Command::new(program)
+        .args(args)
+        .arg(a)
+        .arg(b)
+        .stdout(std::io::stderr())
+        .status();
+
+    eprint!("\nPress Enter to return to walkEd");
+    let _ = std::io::stdin().read_line(&mut String::new());
+    status
+}
+
+/// The user's shell and the flag that makes it run a command line.
+fn shell() -> (String, &'static str) {
+    match std::env::var("SHELL") {
+        Ok(shell) if !shell.is_empty() => (shell, "-c"),
+        _ if cfg!(windows) => ("cmd".to_string(), "/C"),
+        _ => ("/bin/sh".to_string(), "-c"),
+    }
+}
+
+/// Runs `command` with the user's shell in `dir` and waits for the user to read its output.
+/// Its output goes to stderr like walked's own, since stdout is reserved for the final working
+/// directory.
+fn run_shell(command: &str, dir: &Path) -> Result<std::process::ExitStatus, std::io::Error> {
+    let (shell, flag) = shell();
+    let mut cmd = std::process::Command::new(shell);
+    cmd.arg(flag).current_dir(dir).stdout(std::io::stderr());
+    #[cfg(windows)]
+    {
+        // cmd doesn't follow the usual quoting rules, so the line is passed on untouched
+        use std::os::windows::process::CommandExt;
+        cmd.raw_arg(command);
     }
+    #[cfg(not(windows))]
+    cmd.arg(command);
+    let status = cmd.status();
 
-    fn into_centered_line(self) -> Line<'a> {
-        self.into_line().centered()
+    eprint!("\nPress Enter to return to walkEd");
+    let _ = std::io::stdin().read_line(&mut String::new());
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terminal_is_restored_on_panic() {
+        let panicked = std::panic::catch_unwind(|| {
+            // there's nothing to restore without a terminal, like when the tests are run by CI
+            let Ok(_guard) = TerminalGuard::new(false) else {
+                return;
+            };
+            assert!(crossterm::terminal::is_raw_mode_enabled().unwrap());
+            panic!("while the terminal is in raw mode");
+        });
+        if panicked.is_err() {
+            assert!(!crossterm::terminal::is_raw_mode_enabled().unwrap());
+        }
     }
 
-    fn into_right_aligned_line(self) -> Line<'a> {
-        self.into_line().right_aligned()
+    #[cfg(unix)]
+    #[test]
+    fn paths_are_written_as_bytes() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let paths = [
+            PathBuf::from(OsStr::from_bytes(b"/tmp/bad\xff")),
+            PathBuf::from("/tmp/new\nline"),
+        ];
+        let mut out = Vec::new();
+        write_paths(&mut out, &paths, b'\0').unwrap();
+        assert_eq!(out, b"/tmp/bad\xff\0/tmp/new\nline\0");
     }
 
-    fn into_left_aligned_line(self) -> Line<'a> {
-        self.into_line().left_aligned()
+    #[test]
+    fn paths_are_read_back_as_written() {
+        #[cfg(unix)]
+        let path = {
+            use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+            PathBuf::from(OsStr::from_bytes(b"/tmp/bad\xff"))
+        };
+        #[cfg(not(unix))]
+        let path = PathBuf::from("C:\\walked");
+        let mut out = Vec::new();
+        write_paths(&mut out, std::slice::from_ref(&path), b'\n').unwrap();
+        assert_eq!(read_path(out, b'\n'), Some(path));
     }
 }
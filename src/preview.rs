@@ -0,0 +1,164 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{FontStyle, Theme, ThemeSet},
+    parsing::{SyntaxReference, SyntaxSet},
+};
+
+/// At most this much of a file is read for its preview, so huge files don't block the UI.
+const PREVIEW_BYTES: u64 = 64 * 1024;
+/// How many entries are kept before the cache is cleared.
+const CACHE_CAPACITY: usize = 256;
+/// No pane is this tall, so there is no point in highlighting more.
+const PREVIEW_LINES: usize = 500;
+
+/// Syntax definitions and the theme used to highlight previews, these are expensive to load so it
+/// is only done once.
+pub struct Highlighter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+}
+
+impl Highlighter {
+    /// Falls back to the default theme if `theme` isn't one of syntect's bundled themes.
+    pub fn new(theme: &str) -> Self {
+        let mut themes = ThemeSet::load_defaults().themes;
+        let theme = themes
+            .remove(theme)
+            .or_else(|| themes.remove("base16-ocean.dark"))
+            .unwrap_or_default();
+        Self {
+            syntaxes: SyntaxSet::load_defaults_nonewlines(),
+            theme,
+        }
+    }
+
+    fn syntax_for(&self, path: &Path) -> Option<&SyntaxReference> {
+        let extension = path.extension().or_else(|| path.file_name())?;
+        self.syntaxes
+            .find_syntax_by_extension(&extension.to_string_lossy())
+    }
+
+    /// Highlights `lines` according to the syntax of `path`, or returns them as they are if there
+    /// is no syntax for it.
+    fn highlight(&self, path: &Path, lines: Vec<String>) -> Vec<Line<'static>> {
+        let Some(syntax) = self.syntax_for(path) else {
+            return lines.into_iter().map(Line::from).collect();
+        };
+        let mut highlight = HighlightLines::new(syntax, &self.theme);
+        lines
+            .into_iter()
+            .map(
+                |line| match highlight.highlight_line(&line, &self.syntaxes) {
+                    Ok(ranges) => Line::from(
+                        ranges
+                            .into_iter()
+                            .map(|(style, text)| {
+                                Span::styled(text.to_string(), convert_style(style))
+                            })
+                            .collect::<Vec<_>>(),
+                    ),
+                    Err(_) => Line::from(line),
+                },
+            )
+            .collect()
+    }
+}
+
+/// Converts a syntect style to a ratatui one, the background is left to the terminal.
+fn convert_style(style: syntect::highlighting::Style) -> Style {
+    let mut res = Style::new().fg(Color::Rgb(
+        style.foreground.r,
+        style.foreground.g,
+        style.foreground.b,
+    ));
+    if style.font_style.contains(FontStyle::BOLD) {
+        res = res.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        res = res.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        res = res.add_modifier(Modifier::UNDERLINED);
+    }
+    res
+}
+
+pub enum Preview {
+    Text(Vec<Line<'static>>),
+    Binary { bytes: u64 },
+    Directory(Vec<String>),
+    Unreadable(String),
+}
+
+impl Preview {
+    fn read(path: &Path, highlighter: &Highlighter) -> Self {
+        if path.is_dir() {
+            return match std::fs::read_dir(path) {
+                Ok(dir) => {
+                    let mut names = dir
+                        .flatten()
+                        .map(|e| e.file_name().to_string_lossy().to_string())
+                        .collect::<Vec<_>>();
+                    names.sort();
+                    Self::Directory(names)
+                }
+                Err(err) => Self::Unreadable(err.to_string()),
+            };
+        }
+        let bytes = path.metadata().map(|m| m.len()).unwrap_or(0);
+        let mut content = Vec::new();
+        if let Err(err) =
+            std::fs::File::open(path).and_then(|f| f.take(PREVIEW_BYTES).read_to_end(&mut content))
+        {
+            return Self::Unreadable(err.to_string());
+        }
+        if content.contains(&0) {
+            return Self::Binary { bytes };
+        }
+        // a multi-byte character may be cut off at the end of what was read
+        let text = String::from_utf8_lossy(&content);
+        let lines = text
+            .lines()
+            .take(PREVIEW_LINES)
+            .map(|l| l.replace('\t', "    "))
+            .collect();
+        Self::Text(highlighter.highlight(path, lines))
+    }
+}
+
+/// Previews of the entries that were highlighted recently, so moving the cursor back and forth
+/// doesn't read them again. A preview is read again once its entry is modified.
+#[derive(Default)]
+pub struct PreviewCache {
+    previews: HashMap<PathBuf, (Option<SystemTime>, Preview)>,
+}
+
+impl PreviewCache {
+    pub fn get(&mut self, path: &Path, highlighter: &Highlighter) -> &Preview {
+        let modified = path.metadata().and_then(|m| m.modified()).ok();
+        if self
+            .previews
+            .get(path)
+            .is_none_or(|(cached, _)| *cached != modified)
+        {
+            if self.previews.len() >= CACHE_CAPACITY {
+                self.previews.clear();
+            }
+            self.previews.insert(
+                path.to_path_buf(),
+                (modified, Preview::read(path, highlighter)),
+            );
+        }
+        &self.previews[path].1
+    }
+}
@@ -0,0 +1,1059 @@
+use crate::{
+    WalkedError,
+    config::{Config, NameTruncation, SizeFormat, TimeStyle},
+    error_log::Severity,
+    git::GitStatus,
+    preview::{Highlighter, Preview, PreviewCache},
+    transfer::Transfer,
+    window::{
+        Panel, PanelMode, Window, extension, fuzzy_match, mode, permissions_string, selection_range,
+    },
+};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{
+        Block, Clear, Gauge, Padding, Paragraph, Row, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Table, Wrap,
+    },
+};
+use std::{
+    fs::Metadata,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Draws every pane of `window` into `f`, along with the preview and whichever overlay is open.
+/// The cursor is placed in the active pane, and each panel remembers how many entries fit in it.
+/// When the window is zoomed, the active pane is drawn as if it was the only one.
+pub fn render(f: &mut Frame, window: &mut Window) {
+    window.log_errors();
+    let mut area = f.area();
+    window.area = area;
+    let width = area.width;
+    let rows = if window.zoomed {
+        window.panel_focus_i..window.panel_focus_i + 1
+    } else {
+        0..window.panels.len()
+    };
+    let height_per_pane = area.height / rows.len() as u16; // NOTE: assumes that there are panels
+    area.height = height_per_pane;
+    let start = area.x;
+    for i in rows {
+        area.x = start;
+        let columns = if window.zoomed {
+            window.panel_focus_j..window.panel_focus_j + 1
+        } else {
+            0..window.panels[i].len()
+        };
+        // the active panel gets a preview of its selected entry next to it if it has the row to
+        // itself
+        let preview = window.config.show_preview && i == window.panel_focus_i && columns.len() == 1;
+        let width_per_pane = if preview {
+            width / 2
+        } else {
+            width / columns.len() as u16
+        };
+        area.width = width_per_pane;
+        for j in columns {
+            let panel = &mut window.panels[i][j];
+            let view = Block::new()
+                // the scrollbar gets a column of its own so it doesn't cover names
+                .padding(Padding::new(
+                    panel.left,
+                    u16::from(window.config.show_scrollbar),
+                    panel.top,
+                    panel.bottom,
+                ))
+                .title(if !panel.errors.is_empty() {
+                    Line::from(
+                        panel
+                            .errors
+                            .iter()
+                            .map(|err| {
+                                let style = match err {
+                                    WalkedError::Warning(_) => window.config.theme.warning,
+                                    _ => window.config.theme.error,
+                                };
+                                Span::styled(format!("{err} "), style)
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                    .left_aligned()
+                } else {
+                    let loading = panel.reader.is_some().then(|| " loading…".dim());
+                    let width = (area.width as usize)
+                        .saturating_sub(loading.as_ref().map_or(0, |span| span.width()));
+                    let title =
+                        working_directory_title(&panel.working_directory, &window.config, width);
+                    // without a path in front of it, the indicator doesn't need the space
+                    let loading = loading.map(|span| {
+                        if title.is_empty() {
+                            "loading…".dim()
+                        } else {
+                            span
+                        }
+                    });
+                    Line::from(
+                        std::iter::once(Span::raw(title))
+                            .chain(loading)
+                            .collect::<Vec<_>>(),
+                    )
+                    .centered()
+                })
+                // unread errors are counted in the active pane only, the log is shared
+                .title(
+                    if i == window.panel_focus_i
+                        && j == window.panel_focus_j
+                        && window.error_log.unread > 0
+                    {
+                        format!(" {} unread ", window.error_log.unread)
+                            .into_right_aligned_line()
+                            .style(window.config.theme.error)
+                    } else {
+                        Line::default()
+                    },
+                )
+                .title_bottom({
+                    let mut left = Vec::new();
+                    if !panel.filter.is_empty() {
+                        left.push(format!("filter: {}", panel.filter));
+                    }
+                    if window.config.show_status_bar {
+                        left.push(status_bar(panel, window.config.size_format));
+                    }
+                    if let Some(checksum) = &panel.checksum {
+                        let highlighted = panel
+                            .table_state
+                            .selected()
+                            .and_then(|i| panel.entries.get(i));
+                        if highlighted == Some(&checksum.path) {
+                            left.push(format!(
+                                "{}: {}",
+                                checksum.algorithm.name(),
+                                checksum.digest.as_deref().unwrap_or("computing…")
+                            ));
+                        }
+                    }
+                    if window.config.show_disk_usage
+                        && let Some(usage) = panel.disk_usage.get(&panel.working_directory)
+                    {
+                        left.push(format!(
+                            "{} free of {} ({}% used)",
+                            window.config.size_format.format(usage.available),
+                            window.config.size_format.format(usage.total),
+                            usage.used_percent()
+                        ));
+                    }
+                    left.join("  ").into_left_aligned_line()
+                })
+                .title_bottom(
+                    if panel.visual && panel.mode == PanelMode::Normal {
+                        window.config.visual_mode_text.clone()
+                    } else {
+                        panel.mode.to_string(&window.config)
+                    }
+                    .into_centered_line(),
+                )
+                .title_bottom(
+                    format!(
+                        "{}{}",
+                        panel.sort.name(),
+                        if panel.sort_reverse {
+                            " (reversed)"
+                        } else {
+                            ""
+                        }
+                    )
+                    .into_right_aligned_line(),
+                );
+
+            panel.view_height = view.inner(area).height;
+            let mut list_area = view.inner(area);
+            // there's no room for a single entry in panes this small, so they are left out
+            if list_area.height == 0 || list_area.width == 0 {
+                area.x += width_per_pane;
+                continue;
+            }
+            let cursor = panel.table_state.selected();
+            let selected_range = panel
+                .selection_start
+                .zip(cursor)
+                .map(|(start, cursor)| selection_range(Some(start), cursor));
+            let content = panel
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    let mut header = String::new();
+                    if window.config.show_entry_number {
+                        header.push_str(&format!(
+                            "{:w$}",
+                            i,
+                            w = (panel.entries.len() - 1).to_string().chars().count()
+                        ))
+                    }
+                    let metadata = panel.metadata[i].as_ref();
+                    let link_target = panel.link_targets[i].as_ref();
+                    if window.config.show_entry_type && !window.config.use_icons {
+                        let entry_type = {
+                            if metadata.is_some_and(|m| m.is_file()) {
+                                &window.config.file_text
+                            } else if metadata.is_some_and(|m| m.is_dir()) {
+                                &window.config.directory_text
+                            } else if link_target.is_some() {
+                                &window.config.symlink_text
+                            } else {
+                                &window.config.other_text
+                            }
+                        };
+                        if window.config.show_entry_number {
+                            header.push(':');
+                        }
+                        header.push_str(entry_type);
+                    }
+                    if window.config.show_permissions {
+                        if let Some(mode) = metadata.and_then(mode) {
+                            header.push_str(&format!(" {}", permissions_string(mode)));
+                        } else if cfg!(unix) {
+                            header.push_str(" ?????????");
+                        }
+                    }
+                    if let Some(metadata) = metadata {
+                        let size = if metadata.is_file() {
+                            window.config.size_format.format(metadata.len())
+                        } else if metadata.is_dir() {
+                            if window.config.show_dir_size {
+                                panel.dir_sizes.request(p);
+                            }
+                            match panel.dir_sizes.get(p) {
+                                Some(Some(size)) => window.config.size_format.format(size),
+                                Some(None) => "...".to_string(),
+                                None => "-".to_string(),
+                            }
+                        } else {
+                            "-".to_string()
+                        };
+                        if window.config.show_modified {
+                            // padded so the times line up
+                            header.push_str(&format!(" {size:>10}"));
+                        } else {
+                            header.push_str(&format!(" {size}"));
+                        }
+                    }
+                    if window.config.show_modified {
+                        let modified = metadata.and_then(|m| m.modified().ok());
+                        header.push_str(&format!(
+                            " {}",
+                            modified.map_or("-".to_string(), |modified| {
+                                format_time(modified, &window.config)
+                            })
+                        ));
+                    }
+                    let git_marker = panel.git_status.as_ref().map(|statuses| {
+                        match p.file_name().and_then(|n| statuses.get(n)) {
+                            Some(status) => {
+                                let marker = format!(" {}", status.marker());
+                                match status {
+                                    GitStatus::Untracked => marker.blue(),
+                                    GitStatus::Staged => marker.green(),
+                                    GitStatus::Modified => marker.yellow(),
+                                    GitStatus::Conflicted => marker.red(),
+                                }
+                            }
+                            None => Span::raw("  "),
+                        }
+                    });
+                    let icon = window.config.use_icons.then(|| {
+                        let icon = format!(
+                            " {}",
+                            window.config.icon_for(p, metadata, link_target.is_some())
+                        );
+                        if metadata.is_some_and(|m| m.is_dir()) {
+                            icon.blue()
+                        } else if link_target.is_some() {
+                            icon.cyan()
+                        } else {
+                            Span::raw(icon)
+                        }
+                    });
+                    // icons take up a single cell, `width` accounts for that
+                    panel.header_width = (header.chars().count() as u16
+                        + git_marker.as_ref().map_or(0, |m| m.width() as u16)
+                        + icon.as_ref().map_or(0, |i| i.width() as u16))
+                    .max(panel.header_width);
+                    let header = Line::from(
+                        std::iter::once(Span::raw(header))
+                            .chain(git_marker)
+                            .chain(icon)
+                            .collect::<Vec<_>>(),
+                    );
+                    let last = {
+                        if let Some(l) = p.file_name() {
+                            l.to_os_string()
+                        } else {
+                            std::ffi::OsString::from("..")
+                        }
+                    };
+                    if panel.mode == PanelMode::Insert
+                        && let Some(selected) = panel.table_state.selected()
+                        && selected == i
+                    {
+                        return (header, panel.edit_buffer.clone().into_line(), None);
+                    }
+                    // the entry under the cursor is highlighted either way
+                    let is_in_selection = cursor != Some(i)
+                        && (panel.selection.contains(&i)
+                            || selected_range.as_ref().is_some_and(|r| r.contains(&i)));
+                    let mut line = last.to_string_lossy().to_string();
+                    let extension_start = if metadata.is_some_and(|m| m.is_dir()) {
+                        line.chars().count()
+                    } else {
+                        line.chars().count() - extension(&line).chars().count()
+                    };
+                    if window.config.show_type_indicator {
+                        // purely decorative, operations keep using the real path in `entries`
+                        line.push_str(type_indicator(metadata, link_target.is_some()));
+                    }
+                    let line = match fuzzy_match(&panel.filter, &line) {
+                        Some(positions) if !panel.filter.is_empty() => Line::from(
+                            line.chars()
+                                .enumerate()
+                                .map(|(n, c)| {
+                                    if positions.contains(&n) {
+                                        c.to_string().yellow().bold()
+                                    } else {
+                                        c.to_string().into()
+                                    }
+                                })
+                                .collect::<Vec<_>>(),
+                        ),
+                        _ => line.into_line(),
+                    };
+                    // selection and cut styles are applied on top of this
+                    let line = if window.config.colorize_entries {
+                        line.fg(window.config.color_for(p, metadata, link_target.is_some()))
+                    } else {
+                        line
+                    };
+                    let mut line = if is_in_selection {
+                        line.patch_style(window.config.theme.selection)
+                    } else if window.clipboard.is_cut(p) {
+                        line.dim()
+                    } else {
+                        line
+                    };
+                    if window.config.show_symlink_target
+                        && let Some(target) = link_target
+                    {
+                        let target = format!(" -> {}", target.display());
+                        // `metadata` follows symlinks, so it's missing for broken ones
+                        line.push_span(if metadata.is_none() {
+                            target.fg(window.config.theme.broken_symlink)
+                        } else {
+                            target.dim()
+                        });
+                    }
+                    // everything from the extension on is kept visible by middle truncation
+                    let keep = line
+                        .spans
+                        .iter()
+                        .map(|span| span.content.chars().count())
+                        .sum::<usize>()
+                        .saturating_sub(extension_start);
+                    (header, line, Some(keep))
+                })
+                .collect::<Vec<_>>();
+            // names are scrolled together, but only as far as the longest one needs
+            let highlight_width = if panel.mode == PanelMode::Insert {
+                0
+            } else {
+                window.config.highlight_symbol.width() as u16
+            };
+            let name_width = list_area
+                .width
+                .saturating_sub(panel.header_width + 1 + highlight_width)
+                as usize;
+            let longest = content
+                .iter()
+                .filter(|(_, _, keep)| keep.is_some())
+                .map(|(_, line, _)| line.width())
+                .max()
+                .unwrap_or(0);
+            panel.name_scroll = panel.name_scroll.min(longest.saturating_sub(name_width));
+            let content = content
+                .into_iter()
+                .map(|(header, line, keep)| match keep {
+                    Some(_) if panel.name_scroll > 0 => {
+                        Row::new([header, skip_chars(line, panel.name_scroll)])
+                    }
+                    Some(keep) => Row::new([
+                        header,
+                        truncate_line(line, name_width, window.config.name_truncation, keep),
+                    ]),
+                    None => Row::new([header, line]),
+                })
+                .collect::<Vec<_>>();
+            if i == window.panel_focus_i
+                && j == window.panel_focus_j
+                && let Some(i) = panel.table_state.selected()
+            {
+                let row_offset = {
+                    if i < panel.table_state.offset() {
+                        0
+                    } else if !panel.entries.is_empty() {
+                        (i - panel.table_state.offset())
+                            .min((panel.entries.len() - 1).min(list_area.height as usize - 1))
+                            as u16
+                    } else {
+                        0
+                    }
+                };
+                f.set_cursor_position((
+                    area.x
+                        + panel.left
+                        + panel.header_width
+                        + 1
+                        + if panel.mode == PanelMode::Insert {
+                            panel.cursor_offset
+                        } else {
+                            panel.cursor_offset.saturating_sub(panel.name_scroll as u16)
+                        }
+                        + if panel.mode == PanelMode::Normal {
+                            window.config.highlight_symbol.width() as u16
+                        } else {
+                            0
+                        },
+                    area.y + panel.top + 1 + row_offset,
+                ));
+            }
+
+            match panel.mode {
+                PanelMode::Prompt | PanelMode::Confirm => {
+                    list_area.height = list_area.height.saturating_sub(2);
+                    let mut top_area = area;
+                    top_area.height = top_area.height.saturating_sub(2);
+                    let mut bottom_area = top_area;
+                    bottom_area.y += top_area.height;
+                    bottom_area.height = 2;
+                    f.render_stateful_widget(
+                        Table::default()
+                            .widths([Constraint::Length(panel.header_width), Constraint::Min(0)])
+                            .rows(content)
+                            .block(view)
+                            .row_highlight_style(window.config.theme.highlight)
+                            .highlight_symbol(window.config.highlight_symbol.as_str()),
+                        top_area,
+                        &mut panel.table_state,
+                    );
+                    if let Some(op) = &panel.pending_confirmation {
+                        f.render_widget(op.prompt(window.config.size_format), bottom_area);
+                    } else if let Some(paste) = &panel.pending_paste {
+                        f.render_widget(paste.prompt(), bottom_area);
+                    } else if let Some(rename) = &panel.pending_rename {
+                        let mut preview_area = bottom_area;
+                        preview_area.y += 1;
+                        preview_area.height = 1;
+                        f.render_widget(rename.prompt(), bottom_area);
+                        f.render_widget(rename.preview(), preview_area);
+                    } else if let Some(cmd) = &panel.command_prompt {
+                        f.render_widget(format!("({}) >{}_", cmd, panel.edit_buffer), bottom_area);
+                        if let Some(completion) = &panel.completion {
+                            let mut candidates_area = bottom_area;
+                            candidates_area.y += 1;
+                            candidates_area.height = 1;
+                            let names = completion
+                                .names()
+                                .enumerate()
+                                .flat_map(|(n, name)| {
+                                    let name = if completion.current == Some(n) {
+                                        Span::styled(name, window.config.theme.highlight)
+                                    } else {
+                                        Span::raw(name)
+                                    };
+                                    [name, Span::raw("  ")]
+                                })
+                                .collect::<Vec<_>>();
+                            f.render_widget(Line::from(names), candidates_area);
+                        }
+                    } else {
+                        f.render_widget(format!(">{}_", panel.edit_buffer), bottom_area);
+                    }
+                }
+                PanelMode::Normal | PanelMode::Search => {
+                    f.render_stateful_widget(
+                        Table::default()
+                            .widths([Constraint::Length(panel.header_width), Constraint::Min(0)])
+                            .rows(content)
+                            .block(view)
+                            .row_highlight_style(window.config.theme.highlight)
+                            .highlight_symbol(window.config.highlight_symbol.as_str()),
+                        area,
+                        &mut panel.table_state,
+                    );
+                }
+                PanelMode::Insert => {
+                    f.render_stateful_widget(
+                        Table::default()
+                            .widths([Constraint::Length(panel.header_width), Constraint::Min(0)])
+                            .rows(content)
+                            .block(view)
+                            .cell_highlight_style(window.config.theme.insert),
+                        area,
+                        &mut panel.table_state,
+                    );
+                }
+            }
+            if window.config.show_scrollbar {
+                let visible = list_area.height as usize;
+                if panel.entries.len() > visible && list_area.height > 0 {
+                    // one position per first visible entry, the thumb covers the visible fraction
+                    let mut state = ScrollbarState::new(panel.entries.len() - visible + 1)
+                        .position(panel.table_state.offset())
+                        .viewport_content_length(visible);
+                    f.render_stateful_widget(
+                        Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                            .begin_symbol(None)
+                            .end_symbol(None),
+                        Rect {
+                            x: list_area.right(),
+                            width: 1,
+                            ..list_area
+                        },
+                        &mut state,
+                    );
+                }
+            }
+            if let Some(transfer) = &panel.transfer
+                && matches!(panel.mode, PanelMode::Normal | PanelMode::Search)
+            {
+                render_transfer(f, transfer, &window.config, area);
+            }
+            area.x += width_per_pane;
+        }
+        if preview {
+            let panel = &window.panels[i][window.panel_focus_j];
+            let selected = panel
+                .table_state
+                .selected()
+                .and_then(|s| panel.entries.get(s));
+            let preview_area = Rect {
+                width: width - width_per_pane,
+                ..area
+            };
+            render_preview(
+                f,
+                &mut window.preview_cache,
+                &window.highlighter,
+                &window.config,
+                selected,
+                preview_area,
+            );
+        }
+        area.y += height_per_pane;
+    }
+
+    if window.show_operation_log {
+        let popup = centered_rect(f.area(), 70, 60);
+        let lines = window
+            .undo_stack
+            .log()
+            .enumerate()
+            .rev()
+            .map(|(n, (op, applied))| {
+                let line = format!("{:>3} {}", n + 1, op.describe());
+                if applied {
+                    line.into_line()
+                } else {
+                    format!("{line} (undone)").dim().into_line()
+                }
+            })
+            .collect::<Vec<Line>>();
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::bordered()
+                    .border_style(Style::new().fg(window.config.theme.border))
+                    .title("Operations".into_centered_line())
+                    .padding(Padding::horizontal(1)),
+            ),
+            popup,
+        );
+    }
+
+    if let Some(find) = &window.panels[window.panel_focus_i][window.panel_focus_j].find {
+        let popup = centered_rect(f.area(), 70, 60);
+        let height = popup.height.saturating_sub(2) as usize;
+        // the cursor is kept on the last line once it moves past it
+        let offset = (find.cursor + 1).saturating_sub(height);
+        let lines = find
+            .results
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(height)
+            .map(|(i, path)| {
+                let line = path
+                    .strip_prefix(&find.root)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .to_string()
+                    .into_line();
+                if i == find.cursor {
+                    line.style(window.config.theme.highlight)
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<Line>>();
+        let status = if find.done {
+            format!("{} found", find.results.len())
+        } else {
+            "searching…".to_string()
+        };
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::bordered()
+                    .border_style(Style::new().fg(window.config.theme.border))
+                    .title(
+                        if find.directories {
+                            format!("Go to '{}' ({status})", find.pattern)
+                        } else {
+                            format!("Find '{}' ({status})", find.pattern)
+                        }
+                        .into_centered_line(),
+                    )
+                    .padding(Padding::horizontal(1)),
+            ),
+            popup,
+        );
+    }
+
+    if let Some(scroll) = window.error_log_scroll {
+        let popup = centered_rect(f.area(), 70, 60);
+        let lines = window
+            .error_log
+            .entries()
+            .rev()
+            .map(|err| {
+                let time = chrono::DateTime::<chrono::Local>::from(err.time).format("%H:%M:%S");
+                let style = match err.severity {
+                    Severity::Error => window.config.theme.error,
+                    Severity::Warning => window.config.theme.warning,
+                };
+                Line::from(vec![
+                    format!("{time} ").dim(),
+                    Span::styled(err.message.clone(), style),
+                ])
+            })
+            .collect::<Vec<Line>>();
+        let scroll = scroll.min(lines.len().saturating_sub(1) as u16);
+        window.error_log_scroll = Some(scroll);
+        let title = if lines.is_empty() {
+            "Errors (none)"
+        } else {
+            "Errors"
+        };
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .scroll((scroll, 0))
+                .block(
+                    Block::bordered()
+                        .border_style(Style::new().fg(window.config.theme.border))
+                        .title(title.into_centered_line())
+                        .padding(Padding::horizontal(1)),
+                ),
+            popup,
+        );
+    }
+
+    if let Some(scroll) = window.help_scroll {
+        let popup = centered_rect(f.area(), 60, 80);
+        let groups = window.config.keybindings();
+        let name_width = groups
+            .iter()
+            .flat_map(|(_, bindings)| bindings)
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut lines = Vec::new();
+        for (group, bindings) in &groups {
+            if !lines.is_empty() {
+                lines.push(Line::default());
+            }
+            lines.push(group.bold().into_line());
+            lines.extend(bindings.iter().map(|(name, key_event)| {
+                format!(
+                    "  {name:name_width$}  {}",
+                    window.config.binding_to_string(key_event)
+                )
+                .into_line()
+            }));
+        }
+        // don't let the help text scroll out of view completely
+        let scroll = scroll.min(lines.len().saturating_sub(1) as u16);
+        window.help_scroll = Some(scroll);
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines).scroll((scroll, 0)).block(
+                Block::bordered()
+                    .border_style(Style::new().fg(window.config.theme.border))
+                    .title("Help".into_centered_line())
+                    .padding(Padding::horizontal(1)),
+            ),
+            popup,
+        );
+    }
+}
+
+/// The working directory as it's shown in the title of its pane. Paths wider than `width` lose
+/// their leading components to a '…', keeping as many of the last ones as fit (but at least the
+/// last one).
+fn working_directory_title(dir: &Path, config: &Config, width: usize) -> String {
+    if !config.show_working_directory {
+        return String::new();
+    }
+    let full = dir.to_string_lossy().to_string();
+    if config.simple_working_directory {
+        return dir
+            .file_name()
+            .map_or(full, |name| name.to_string_lossy().to_string());
+    }
+    let names = dir
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if full.width() <= width || names.len() < 2 {
+        return full;
+    }
+    let breadcrumbs = |keep: usize| {
+        std::iter::once("…".into())
+            .chain(names[names.len() - keep..].iter().cloned())
+            .collect::<Vec<_>>()
+            .join(std::path::MAIN_SEPARATOR_STR)
+    };
+    (1..names.len())
+        .rev()
+        .map(breadcrumbs)
+        .find(|title| title.width() <= width)
+        .unwrap_or_else(|| breadcrumbs(1))
+}
+
+/// The size of entry `i` according to the metadata read with the directory, directories only
+/// count once their size was computed.
+fn entry_bytes(panel: &Panel, i: usize) -> u64 {
+    match &panel.metadata[i] {
+        Some(metadata) if metadata.is_file() => metadata.len(),
+        Some(metadata) if metadata.is_dir() => panel
+            .dir_sizes
+            .get(&panel.entries[i])
+            .flatten()
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// How many entries are selected and their size (or the size of the entry under the cursor),
+/// followed by the same for the whole directory.
+fn status_bar(panel: &Panel, size_format: SizeFormat) -> String {
+    let total = (0..panel.entries.len())
+        .map(|i| entry_bytes(panel, i))
+        .sum::<u64>();
+    let directory = format!(
+        "{} {}, {}",
+        panel.entries.len(),
+        if panel.entries.len() == 1 {
+            "entry"
+        } else {
+            "entries"
+        },
+        size_format.format(total)
+    );
+    if panel.has_selection() {
+        let indices = panel.selected_indices();
+        let selected = indices.iter().map(|&i| entry_bytes(panel, i)).sum::<u64>();
+        format!(
+            "{} selected ({}) of {directory}",
+            indices.len(),
+            size_format.format(selected)
+        )
+    } else if let Some(i) = panel
+        .table_state
+        .selected()
+        .filter(|&i| i < panel.entries.len())
+    {
+        format!(
+            "{} of {directory}",
+            size_format.format(entry_bytes(panel, i))
+        )
+    } else {
+        directory
+    }
+}
+
+impl PanelMode {
+    fn to_string(&self, config: &Config) -> String {
+        match *self {
+            PanelMode::Normal => config.normal_mode_text.clone(),
+            PanelMode::Prompt | PanelMode::Confirm => config.normal_mode_text.clone(),
+            PanelMode::Search => config.search_mode_text.clone(),
+            PanelMode::Insert => config.insert_mode_text.clone(),
+        }
+    }
+}
+
+/// Draws the progress of `transfer` over the bottom of the panel in `area`.
+fn render_transfer(f: &mut Frame, transfer: &Transfer, config: &Config, area: Rect) {
+    let area = Rect {
+        y: area.y + area.height.saturating_sub(2),
+        height: area.height.min(2),
+        ..area
+    };
+    let [status_area, gauge_area] =
+        Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(area);
+    let status = if transfer.is_cancelled() {
+        "Cancelling...".to_string()
+    } else {
+        format!(
+            "Copying {}({}/{} files, Esc to cancel)",
+            transfer
+                .current
+                .file_name()
+                .map(|n| format!("'{}' ", n.to_string_lossy()))
+                .unwrap_or_default(),
+            transfer.copied_files,
+            transfer.total_files
+        )
+    };
+    f.render_widget(Clear, area);
+    f.render_widget(status, status_area);
+    f.render_widget(
+        Gauge::default()
+            .gauge_style(config.theme.highlight)
+            .ratio(transfer.ratio())
+            .label(format!(
+                "{} / {}",
+                config.size_format.format(transfer.copied_bytes),
+                config.size_format.format(transfer.total_bytes)
+            )),
+        gauge_area,
+    );
+}
+
+fn render_preview(
+    f: &mut Frame,
+    cache: &mut PreviewCache,
+    highlighter: &Highlighter,
+    config: &Config,
+    path: Option<&PathBuf>,
+    area: Rect,
+) {
+    let mut block = Block::bordered()
+        .border_style(Style::new().fg(config.theme.border))
+        .padding(Padding::horizontal(1));
+    let Some(path) = path else {
+        f.render_widget(block, area);
+        return;
+    };
+    if let Some(name) = path.file_name() {
+        block = block.title(name.to_string_lossy().to_string().into_centered_line());
+    }
+    let width = block.inner(area).width as usize;
+    let height = block.inner(area).height as usize;
+    // long lines are cut off at the edge of the pane since the paragraph isn't wrapped
+    let lines = match cache.get(path, highlighter) {
+        Preview::Text(lines) => lines.iter().take(height).cloned().collect(),
+        Preview::Directory(names) if names.is_empty() => vec!["empty directory".dim().into_line()],
+        Preview::Directory(names) => names
+            .iter()
+            .take(height)
+            .map(|n| n.chars().take(width).collect::<String>().into_line())
+            .collect(),
+        Preview::Binary { bytes } => vec![
+            "binary file".dim().into_line(),
+            config.size_format.format(*bytes).dim().into_line(),
+        ],
+        Preview::Unreadable(err) => vec![err.clone().into_line().style(config.theme.error)],
+    };
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn format_time(time: SystemTime, config: &Config) -> String {
+    match config.time_style {
+        TimeStyle::Absolute => {
+            use std::fmt::Write;
+
+            let mut res = String::new();
+            let time = chrono::DateTime::<chrono::Local>::from(time);
+            // `to_string` would panic on an invalid format
+            if write!(res, "{}", time.format(&config.date_format)).is_err() {
+                return "invalid date_format".to_string();
+            }
+            res
+        }
+        // padded so whatever follows lines up
+        TimeStyle::Relative => format!("{:14}", relative_time(time)),
+    }
+}
+
+/// Describes how long ago `time` was, like "3 days ago".
+fn relative_time(time: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (n, unit) = match secs {
+        0..60 => return "just now".to_string(),
+        60..3600 => (secs / 60, "minute"),
+        3600..86400 => (secs / 3600, "hour"),
+        86400..2592000 => (secs / 86400, "day"),
+        2592000..31536000 => (secs / 2592000, "month"),
+        _ => (secs / 31536000, "year"),
+    };
+    format!("{n} {unit}{} ago", if n == 1 { "" } else { "s" })
+}
+
+/// Drops the first `n` characters of `line`, the rest keeps its style.
+fn skip_chars(line: Line<'_>, mut n: usize) -> Line<'_> {
+    let spans = line
+        .spans
+        .into_iter()
+        .filter_map(|span| {
+            let count = span.content.chars().count();
+            if n >= count {
+                n -= count;
+                return None;
+            }
+            let content = span.content.chars().skip(n).collect::<String>();
+            n = 0;
+            Some(Span::styled(content, span.style))
+        })
+        .collect::<Vec<_>>();
+    Line { spans, ..line }
+}
+
+/// Shortens `line` to `width` cells with an ellipsis where it's cut. With
+/// [`NameTruncation::Middle`] the last `keep` characters stay visible if they fit.
+fn truncate_line(
+    line: Line<'_>,
+    width: usize,
+    truncation: NameTruncation,
+    keep: usize,
+) -> Line<'_> {
+    if truncation == NameTruncation::None || width == 0 || line.width() <= width {
+        return line;
+    }
+    let chars = line
+        .spans
+        .iter()
+        .flat_map(|span| span.content.chars().map(|c| (c, span.style)))
+        .collect::<Vec<_>>();
+    let char_width = |&(c, _): &(char, Style)| c.width().unwrap_or(0);
+    // one cell goes to the ellipsis
+    let room = width - 1;
+    let tail_width = match truncation {
+        NameTruncation::Middle => {
+            let kept = chars[chars.len() - keep.min(chars.len())..]
+                .iter()
+                .map(char_width)
+                .sum::<usize>();
+            if kept < room {
+                kept.max(room / 2)
+            } else {
+                room / 2
+            }
+        }
+        _ => 0,
+    };
+    let mut tail_len = 0;
+    let mut used = 0;
+    for c in chars.iter().rev() {
+        if used + char_width(c) > tail_width {
+            break;
+        }
+        used += char_width(c);
+        tail_len += 1;
+    }
+    let mut head_len = 0;
+    for c in &chars {
+        if used + char_width(c) > room {
+            break;
+        }
+        used += char_width(c);
+        head_len += 1;
+    }
+    let ellipsis = ('…', chars[head_len].1);
+    let chars = chars[..head_len]
+        .iter()
+        .chain(std::iter::once(&ellipsis))
+        .chain(&chars[chars.len() - tail_len..]);
+    let mut spans: Vec<Span> = Vec::new();
+    for &(c, style) in chars {
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push(c),
+            _ => spans.push(Span::styled(c.to_string(), style)),
+        }
+    }
+    Line { spans, ..line }
+}
+
+/// Returns the `ls -F` style suffix for an entry.
+fn type_indicator(metadata: Option<&Metadata>, is_symlink: bool) -> &'static str {
+    if is_symlink {
+        "@"
+    } else if metadata.is_some_and(|m| m.is_dir()) {
+        "/"
+    } else if metadata.is_some_and(|m| m.is_file() && mode(m).is_some_and(|mode| mode & 0o111 != 0))
+    {
+        "*"
+    } else {
+        ""
+    }
+}
+
+/// Returns a rectangle of the given size (in percent) centered inside `area`.
+fn centered_rect(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let [area] = Layout::vertical([Constraint::Percentage(percent_y)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::horizontal([Constraint::Percentage(percent_x)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
+
+pub trait IntoLine<'a> {
+    fn into_line(self) -> Line<'a>;
+    fn into_centered_line(self) -> Line<'a>;
+    fn into_right_aligned_line(self) -> Line<'a>;
+    fn into_left_aligned_line(self) -> Line<'a>;
+}
+impl<'a, T> IntoLine<'a> for T
+where
+    T: Into<Line<'a>>,
+{
+    fn into_line(self) -> Line<'a> {
+        self.into()
+    }
+
+    fn into_centered_line(self) -> Line<'a> {
+        self.into_line().centered()
+    }
+
+    fn into_right_aligned_line(self) -> Line<'a> {
+        self.into_line().right_aligned()
+    }
+
+    fn into_left_aligned_line(self) -> Line<'a> {
+        self.into_line().left_aligned()
+    }
+}
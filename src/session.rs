@@ -0,0 +1,98 @@
+use crate::{PathKind, WalkedError};
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// The pane layout of a window: the working directory of each pane, row by row, and which pane
+/// is active. Saved with the `save_session` key and restored on startup.
+pub struct Session {
+    pub rows: Vec<Vec<PathBuf>>,
+    pub focus: (usize, usize),
+}
+
+impl Session {
+    /// Reads a session saved with [`Session::save`]. Rows without panes are left out, so the
+    /// session may still turn out to be empty.
+    pub fn load(file: &Path) -> Result<Self, WalkedError> {
+        let content = std::fs::read_to_string(file).map_err(|err| {
+            WalkedError::from_io(
+                &err,
+                file,
+                PathKind::File,
+                format!("Couldn't read the session '{}'", file.display()),
+            )
+        })?;
+        let table = content.parse::<Table>().map_err(|err| {
+            WalkedError::Message(format!(
+                "Couldn't parse the session '{}': {}",
+                file.display(),
+                err.message()
+            ))
+        })?;
+        let rows = table
+            .get("rows")
+            .and_then(Value::as_array)
+            .map(|rows| {
+                rows.iter()
+                    .filter_map(|row| row.get("panes").and_then(Value::as_array))
+                    .map(|panes| {
+                        panes
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .map(PathBuf::from)
+                            .collect::<Vec<_>>()
+                    })
+                    .filter(|row| !row.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let focus = table
+            .get("focus")
+            .and_then(Value::as_array)
+            .and_then(|focus| match focus.as_slice() {
+                [i, j] => Some((i.as_integer()?, j.as_integer()?)),
+                _ => None,
+            })
+            .map_or((0, 0), |(i, j)| (i.max(0) as usize, j.max(0) as usize));
+        Ok(Self { rows, focus })
+    }
+
+    /// Writes the session to `file`, creating the directory it's in if needed.
+    pub fn save(&self, file: &Path) -> Result<(), WalkedError> {
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                let panes = row
+                    .iter()
+                    .map(|dir| Value::String(dir.to_string_lossy().to_string()))
+                    .collect();
+                Value::Table(Table::from_iter([(
+                    "panes".to_string(),
+                    Value::Array(panes),
+                )]))
+            })
+            .collect();
+        let table = Table::from_iter([
+            (
+                "focus".to_string(),
+                Value::Array(vec![
+                    Value::Integer(self.focus.0 as i64),
+                    Value::Integer(self.focus.1 as i64),
+                ]),
+            ),
+            ("rows".to_string(), Value::Array(rows)),
+        ]);
+        let error = |err: std::io::Error| {
+            WalkedError::from_io(
+                &err,
+                file,
+                PathKind::File,
+                format!("Couldn't save the session to '{}'", file.display()),
+            )
+        };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir).map_err(error)?;
+        }
+        std::fs::write(file, table.to_string()).map_err(error)
+    }
+}
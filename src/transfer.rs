@@ -0,0 +1,215 @@
+use crate::{WalkedError, trash::TrashedEntry, window::copy_entry_with};
+use std::{
+    fs::File,
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, Sender, channel},
+    },
+};
+
+/// Copies smaller than this (in bytes and in files) are done right away, a worker thread isn't
+/// worth it for them. Files smaller than `BACKGROUND_BYTES` are hashed right away too.
+pub const BACKGROUND_BYTES: u64 = 16 * 1024 * 1024;
+pub const BACKGROUND_FILES: u64 = 256;
+/// How much of a file is read at once when it's copied or hashed on a worker thread.
+pub(crate) const CHUNK_SIZE: usize = 1024 * 1024;
+
+enum Update {
+    File(PathBuf),
+    Bytes(u64),
+    Done(Finished),
+}
+
+/// The outcome of a transfer once the worker is done with it.
+pub struct Finished {
+    /// The entries that were copied completely, as `(source, copy)` pairs.
+    pub copies: Vec<(PathBuf, PathBuf)>,
+    pub errors: Vec<WalkedError>,
+    pub cancelled: bool,
+}
+
+/// Entries being copied on a worker thread, along with how far it got.
+pub struct Transfer {
+    pub total_files: u64,
+    pub total_bytes: u64,
+    pub copied_files: u64,
+    pub copied_bytes: u64,
+    /// The file being copied right now.
+    pub current: PathBuf,
+    /// Entries that were trashed to make room for the copies, put back if their copy fails.
+    pub replaced: Vec<TrashedEntry>,
+    cancel: Arc<AtomicBool>,
+    updates: Receiver<Update>,
+}
+
+impl Transfer {
+    /// Starts copying each `(source, destination)` pair, the destinations shouldn't exist yet.
+    /// Files are reflinked if `reflink` is set and the filesystem supports it.
+    pub fn start(
+        jobs: Vec<(PathBuf, PathBuf)>,
+        total_files: u64,
+        total_bytes: u64,
+        reflink: bool,
+    ) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, updates) = channel();
+        let worker_cancel = cancel.clone();
+        std::thread::spawn(move || work(jobs, reflink, &worker_cancel, &tx));
+        Self {
+            total_files,
+            total_bytes,
+            copied_files: 0,
+            copied_bytes: 0,
+            current: PathBuf::new(),
+            replaced: Vec::new(),
+            cancel,
+            updates,
+        }
+    }
+
+    /// Stops copying, whatever was copied of the current entry is removed again.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Cancels the transfer and waits until the worker cleaned up after itself.
+    pub fn cancel_and_wait(self) {
+        self.cancel();
+        while let Ok(update) = self.updates.recv() {
+            if let Update::Done(_) = update {
+                break;
+            }
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Takes in the progress reported since the last call, returns the outcome once the worker is
+    /// done.
+    pub fn poll(&mut self) -> Option<Finished> {
+        while let Ok(update) = self.updates.try_recv() {
+            match update {
+                Update::File(path) => {
+                    self.copied_files += 1;
+                    self.current = path;
+                }
+                Update::Bytes(bytes) => self.copied_bytes += bytes,
+                Update::Done(finished) => return Some(finished),
+            }
+        }
+        None
+    }
+
+    pub fn ratio(&self) -> f64 {
+        if self.total_bytes == 0 {
+            self.copied_files as f64 / self.total_files.max(1) as f64
+        } else {
+            self.copied_bytes as f64 / self.total_bytes as f64
+        }
+        .min(1.0)
+    }
+}
+
+fn work(jobs: Vec<(PathBuf, PathBuf)>, reflink: bool, cancel: &AtomicBool, tx: &Sender<Update>) {
+    let mut finished = Finished {
+        copies: Vec::new(),
+        errors: Vec::new(),
+        cancelled: false,
+    };
+    for (src, dest) in jobs {
+        let mut errors = Vec::new();
+        let copied = copy_entry_with(&src, &dest, &mut errors, &mut |src, dest| {
+            if reflink && try_reflink(src, dest, tx)? {
+                return Ok(());
+            }
+            copy_file(src, dest, cancel, tx)
+        });
+        if cancel.load(Ordering::Relaxed) {
+            // whatever was copied of the interrupted entry is incomplete, so it's removed
+            if dest.symlink_metadata().is_ok() {
+                let _ = if dest.is_dir() {
+                    std::fs::remove_dir_all(&dest)
+                } else {
+                    std::fs::remove_file(&dest)
+                };
+            }
+            finished.cancelled = true;
+            break;
+        }
+        finished.errors.extend(errors);
+        if copied.created() {
+            finished.copies.push((src, dest));
+        }
+    }
+    let _ = tx.send(Update::Done(finished));
+}
+
+/// Copies a file in chunks, reporting each of them.
+fn copy_file(
+    src: &Path,
+    dest: &Path,
+    cancel: &AtomicBool,
+    tx: &Sender<Update>,
+) -> std::io::Result<()> {
+    let _ = tx.send(Update::File(src.to_path_buf()));
+    let mut reader = File::open(src)?;
+    let mut writer = File::create_new(dest)?;
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(std::io::ErrorKind::Interrupted.into());
+        }
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        writer.write_all(&buf[..n])?;
+        let _ = tx.send(Update::Bytes(n as u64));
+    }
+    // `std::fs::copy` keeps the permissions too
+    std::fs::set_permissions(dest, reader.metadata()?.permissions())
+}
+
+/// Reflinks a file as a whole, returns false if the filesystem doesn't support it and the file
+/// has to be copied after all.
+fn try_reflink(src: &Path, dest: &Path, tx: &Sender<Update>) -> std::io::Result<bool> {
+    match reflink_copy::reflink(src, dest) {
+        Ok(()) => {
+            let _ = tx.send(Update::File(src.to_path_buf()));
+            let _ = tx.send(Update::Bytes(dest.metadata()?.len()));
+            Ok(true)
+        }
+        Err(err)
+            if matches!(
+                err.kind(),
+                ErrorKind::NotFound | ErrorKind::PermissionDenied | ErrorKind::AlreadyExists
+            ) =>
+        {
+            Err(err)
+        }
+        Err(_) => Ok(false),
+    }
+}
+
+/// How many files and bytes copying `path` involves, following symlinks like copying does.
+pub fn count(path: &Path) -> (u64, u64) {
+    let Ok(metadata) = path.metadata() else {
+        return (0, 0);
+    };
+    if metadata.is_dir() {
+        std::fs::read_dir(path)
+            .map(|dir| {
+                dir.flatten()
+                    .map(|e| count(&e.path()))
+                    .fold((0, 0), |(files, bytes), (f, b)| (files + f, bytes + b))
+            })
+            .unwrap_or((0, 0))
+    } else {
+        (1, metadata.len())
+    }
+}
@@ -0,0 +1,123 @@
+use crate::WalkedError;
+use std::path::{Path, PathBuf};
+
+/// An entry that was moved to the trash.
+pub struct TrashedEntry {
+    pub original: PathBuf,
+    /// The entry as the trash lists it, `None` on platforms whose trash can't be listed (macOS),
+    /// where entries can't be restored either.
+    item: Option<::trash::TrashItem>,
+}
+
+impl TrashedEntry {
+    /// Moves the entry back to where it was trashed from.
+    pub fn restore(&self) -> Result<(), WalkedError> {
+        if self.original.symlink_metadata().is_ok() {
+            return Err(WalkedError::Message(format!(
+                "'{}' already exists",
+                self.original.display()
+            )));
+        }
+        let Some(item) = &self.item else {
+            return Err(WalkedError::Message(format!(
+                "Couldn't find '{}' in the trash",
+                self.original.display()
+            )));
+        };
+        restore(item.clone()).map_err(|err| {
+            WalkedError::Message(format!(
+                "Couldn't restore '{}': {err}",
+                self.original.display()
+            ))
+        })
+    }
+
+    /// Moves the entry to the trash again after it was restored.
+    pub fn retrash(&mut self) -> Result<(), WalkedError> {
+        *self = trash(&self.original)?;
+        Ok(())
+    }
+}
+
+/// Moves `path` to the trash of the platform.
+pub fn trash(path: &Path) -> Result<TrashedEntry, WalkedError> {
+    let original = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    if let Err(err) = ::trash::delete(&original) {
+        return Err(WalkedError::Message(format!(
+            "Couldn't move '{}' to the trash: {err}",
+            original.display()
+        )));
+    }
+    Ok(TrashedEntry {
+        item: find(&original),
+        original,
+    })
+}
+
+/// The most recently trashed entry that was at `original`.
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn find(original: &Path) -> Option<::trash::TrashItem> {
+    // the trash remembers the parent directory with its symlinks resolved
+    let original = original
+        .parent()
+        .and_then(|parent| parent.canonicalize().ok())
+        .zip(original.file_name())
+        .map_or_else(
+            || original.to_path_buf(),
+            |(parent, name)| parent.join(name),
+        );
+    ::trash::os_limited::list()
+        .ok()?
+        .into_iter()
+        .filter(|item| item.original_path() == original)
+        .max_by_key(|item| item.time_deleted)
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+fn find(_original: &Path) -> Option<::trash::TrashItem> {
+    None
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn restore(item: ::trash::TrashItem) -> Result<(), ::trash::Error> {
+    ::trash::os_limited::restore_all([item])
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+fn restore(_item: ::trash::TrashItem) -> Result<(), ::trash::Error> {
+    Err(::trash::Error::Unknown {
+        description: "restoring isn't supported on this platform".to_string(),
+    })
+}
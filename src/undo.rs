@@ -0,0 +1,507 @@
+use crate::{
+    PathKind, WalkedError,
+    trash::TrashedEntry,
+    window::{copy_entry, create_symlink, move_entry, rename_entries},
+};
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// How many operations are remembered before the oldest ones are forgotten.
+pub const UNDO_LIMIT: usize = 100;
+
+/// A reversible filesystem operation.
+pub enum Operation {
+    Create {
+        path: PathBuf,
+        path_kind: PathKind,
+    },
+    /// `(source, destination)` pairs created by a duplicate or paste.
+    Copy {
+        copies: Vec<(PathBuf, PathBuf)>,
+        /// Entries that were in the way of an overwriting paste.
+        replaced: Vec<TrashedEntry>,
+        /// When the copies were done, copies modified after it aren't removed.
+        copied_at: SystemTime,
+    },
+    /// `(source, destination)` pairs moved by pasting cut entries.
+    Move {
+        moves: Vec<(PathBuf, PathBuf)>,
+        replaced: Vec<TrashedEntry>,
+    },
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// `(from, to)` pairs renamed together, the names may have been swapped among them.
+    BulkRename {
+        renames: Vec<(PathBuf, PathBuf)>,
+    },
+    Trash {
+        entries: Vec<TrashedEntry>,
+    },
+    Symlink {
+        target: PathBuf,
+        link: PathBuf,
+    },
+}
+
+impl Operation {
+    pub fn describe(&self) -> String {
+        match self {
+            Operation::Create { path, path_kind } => format!(
+                "Create {} '{}'",
+                match path_kind {
+                    PathKind::Dir => "directory",
+                    _ => "file",
+                },
+                path.display()
+            ),
+            Operation::Copy { copies, .. } => {
+                if copies.len() == 1 {
+                    format!(
+                        "Copy '{}' to '{}'",
+                        copies[0].0.display(),
+                        copies[0].1.display()
+                    )
+                } else {
+                    format!("Copy {} entries", copies.len())
+                }
+            }
+            Operation::Move { moves, .. } => {
+                if moves.len() == 1 {
+                    format!(
+                        "Move '{}' to '{}'",
+                        moves[0].0.display(),
+                        moves[0].1.display()
+                    )
+                } else {
+                    format!("Move {} entries", moves.len())
+                }
+            }
+            Operation::Rename { from, to } => {
+                format!("Rename '{}' to '{}'", from.display(), to.display())
+            }
+            Operation::BulkRename { renames } => {
+                if renames.len() == 1 {
+                    format!(
+                        "Rename '{}' to '{}'",
+                        renames[0].0.display(),
+                        renames[0].1.display()
+                    )
+                } else {
+                    format!("Rename {} entries", renames.len())
+                }
+            }
+            Operation::Trash { entries } => {
+                if entries.len() == 1 {
+                    format!("Trash '{}'", entries[0].original.display())
+                } else {
+                    format!("Trash {} entries", entries.len())
+                }
+            }
+            Operation::Symlink { target, link } => {
+                format!("Link '{}' to '{}'", link.display(), target.display())
+            }
+        }
+    }
+
+    /// Reverts the operation, returns false if it couldn't be reverted. Files that have to be
+    /// copied are reflinked if `reflink` is set.
+    fn revert(&self, errors: &mut Vec<WalkedError>, reflink: bool) -> bool {
+        match self {
+            Operation::Create { path, path_kind } => {
+                let res = match path_kind {
+                    PathKind::Dir => std::fs::remove_dir(path),
+                    _ => {
+                        if std::fs::metadata(path).is_ok_and(|m| m.len() > 0) {
+                            errors.push(WalkedError::Message(format!(
+                                "'{}' was modified after it was created",
+                                path.display()
+                            )));
+                            return false;
+                        }
+                        std::fs::remove_file(path)
+                    }
+                };
+                if let Err(err) = res {
+                    errors.push(WalkedError::from_io(
+                        &err,
+                        path,
+                        *path_kind,
+                        format!("Couldn't remove '{}'", path.display()),
+                    ));
+                    return false;
+                }
+                true
+            }
+            Operation::Copy {
+                copies,
+                replaced,
+                copied_at,
+            } => {
+                // nothing is removed if any copy was changed since, so the changes aren't lost
+                for (_, dest) in copies {
+                    if let Some(path) = modified_since(dest, *copied_at) {
+                        errors.push(WalkedError::Message(format!(
+                            "'{}' was modified after it was copied",
+                            path.display()
+                        )));
+                        return false;
+                    }
+                }
+                let mut ok = true;
+                for (_, dest) in copies.iter().rev() {
+                    let (res, path_kind) = if dest.is_dir() {
+                        (std::fs::remove_dir_all(dest), PathKind::Dir)
+                    } else {
+                        (std::fs::remove_file(dest), PathKind::File)
+                    };
+                    if let Err(err) = res {
+                        errors.push(WalkedError::from_io(
+                            &err,
+                            dest,
+                            path_kind,
+                            format!("Couldn't remove '{}'", dest.display()),
+                        ));
+                        ok = false;
+                    }
+                }
+                ok && restore(replaced, errors)
+            }
+            Operation::Move { moves, replaced } => {
+                let mut ok = true;
+                for (src, dest) in moves.iter().rev() {
+                    ok &= move_to(dest, src, errors, reflink);
+                }
+                ok && restore(replaced, errors)
+            }
+            Operation::Rename { from, to } => rename(to, from, errors),
+            Operation::BulkRename { renames } => {
+                let reverted = renames
+                    .iter()
+                    .map(|(from, to)| (to.clone(), from.clone()))
+                    .collect::<Vec<_>>();
+                rename_entries(&reverted, errors).len() == renames.len()
+            }
+            Operation::Trash { entries } => restore(entries, errors),
+            Operation::Symlink { link, .. } => {
+                // directory symlinks are removed like directories on Windows
+                let res = if cfg!(windows) && link.is_dir() {
+                    std::fs::remove_dir(link)
+                } else {
+                    std::fs::remove_file(link)
+                };
+                if let Err(err) = res {
+                    errors.push(WalkedError::from_io(
+                        &err,
+                        link,
+                        PathKind::Ambigious,
+                        format!("Couldn't remove '{}'", link.display()),
+                    ));
+                    return false;
+                }
+                true
+            }
+        }
+    }
+
+    /// Applies the operation again after it was reverted, returns false if it couldn't be applied.
+    fn apply(&mut self, errors: &mut Vec<WalkedError>, reflink: bool) -> bool {
+        match self {
+            Operation::Create { path, path_kind } => {
+                let res = match path_kind {
+                    PathKind::Dir => std::fs::create_dir(&path),
+                    _ => std::fs::File::create_new(&path).map(|_| ()),
+                };
+                if let Err(err) = res {
+                    errors.push(WalkedError::from_io(
+                        &err,
+                        path,
+                        *path_kind,
+                        format!("Couldn't create '{}'", path.display()),
+                    ));
+                    return false;
+                }
+                true
+            }
+            Operation::Copy {
+                copies,
+                replaced,
+                copied_at,
+            } => {
+                if !retrash(replaced, errors) {
+                    return false;
+                }
+                let mut ok = true;
+                for (src, dest) in copies.iter() {
+                    if dest.exists() {
+                        errors.push(WalkedError::Message(format!(
+                            "'{}' already exists",
+                            dest.display()
+                        )));
+                        ok = false;
+                    } else if !copy_entry(src, dest, errors, reflink).created() {
+                        ok = false;
+                    }
+                }
+                *copied_at = SystemTime::now();
+                ok
+            }
+            Operation::Move { moves, replaced } => {
+                if !retrash(replaced, errors) {
+                    return false;
+                }
+                let mut ok = true;
+                for (src, dest) in moves.iter() {
+                    ok &= move_to(src, dest, errors, reflink);
+                }
+                ok
+            }
+            Operation::Rename { from, to } => rename(from, to, errors),
+            Operation::BulkRename { renames } => {
+                rename_entries(renames, errors).len() == renames.len()
+            }
+            Operation::Trash { entries } => retrash(entries, errors),
+            Operation::Symlink { target, link } => {
+                if link.symlink_metadata().is_ok() {
+                    errors.push(WalkedError::Message(format!(
+                        "'{}' already exists",
+                        link.display()
+                    )));
+                    return false;
+                }
+                create_symlink(target, link, errors)
+            }
+        }
+    }
+}
+
+/// Takes trashed entries out of the trash again, the last one trashed first.
+fn restore(entries: &[TrashedEntry], errors: &mut Vec<WalkedError>) -> bool {
+    let mut ok = true;
+    for entry in entries.iter().rev() {
+        if let Err(err) = entry.restore() {
+            errors.push(err);
+            ok = false;
+        }
+    }
+    ok
+}
+
+fn retrash(entries: &mut [TrashedEntry], errors: &mut Vec<WalkedError>) -> bool {
+    let mut ok = true;
+    for entry in entries.iter_mut() {
+        if let Err(err) = entry.retrash() {
+            errors.push(err);
+            ok = false;
+        }
+    }
+    ok
+}
+
+/// Returns `path` or the first entry inside it that was modified after `time`.
+fn modified_since(path: &Path, time: SystemTime) -> Option<PathBuf> {
+    let metadata = path.symlink_metadata().ok()?;
+    if metadata.modified().is_ok_and(|modified| modified > time) {
+        return Some(path.to_path_buf());
+    }
+    if metadata.is_dir() {
+        for entry in std::fs::read_dir(path).ok()?.flatten() {
+            if let Some(path) = modified_since(&entry.path(), time) {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+fn rename(from: &Path, to: &Path, errors: &mut Vec<WalkedError>) -> bool {
+    if to.exists() {
+        errors.push(WalkedError::Message(format!(
+            "'{}' already exists",
+            to.display()
+        )));
+        return false;
+    }
+    if let Err(err) = std::fs::rename(from, to) {
+        errors.push(WalkedError::from_io(
+            &err,
+            from,
+            PathKind::Ambigious,
+            format!("Couldn't rename '{}' to '{}'", from.display(), to.display()),
+        ));
+        return false;
+    }
+    true
+}
+
+fn move_to(from: &Path, to: &Path, errors: &mut Vec<WalkedError>, reflink: bool) -> bool {
+    if to.exists() {
+        errors.push(WalkedError::Message(format!(
+            "'{}' already exists",
+            to.display()
+        )));
+        return false;
+    }
+    move_entry(from, to, errors, reflink)
+}
+
+/// Operations done through walked, in the order they were done.
+/// Operations after `applied` have been undone and can be redone.
+#[derive(Default)]
+pub struct UndoStack {
+    operations: Vec<Operation>,
+    applied: usize,
+}
+
+impl UndoStack {
+    /// Records a new operation, forgetting everything that was undone before it.
+    pub fn push(&mut self, op: Operation) {
+        self.operations.truncate(self.applied);
+        self.operations.push(op);
+        if self.operations.len() > UNDO_LIMIT {
+            self.operations.remove(0);
+        }
+        self.applied = self.operations.len();
+    }
+
+    /// Returns false if nothing was undone.
+    pub fn undo(&mut self, errors: &mut Vec<WalkedError>, reflink: bool) -> bool {
+        if self.applied == 0 {
+            errors.push(WalkedError::Message("Nothing to undo".to_string()));
+            return false;
+        }
+        if self.operations[self.applied - 1].revert(errors, reflink) {
+            self.applied -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns false if nothing was redone.
+    pub fn redo(&mut self, errors: &mut Vec<WalkedError>, reflink: bool) -> bool {
+        if self.applied >= self.operations.len() {
+            errors.push(WalkedError::Message("Nothing to redo".to_string()));
+            return false;
+        }
+        if self.operations[self.applied].apply(errors, reflink) {
+            self.applied += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Every remembered operation along with whether it is currently applied, oldest first.
+    pub fn log(&self) -> impl DoubleEndedIterator<Item = (&Operation, bool)> + ExactSizeIterator {
+        self.operations
+            .iter()
+            .enumerate()
+            .map(|(i, op)| (op, i < self.applied))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::Copied;
+    use std::time::Duration;
+
+    #[test]
+    fn rename_is_undone_and_redone() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("a"), dir.path().join("b"));
+        std::fs::write(&from, "content").unwrap();
+        std::fs::rename(&from, &to).unwrap();
+        let mut stack = UndoStack::default();
+        stack.push(Operation::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        });
+        let mut errors = Vec::new();
+
+        assert!(stack.undo(&mut errors, false));
+        assert!(from.exists() && !to.exists());
+        assert!(stack.redo(&mut errors, false));
+        assert!(!from.exists() && to.exists());
+        assert!(errors.is_empty());
+        assert_eq!(std::fs::read_to_string(&to).unwrap(), "content");
+    }
+
+    #[test]
+    fn rename_isnt_undone_over_an_existing_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("a"), dir.path().join("b"));
+        std::fs::write(&to, "renamed").unwrap();
+        std::fs::write(&from, "new").unwrap();
+        let mut stack = UndoStack::default();
+        stack.push(Operation::Rename {
+            from: from.clone(),
+            to,
+        });
+        let mut errors = Vec::new();
+
+        assert!(!stack.undo(&mut errors, false));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(std::fs::read_to_string(&from).unwrap(), "new");
+    }
+
+    fn copied(dir: &Path) -> (PathBuf, PathBuf, UndoStack) {
+        let (src, dest) = (dir.join("src"), dir.join("dest"));
+        std::fs::create_dir(&src).unwrap();
+        std::fs::write(src.join("file"), "original").unwrap();
+        assert_eq!(
+            copy_entry(&src, &dest, &mut Vec::new(), false),
+            Copied::Fully
+        );
+        let mut stack = UndoStack::default();
+        stack.push(Operation::Copy {
+            copies: vec![(src.clone(), dest.clone())],
+            replaced: Vec::new(),
+            copied_at: SystemTime::now(),
+        });
+        (src, dest, stack)
+    }
+
+    #[test]
+    fn copy_is_undone_and_redone() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src, dest, mut stack) = copied(dir.path());
+        let mut errors = Vec::new();
+
+        assert!(stack.undo(&mut errors, false));
+        assert!(src.exists() && !dest.exists());
+        assert!(stack.redo(&mut errors, false));
+        assert!(errors.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(dest.join("file")).unwrap(),
+            "original"
+        );
+        // the copy made by the redo can be undone again
+        assert!(stack.undo(&mut errors, false));
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn copy_modified_since_isnt_undone() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, dest, mut stack) = copied(dir.path());
+        let file = std::fs::File::options()
+            .write(true)
+            .open(dest.join("file"))
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        let mut errors = Vec::new();
+
+        assert!(!stack.undo(&mut errors, false));
+        assert!(dest.join("file").exists());
+        assert!(matches!(
+            &errors[..],
+            [WalkedError::Message(msg)] if msg.contains("was modified after it was copied")
+        ));
+    }
+}
@@ -0,0 +1,98 @@
+use notify::{RecursiveMode, Watcher};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, channel},
+    time::{Duration, Instant},
+};
+
+/// How often the main loop checks for filesystem events while waiting for input.
+pub const WATCH_TICK: Duration = Duration::from_millis(100);
+/// Changes are reported once no more events arrived for this long...
+const DEBOUNCE: Duration = Duration::from_millis(250);
+/// ...or once this much time passed since the first of them, so a long burst (like extracting a
+/// big archive) still shows progress without refreshing on every single event.
+const MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// Watches the working directories of the panels for changes made by other programs.
+pub struct DirWatcher {
+    watcher: notify::RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    watched: Vec<PathBuf>,
+    changed: HashSet<PathBuf>,
+    /// When the first and the last unreported event arrived.
+    pending: Option<(Instant, Instant)>,
+}
+
+impl DirWatcher {
+    /// Returns `None` if the platform's watcher couldn't be started.
+    pub fn new() -> Option<Self> {
+        let (tx, events) = channel();
+        let watcher = notify::recommended_watcher(tx).ok()?;
+        Some(Self {
+            watcher,
+            events,
+            watched: Vec::new(),
+            changed: HashSet::new(),
+            pending: None,
+        })
+    }
+
+    /// Makes `dirs` the watched directories, registering and unregistering only what changed.
+    pub fn watch<'a>(&mut self, dirs: impl Iterator<Item = &'a Path>) {
+        let dirs = dirs.map(Path::to_path_buf).collect::<Vec<_>>();
+        for dir in self.watched.iter() {
+            if !dirs.contains(dir) {
+                let _ = self.watcher.unwatch(dir);
+            }
+        }
+        let mut watched = Vec::new();
+        for dir in dirs {
+            if watched.contains(&dir) {
+                continue;
+            }
+            // directories that can't be watched simply don't refresh by themselves
+            if self.watched.contains(&dir)
+                || self
+                    .watcher
+                    .watch(&dir, RecursiveMode::NonRecursive)
+                    .is_ok()
+            {
+                watched.push(dir);
+            }
+        }
+        self.watched = watched;
+    }
+
+    /// Returns the directories that changed, once the events about them have settled down.
+    pub fn changes(&mut self) -> Option<HashSet<PathBuf>> {
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+            if event.kind.is_access() {
+                continue;
+            }
+            for path in event.paths {
+                // an event is about an entry inside a watched directory or the directory itself
+                if let Some(parent) = path.parent() {
+                    self.changed.insert(parent.to_path_buf());
+                }
+                self.changed.insert(path);
+            }
+            let now = Instant::now();
+            self.pending = Some(match self.pending {
+                Some((first, _)) => (first, now),
+                None => (now, now),
+            });
+        }
+
+        let (first, last) = self.pending?;
+        if last.elapsed() >= DEBOUNCE || first.elapsed() >= MAX_DELAY {
+            self.pending = None;
+            Some(std::mem::take(&mut self.changed))
+        } else {
+            None
+        }
+    }
+}
@@ -1,46 +1,604 @@
-use crate::{PathKind, WalkedError, config::Config};
+use crate::{
+    PathKind, WalkedError, archive,
+    checksum::Checksum,
+    completion::{self, Completion},
+    config::{
+        Config, DEFAULT_COLLISION_SUFFIX, PasteConflictPolicy, SizeFormat, SortMode,
+        TypeConflictPolicy, expand_path,
+    },
+    dir_reader::{DirReader, Entry, READ_BATCH, read_entry},
+    dir_size::DirSizes,
+    disk::DiskUsageCache,
+    error_log::ErrorLog,
+    find::Find,
+    frecency::Frecency,
+    git::{self, GitStatus},
+    history::History,
+    preview::{Highlighter, PreviewCache},
+    session::Session,
+    transfer::{self, BACKGROUND_BYTES, BACKGROUND_FILES, Transfer},
+    trash::{self, TrashedEntry},
+    undo::{Operation, UndoStack},
+};
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
-use ratatui::widgets::TableState;
-use std::{path::PathBuf, str::FromStr};
+use ratatui::{layout::Rect, widgets::TableState};
+use regex::Regex;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet, VecDeque},
+    ffi::OsString,
+    fs::Metadata,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
 
 pub const TABLE_HEADER_MIN_WIDTH: u16 = 8;
-// pub const NEW_DIRECTORY_TEXT: &'static str = ".#NEWDIR";
-// pub const NEW_FILE_TEXT: &'static str = ".#NEWFILE";
+/// How long the first key of a two key sequence (like `gg`) waits for the second one.
+pub const KEY_SEQUENCE_TIMEOUT: Duration = Duration::from_millis(800);
+/// How long a typed entry number waits for more digits before it is jumped to.
+pub const NUMBER_PREFIX_TIMEOUT: Duration = Duration::from_millis(1000);
+/// How many characters `scroll_left` and `scroll_right` scroll names by.
+pub const NAME_SCROLL_STEP: usize = 4;
+/// Names new entries get until they are renamed, when `inline_new_entries` is set.
+pub const NEW_DIRECTORY_TEXT: &str = "NEWDIR";
+pub const NEW_FILE_TEXT: &str = "NEWFILE";
 
 #[derive(Clone)]
 pub enum CommandKind {
     NewFile,
     NewDirectory,
     IncrementalSearch,
+    Jump,
+    Filter,
+    Goto,
+    Shell,
+    Chmod,
+    Compress,
+    PatternRename,
+    NumberRename,
+    SelectGlob,
+    Find,
     #[allow(dead_code)]
     Custom(String), // NOTE: For future if we need plugins or such
 }
 
-impl ToString for CommandKind {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for CommandKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            CommandKind::NewFile => "new-file".to_string(),
-            CommandKind::NewDirectory => "new-directory".to_string(),
-            CommandKind::IncrementalSearch => "incremental-search".to_string(),
-            CommandKind::Custom(s) => s.clone(),
+            CommandKind::NewFile => write!(f, "new-file"),
+            CommandKind::NewDirectory => write!(f, "new-directory"),
+            CommandKind::IncrementalSearch => write!(f, "incremental-search"),
+            CommandKind::Jump => write!(f, "jump"),
+            CommandKind::Filter => write!(f, "filter"),
+            CommandKind::Goto => write!(f, "goto"),
+            CommandKind::Shell => write!(f, "shell"),
+            CommandKind::Chmod => write!(f, "chmod"),
+            CommandKind::Compress => write!(f, "compress"),
+            CommandKind::PatternRename => write!(f, "search/replace"),
+            CommandKind::NumberRename => write!(f, "number"),
+            CommandKind::SelectGlob => write!(f, "select"),
+            CommandKind::Find => write!(f, "find"),
+            CommandKind::Custom(s) => write!(f, "{s}"),
         }
     }
 }
 
-pub struct Command {
+#[derive(Clone)]
+pub(crate) struct Command {
     pub kind: CommandKind,
     pub arg: String,
 }
 
+/// An action the `repeat` key does again, on whatever is selected by then.
+#[derive(Clone)]
+pub(crate) enum RepeatableAction {
+    /// A key that acts on the selected entries by itself, like `duplicate` or `paste`.
+    Key(KeyEvent),
+    /// A prompt that was answered, it's answered the same way again.
+    Command(Command),
+}
+
+/// Entries that were copied or cut, waiting to be pasted.
+pub enum Clipboard {
+    Copy(Vec<PathBuf>),
+    Cut(Vec<PathBuf>),
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Clipboard::Copy(Vec::new())
+    }
+}
+
+impl Clipboard {
+    pub fn paths(&self) -> &[PathBuf] {
+        match self {
+            Clipboard::Copy(paths) | Clipboard::Cut(paths) => paths,
+        }
+    }
+
+    /// Returns true if `path` was cut and will be moved on the next paste.
+    pub fn is_cut(&self, path: &Path) -> bool {
+        match self {
+            Clipboard::Copy(_) => false,
+            Clipboard::Cut(paths) => paths.iter().any(|p| p == path),
+        }
+    }
+}
+
 pub struct Window {
     pub panels: Vec<Vec<Panel>>,
     pub panel_focus_i: usize,
     pub panel_focus_j: usize,
-    pub clipboard: Vec<PathBuf>,
+    /// Only the active pane is drawn, over the whole window. The other panes stay as they are.
+    pub zoomed: bool,
+    /// The size of the window when it was last drawn.
+    pub(crate) area: Rect,
+    /// Where `save_session` saves the pane layout, `None` if it can't be saved.
+    pub session_file: Option<PathBuf>,
+    pub(crate) clipboard: Clipboard,
+    pub(crate) undo_stack: UndoStack,
+    pub(crate) show_operation_log: bool,
+    pub(crate) error_log: ErrorLog,
+    /// Scroll offset of the error log overlay, `None` while it is closed.
+    pub(crate) error_log_scroll: Option<u16>,
+    pub(crate) frecency: Frecency,
+    /// What was entered into prompts in any pane.
+    pub history: History,
+    /// Scroll offset of the help overlay, `None` while it is closed.
+    pub help_scroll: Option<u16>,
+    pub(crate) preview_cache: PreviewCache,
+    pub(crate) highlighter: Highlighter,
+    /// Opened the first time a path is yanked and kept open, some platforms forget the text
+    /// once it's closed.
+    pub(crate) system_clipboard: Option<arboard::Clipboard>,
     pub config: Config,
 }
 
 impl Window {
+    /// A window with a single pane in `current_dir`. Warnings about the config are shown in it.
+    pub fn new(config: Config, current_dir: PathBuf, mut frecency: Frecency) -> Self {
+        frecency.visit(&current_dir);
+        let mut window = Window {
+            panels: vec![vec![Panel::new(current_dir, &config)]],
+            panel_focus_i: 0,
+            panel_focus_j: 0,
+            zoomed: false,
+            area: Rect::default(),
+            session_file: None,
+            clipboard: Clipboard::default(),
+            undo_stack: UndoStack::default(),
+            show_operation_log: false,
+            error_log: ErrorLog::default(),
+            error_log_scroll: None,
+            history: History::default(),
+            help_scroll: None,
+            preview_cache: PreviewCache::default(),
+            highlighter: Highlighter::new(&config.preview_theme),
+            system_clipboard: None,
+            frecency,
+            config,
+        };
+        window.show_config_warnings();
+        window
+    }
+
+    /// Handles `key_event`, which may start or finish a key sequence.
+    ///
+    /// The first key of a sequence is held back until the next key shows whether the sequence is
+    /// pressed. If that key doesn't finish a sequence, or none follows within
+    /// [`KEY_SEQUENCE_TIMEOUT`], the first key does what it's bound to on its own.
+    pub fn process_key_event(&mut self, key_event: KeyEvent) -> PanelFrameData {
+        if !key_event.is_press() || !self.binds_keys() {
+            return self.dispatch_key_event(key_event);
+        }
+        let panel = &mut self.panels[self.panel_focus_i][self.panel_focus_j];
+        if let Some((prefix, pressed)) = panel.pending_key.take() {
+            if pressed.elapsed() < KEY_SEQUENCE_TIMEOUT {
+                if let Some(sequence) = self
+                    .config
+                    .key_sequences
+                    .iter()
+                    .find(|s| s.keys == [prefix, key_event])
+                {
+                    return self.dispatch_key_event(sequence.action);
+                }
+            } else if prefix == key_event {
+                // handed back by `Panel::expired_key` since nothing followed it in time
+                return self.dispatch_key_event(key_event);
+            }
+            let res = self.dispatch_key_event(prefix);
+            if res.hands_back() {
+                return res;
+            }
+            self.process_key_event(key_event)
+        } else if self
+            .config
+            .key_sequences
+            .iter()
+            .any(|s| s.keys[0] == key_event)
+        {
+            // like any other key, it abandons a typed entry number
+            panel.number_prefix = None;
+            panel.pending_key = Some((key_event, Instant::now()));
+            PanelFrameData::default()
+        } else {
+            self.dispatch_key_event(key_event)
+        }
+    }
+
+    /// Handles `key_event`, either switching between panes and overlays or passing it on to the
+    /// active panel. Running the returned `shell_command` or opening `edit_file` is up to the
+    /// caller since it needs the terminal, and so is leaving once `quit` is set. Transfers are
+    /// already cancelled and the frecency is saved by then.
+    fn dispatch_key_event(&mut self, key_event: KeyEvent) -> PanelFrameData {
+        let mut res = PanelFrameData::default();
+        let ends_visual =
+            key_event.is_press() && self.binds_keys() && !self.keeps_visual(key_event);
+        let normal_mode = self.panel().mode == PanelMode::Normal;
+        if self.show_operation_log {
+            if key_event.is_press() {
+                self.show_operation_log = false;
+            }
+        } else if let Some(scroll) = self.error_log_scroll {
+            // only closed on purpose, so errors aren't missed by pressing some key
+            if key_event == self.config.down {
+                self.error_log_scroll = Some(scroll.saturating_add(1));
+            } else if key_event == self.config.up {
+                self.error_log_scroll = Some(scroll.saturating_sub(1));
+            } else if key_event == self.config.error_log || key_event == self.config.normal_mode {
+                self.error_log_scroll = None;
+            }
+        } else if let Some(scroll) = self.help_scroll {
+            if key_event == self.config.down {
+                self.help_scroll = Some(scroll.saturating_add(1));
+            } else if key_event == self.config.up {
+                self.help_scroll = Some(scroll.saturating_sub(1));
+            } else if key_event.is_press() {
+                self.help_scroll = None;
+            }
+        } else if self.panel().find.is_some() {
+            self.process_find_key(key_event);
+        } else if normal_mode && key_event == self.config.help {
+            self.help_scroll = Some(0);
+        } else if normal_mode && key_event == self.config.undo {
+            self.undo();
+        } else if normal_mode && key_event == self.config.redo {
+            self.redo();
+        } else if normal_mode && key_event == self.config.yank_path {
+            self.yank_paths(false);
+        } else if normal_mode && key_event == self.config.yank_path_relative {
+            self.yank_paths(true);
+        } else if normal_mode && key_event == self.config.checksum {
+            self.checksum();
+        } else if normal_mode && key_event == self.config.copy_to_other_pane {
+            self.paste_into_other_pane(false);
+        } else if normal_mode && key_event == self.config.move_to_other_pane {
+            self.paste_into_other_pane(true);
+        } else if normal_mode && key_event == self.config.operation_log {
+            self.show_operation_log = true;
+        } else if normal_mode && key_event == self.config.error_log {
+            self.error_log_scroll = Some(0);
+            self.error_log.unread = 0;
+        } else if key_event == self.config.pane_up {
+            self.pane_up();
+        } else if key_event == self.config.pane_down {
+            self.pane_down();
+        } else if key_event == self.config.pane_left {
+            self.pane_left();
+        } else if key_event == self.config.pane_right {
+            self.pane_right();
+        } else if key_event == self.config.split_pane_up {
+            self.split_up();
+        } else if key_event == self.config.split_pane_down {
+            self.split_down();
+        } else if key_event == self.config.split_pane_left {
+            self.split_left();
+        } else if key_event == self.config.split_pane_right {
+            self.split_right();
+        } else if key_event == self.config.swap_pane_up {
+            self.swap_pane_up();
+        } else if key_event == self.config.swap_pane_down {
+            self.swap_pane_down();
+        } else if key_event == self.config.swap_pane_left {
+            self.swap_pane_left();
+        } else if key_event == self.config.swap_pane_right {
+            self.swap_pane_right();
+        } else if key_event == self.config.zoom_pane {
+            self.zoomed = !self.zoomed;
+        } else if normal_mode && key_event == self.config.save_session {
+            self.save_session();
+        } else if key_event == self.config.close_active_pane {
+            self.close_active();
+        } else if self.panel().mode == PanelMode::Prompt
+            && key_event.is_press()
+            && matches!(key_event.code, KeyCode::Up | KeyCode::Down)
+        {
+            self.recall_history(key_event.code == KeyCode::Up);
+        } else {
+            let was_prompt = self.panel().mode == PanelMode::Prompt;
+            let submitted =
+                (was_prompt && key_event.code == KeyCode::Enter && key_event.is_press())
+                    .then(|| self.panel().edit_buffer.clone());
+            let previous_dir = self.panel().working_directory.clone();
+            res = self.panels[self.panel_focus_i][self.panel_focus_j].process_key_event(
+                key_event,
+                &mut self.clipboard,
+                &mut self.undo_stack,
+                &mut self.frecency,
+                &self.config,
+            );
+            if self.panel().working_directory != previous_dir {
+                let wd = self.panel().working_directory.clone();
+                self.frecency.visit(&wd);
+            }
+            if let Some(entry) = submitted {
+                self.history.push(&entry);
+            } else if !was_prompt && self.panel().mode == PanelMode::Prompt {
+                // a new prompt starts out with what the user types
+                self.history.reset();
+            }
+            if res.quit {
+                for panel in self.panels.iter_mut().flatten() {
+                    if let Some(transfer) = panel.transfer.take() {
+                        // otherwise the entry being copied would be left half done
+                        transfer.cancel_and_wait();
+                    }
+                }
+                let _ = self.frecency.save();
+                let _ = self.history.save();
+                return res;
+            }
+            if res.should_refresh {
+                for i in 0..self.panels.len() {
+                    for j in 0..self.panels[i].len() {
+                        if i == self.panel_focus_i && j == self.panel_focus_j {
+                            continue;
+                        }
+                        let wd = &self.panels[i][j].working_directory;
+                        if !res.changed_dirs.is_empty()
+                            && !res
+                                .changed_dirs
+                                .iter()
+                                .any(|dir| dir.starts_with(wd) || wd.starts_with(dir))
+                        {
+                            continue;
+                        }
+                        let panel = &mut self.panels[i][j];
+                        if !panel.working_directory.is_dir() {
+                            // it was removed or moved away along with a directory it was in
+                            if let Some(dir) =
+                                panel.working_directory.ancestors().find(|d| d.is_dir())
+                            {
+                                panel.change_directory(dir.to_path_buf(), &self.config);
+                                continue;
+                            }
+                        }
+                        panel.read_working_dir();
+                        panel.refresh_cursor();
+                    }
+                }
+            }
+        }
+        if ends_visual {
+            self.panels[self.panel_focus_i][self.panel_focus_j].visual = false;
+        }
+        res
+    }
+
+    /// Whether `key_event` can't change anything, so the window doesn't have to be drawn again.
+    /// Only key releases and unbound keys pressed in Normal mode with nothing pending are known to
+    /// be ignored, any other key is assumed to change something.
+    pub fn ignores(&self, key_event: KeyEvent) -> bool {
+        if key_event.kind == KeyEventKind::Release {
+            return true;
+        }
+        let panel = &self.panels[self.panel_focus_i][self.panel_focus_j];
+        self.binds_keys()
+            && panel.pending_key.is_none()
+            && panel.number_prefix.is_none()
+            // these do something even when they aren't bound
+            && !matches!(
+                key_event.code,
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('0'..='9')
+            )
+            && self
+                .config
+                .keybindings()
+                .iter()
+                .flat_map(|(_, keys)| keys)
+                .all(|(_, key)| **key != key_event)
+    }
+
+    /// Whether `key_event` only moves the cursor or shows something, anything else is an
+    /// operation that ends visual mode.
+    fn keeps_visual(&self, key_event: KeyEvent) -> bool {
+        let config = &self.config;
+        [
+            &config.visual_mode,
+            &config.up,
+            &config.down,
+            &config.select_up,
+            &config.select_down,
+            &config.page_up,
+            &config.page_down,
+            &config.half_page_up,
+            &config.half_page_down,
+            &config.goto_top,
+            &config.goto_bottom,
+            &config.left,
+            &config.right,
+            &config.scroll_left,
+            &config.scroll_right,
+            &config.pane_up,
+            &config.pane_down,
+            &config.pane_left,
+            &config.pane_right,
+            &config.zoom_pane,
+            &config.help,
+            &config.error_log,
+            &config.operation_log,
+        ]
+        .contains(&&key_event)
+            // typing an entry number to jump to
+            || matches!(key_event.code, KeyCode::Char('0'..='9'))
+            || (key_event.code == KeyCode::Enter
+                && self.panels[self.panel_focus_i][self.panel_focus_j]
+                    .number_prefix
+                    .is_some())
+    }
+
+    /// Whether keys are bound to actions right now, instead of being typed or used by an overlay.
+    fn binds_keys(&self) -> bool {
+        let panel = &self.panels[self.panel_focus_i][self.panel_focus_j];
+        !self.show_operation_log
+            && self.error_log_scroll.is_none()
+            && self.help_scroll.is_none()
+            && panel.find.is_none()
+            && panel.mode == PanelMode::Normal
+            && panel.errors.is_empty()
+    }
+
+    /// Picks up what happened since the last call without a key being pressed: a typed entry
+    /// number that is due, computed directory sizes and the progress of transfers. Returns whether
+    /// the window should be drawn again.
+    pub fn tick(&mut self) -> bool {
+        let mut redraw = false;
+        // a typed entry number is jumped to once no more digits follow for a while
+        if self
+            .panel()
+            .number_prefix_deadline()
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.panels[self.panel_focus_i][self.panel_focus_j].apply_number_prefix();
+            redraw = true;
+        }
+        let mut refresh_all = false;
+        let mut digests = Vec::new();
+        for panel in self.panels.iter_mut().flatten() {
+            if panel.dir_sizes.receive() {
+                redraw = true;
+            }
+            if let Some(res) = panel.checksum.as_mut().and_then(Checksum::receive) {
+                match res {
+                    Ok(digest) => digests.push(digest),
+                    Err(err) => {
+                        panel.checksum = None;
+                        panel.errors.push(err);
+                    }
+                }
+                redraw = true;
+            }
+            if panel.receive_entries() {
+                redraw = true;
+            }
+            if panel.find.as_mut().is_some_and(Find::receive) {
+                redraw = true;
+            }
+            let Some(transfer) = &mut panel.transfer else {
+                continue;
+            };
+            redraw = true;
+            if let Some(finished) = transfer.poll() {
+                let replaced = std::mem::take(&mut transfer.replaced);
+                panel.transfer = None;
+                panel.errors.extend(finished.errors);
+                if finished.cancelled {
+                    panel
+                        .errors
+                        .push(WalkedError::Message("Paste cancelled".to_string()));
+                }
+                // entries that were trashed to make room for a copy that didn't happen are put back
+                let (replaced, unused) = replaced.into_iter().partition::<Vec<_>, _>(|entry| {
+                    finished
+                        .copies
+                        .iter()
+                        .any(|(_, dest)| *dest == entry.original)
+                });
+                for entry in unused {
+                    put_back(&entry, &mut panel.errors);
+                }
+                if !finished.copies.is_empty() {
+                    self.undo_stack.push(Operation::Copy {
+                        copies: finished.copies,
+                        replaced,
+                        copied_at: SystemTime::now(),
+                    });
+                }
+                refresh_all = true;
+            }
+        }
+        if refresh_all {
+            self.refresh_panels();
+        }
+        if self.config.copy_checksum {
+            for digest in digests {
+                self.set_system_clipboard(digest);
+            }
+        }
+        redraw
+    }
+
+    /// Replaces the configuration with the one in `path`, keeping the current one if it's invalid.
+    pub fn reload_config(&mut self, path: &Path) {
+        match Config::load(path) {
+            Ok(config) => {
+                if config.preview_theme != self.config.preview_theme {
+                    self.highlighter = Highlighter::new(&config.preview_theme);
+                }
+                for panel in self.panels.iter_mut().flatten() {
+                    panel.show_git_status = config.show_git_status;
+                    panel.read_working_dir();
+                }
+                self.config = config;
+                self.show_config_warnings();
+            }
+            Err(err) => self.panels[self.panel_focus_i][self.panel_focus_j]
+                .errors
+                .push(WalkedError::Message(err)),
+        }
+    }
+
+    fn show_config_warnings(&mut self) {
+        let warnings = std::mem::take(&mut self.config.warnings);
+        self.panels[self.panel_focus_i][self.panel_focus_j]
+            .errors
+            .extend(warnings.into_iter().map(WalkedError::Warning));
+    }
+
+    /// Replaces what's typed into the active pane's prompt with an older entry of the history, or
+    /// a newer one if `older` isn't set.
+    fn recall_history(&mut self, older: bool) {
+        let panel = &mut self.panels[self.panel_focus_i][self.panel_focus_j];
+        let entry = if older {
+            self.history.older(&panel.edit_buffer)
+        } else {
+            self.history.newer()
+        };
+        let Some(entry) = entry else {
+            return;
+        };
+        panel.edit_buffer = entry.to_string();
+        panel.completion = None;
+        if matches!(panel.command_prompt, Some(CommandKind::Filter)) {
+            panel.set_filter(panel.edit_buffer.clone());
+        }
+    }
+
+    /// Adds the errors shown in the panes since the last call to the error log.
+    pub fn log_errors(&mut self) {
+        for panel in self.panels.iter_mut().flatten() {
+            for err in &panel.errors[panel.errors_logged.min(panel.errors.len())..] {
+                self.error_log.push(err);
+            }
+            panel.errors_logged = panel.errors.len();
+        }
+        if self.error_log_scroll.is_some() {
+            self.error_log.unread = 0;
+        }
+    }
+
     pub fn pane_up(&mut self) {
         if self.panel_focus_i > 0 {
             self.panel_focus_i -= 1;
@@ -71,31 +629,111 @@ impl Window {
         }
     }
 
+    /// Whether there's room for another row of panes (`vertical`) or for another pane in the
+    /// active row, reports it if there isn't.
+    fn can_split(&mut self, vertical: bool) -> bool {
+        let fits = if vertical {
+            self.area.height / (self.panels.len() as u16 + 1) >= self.config.min_pane_height
+        } else {
+            self.area.width / (self.panels[self.panel_focus_i].len() as u16 + 1)
+                >= self.config.min_pane_width
+        };
+        if !fits {
+            self.panels[self.panel_focus_i][self.panel_focus_j]
+                .errors
+                .push(WalkedError::Message(
+                    "There's no room for another pane".to_string(),
+                ));
+        }
+        fits
+    }
+
     pub fn split_up(&mut self) {
+        if !self.can_split(true) {
+            return;
+        }
         let wd = self.panel().working_directory.clone();
-        self.panels.insert(self.panel_focus_i, vec![Panel::new(wd)]);
+        self.panels
+            .insert(self.panel_focus_i, vec![Panel::new(wd, &self.config)]);
         self.panel_focus_j = 0;
     }
 
     pub fn split_down(&mut self) {
+        if !self.can_split(true) {
+            return;
+        }
         let wd = self.panel().working_directory.clone();
         self.panels
-            .insert(self.panel_focus_i + 1, vec![Panel::new(wd)]);
+            .insert(self.panel_focus_i + 1, vec![Panel::new(wd, &self.config)]);
         self.panel_focus_i += 1;
         self.panel_focus_j = 0;
     }
 
     pub fn split_left(&mut self) {
+        if !self.can_split(false) {
+            return;
+        }
         let wd = self.panel().working_directory.clone();
-        self.panels[self.panel_focus_i].insert(self.panel_focus_j, Panel::new(wd));
+        self.panels[self.panel_focus_i].insert(self.panel_focus_j, Panel::new(wd, &self.config));
     }
 
     pub fn split_right(&mut self) {
+        if !self.can_split(false) {
+            return;
+        }
         let wd = self.panel().working_directory.clone();
-        self.panels[self.panel_focus_i].insert(self.panel_focus_j + 1, Panel::new(wd));
+        self.panels[self.panel_focus_i]
+            .insert(self.panel_focus_j + 1, Panel::new(wd, &self.config));
         self.panel_focus_j += 1;
     }
 
+    /// Swaps the active pane with the one above it. Like the other swaps, the focus moves along
+    /// with the pane so pressing the key again keeps moving it.
+    pub fn swap_pane_up(&mut self) {
+        if self.panel_focus_i > 0 {
+            let i = self.panel_focus_i - 1;
+            self.swap_active_with(i, self.panel_focus_j.min(self.panels[i].len() - 1));
+        }
+    }
+
+    pub fn swap_pane_down(&mut self) {
+        if self.panel_focus_i + 1 < self.panels.len() {
+            let i = self.panel_focus_i + 1;
+            self.swap_active_with(i, self.panel_focus_j.min(self.panels[i].len() - 1));
+        }
+    }
+
+    pub fn swap_pane_left(&mut self) {
+        if self.panel_focus_j > 0 {
+            self.swap_active_with(self.panel_focus_i, self.panel_focus_j - 1);
+        }
+    }
+
+    pub fn swap_pane_right(&mut self) {
+        if self.panel_focus_j + 1 < self.panels[self.panel_focus_i].len() {
+            self.swap_active_with(self.panel_focus_i, self.panel_focus_j + 1);
+        }
+    }
+
+    /// Exchanges the active pane with the one at `i`, `j` and focuses it in its new place. Only
+    /// the panes trade places, so every row keeps its length.
+    fn swap_active_with(&mut self, i: usize, j: usize) {
+        let (focus_i, focus_j) = (self.panel_focus_i, self.panel_focus_j);
+        if i == focus_i {
+            self.panels[i].swap(focus_j, j);
+        } else {
+            let ((top_i, top_j), (bottom_i, bottom_j)) = if i < focus_i {
+                ((i, j), (focus_i, focus_j))
+            } else {
+                ((focus_i, focus_j), (i, j))
+            };
+            let (top, bottom) = self.panels.split_at_mut(bottom_i);
+            std::mem::swap(&mut top[top_i][top_j], &mut bottom[0][bottom_j]);
+        }
+        self.panel_focus_i = i;
+        self.panel_focus_j = j;
+    }
+
     pub fn close_active(&mut self) {
         let row_count = self.panels.len();
         let row_len = self.panels[self.panel_focus_i].len();
@@ -116,6 +754,276 @@ impl Window {
         }
     }
 
+    pub fn undo(&mut self) {
+        let mut errors = Vec::new();
+        if self.undo_stack.undo(&mut errors, self.config.use_reflink) {
+            self.refresh_panels();
+        }
+        self.panels[self.panel_focus_i][self.panel_focus_j]
+            .errors
+            .extend(errors);
+    }
+
+    pub fn redo(&mut self) {
+        let mut errors = Vec::new();
+        if self.undo_stack.redo(&mut errors, self.config.use_reflink) {
+            self.refresh_panels();
+        }
+        self.panels[self.panel_focus_i][self.panel_focus_j]
+            .errors
+            .extend(errors);
+    }
+
+    /// Applies the bulk rename of the active panel once its names were edited, `edited` is false if
+    /// the editor failed and the names shouldn't be used.
+    pub fn finish_editing(&mut self, edited: bool) {
+        let panel = &mut self.panels[self.panel_focus_i][self.panel_focus_j];
+        if let Some(renames) = panel.finish_bulk_rename(edited, &self.config) {
+            self.undo_stack.push(Operation::BulkRename { renames });
+        }
+        self.refresh_panels();
+    }
+
+    /// Puts the paths of the selected entries on the system clipboard, one per line. `relative`
+    /// makes them relative to the directory walked was started in.
+    fn yank_paths(&mut self, relative: bool) {
+        let panel = &mut self.panels[self.panel_focus_i][self.panel_focus_j];
+        let indices = panel.selected_indices();
+        if indices.is_empty() {
+            return;
+        }
+        let base = std::env::current_dir().ok().filter(|_| relative);
+        let text = indices
+            .iter()
+            .map(|&i| match &base {
+                Some(base) => relative_path(&panel.entries[i], base),
+                None => panel.entries[i].clone(),
+            })
+            .map(|path| path.to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.set_system_clipboard(text);
+    }
+
+    /// Moves through the results of `find`, Enter goes to the one under the cursor and any other
+    /// key closes them.
+    fn process_find_key(&mut self, key_event: KeyEvent) {
+        let panel = &mut self.panels[self.panel_focus_i][self.panel_focus_j];
+        let Some(find) = &mut panel.find else {
+            return;
+        };
+        if key_event == self.config.down {
+            find.cursor = (find.cursor + 1).min(find.results.len().saturating_sub(1));
+        } else if key_event == self.config.up {
+            find.cursor = find.cursor.saturating_sub(1);
+        } else if key_event.code == KeyCode::Enter && key_event.is_press() {
+            let Some(path) = find.selected().cloned() else {
+                return;
+            };
+            let directories = find.directories;
+            panel.find = None;
+            if directories {
+                panel.change_directory(path.clone(), &self.config);
+                self.frecency.visit(&path);
+                return;
+            }
+            let Some(dir) = path.parent() else {
+                return;
+            };
+            panel.change_directory(dir.to_path_buf(), &self.config);
+            self.frecency.visit(dir);
+            let panel = &mut self.panels[self.panel_focus_i][self.panel_focus_j];
+            if let Some(i) = panel.entries.iter().position(|entry| *entry == path) {
+                panel.table_state.select(Some(i));
+                panel.refresh_cursor();
+            }
+        } else if key_event.is_press() {
+            panel.find = None;
+        }
+    }
+
+    /// Hashes the highlighted file, the digest is shown below the pane once it's computed.
+    fn checksum(&mut self) {
+        let algorithm = self.config.checksum_algorithm;
+        let panel = &mut self.panels[self.panel_focus_i][self.panel_focus_j];
+        let Some(i) = panel
+            .table_state
+            .selected()
+            .filter(|&i| i < panel.entries.len())
+        else {
+            return;
+        };
+        if panel.metadata[i].as_ref().is_some_and(|m| m.is_dir()) {
+            return;
+        }
+        match Checksum::start(&panel.entries[i], algorithm) {
+            Ok(checksum) => {
+                let digest = checksum.digest.clone();
+                panel.checksum = Some(checksum);
+                if let Some(digest) = digest.filter(|_| self.config.copy_checksum) {
+                    self.set_system_clipboard(digest);
+                }
+            }
+            Err(err) => panel.errors.push(err),
+        }
+    }
+
+    /// Puts `text` on the system clipboard, connecting to it first if this is the first time.
+    fn set_system_clipboard(&mut self, text: String) {
+        let res = match &mut self.system_clipboard {
+            Some(clipboard) => clipboard.set_text(text),
+            None => arboard::Clipboard::new().and_then(|mut clipboard| {
+                let res = clipboard.set_text(text);
+                self.system_clipboard = Some(clipboard);
+                res
+            }),
+        };
+        if let Err(err) = res {
+            self.panels[self.panel_focus_i][self.panel_focus_j]
+                .errors
+                .push(WalkedError::Message(format!(
+                    "Couldn't use the system clipboard: {err}"
+                )));
+        }
+    }
+
+    /// The working directory of each pane and which one is active.
+    pub fn session(&self) -> Session {
+        Session {
+            rows: self
+                .panels
+                .iter()
+                .map(|row| row.iter().map(|p| p.working_directory.clone()).collect())
+                .collect(),
+            focus: (self.panel_focus_i, self.panel_focus_j),
+        }
+    }
+
+    /// Replaces the panes with the ones of `session`. Panes whose directory is gone start in
+    /// `fallback` instead, with a warning. Messages in the active pane are kept.
+    pub fn restore_session(&mut self, session: Session, fallback: &Path) {
+        if session.rows.is_empty() {
+            return;
+        }
+        let panels = session
+            .rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|dir| {
+                        if dir.is_dir() {
+                            return Panel::new(dir, &self.config);
+                        }
+                        let mut panel = Panel::new(fallback.to_path_buf(), &self.config);
+                        panel.errors.push(WalkedError::Message(format!(
+                            "'{}' is gone, this pane starts in '{}' instead",
+                            dir.display(),
+                            fallback.display()
+                        )));
+                        panel
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let errors =
+            std::mem::take(&mut self.panels[self.panel_focus_i][self.panel_focus_j].errors);
+        self.panels = panels;
+        self.panel_focus_i = session.focus.0.min(self.panels.len() - 1);
+        self.panel_focus_j = session
+            .focus
+            .1
+            .min(self.panels[self.panel_focus_i].len() - 1);
+        self.panels[self.panel_focus_i][self.panel_focus_j]
+            .errors
+            .extend(errors);
+    }
+
+    fn save_session(&mut self) {
+        let res = match &self.session_file {
+            Some(file) => self.session().save(file),
+            None => Err(WalkedError::Message(
+                "There's nowhere to save the session".to_string(),
+            )),
+        };
+        if let Err(err) = res {
+            self.panels[self.panel_focus_i][self.panel_focus_j]
+                .errors
+                .push(err);
+        }
+    }
+
+    /// The pane `copy_to_other_pane` and `move_to_other_pane` paste into: the next pane in the
+    /// active row (the previous one for the last pane of a row), or the pane below (or above) if
+    /// the active pane has its row to itself.
+    fn other_pane(&self) -> Option<(usize, usize)> {
+        let (i, j) = (self.panel_focus_i, self.panel_focus_j);
+        let row_len = self.panels[i].len();
+        if row_len > 1 {
+            Some((i, if j + 1 < row_len { j + 1 } else { j - 1 }))
+        } else if i + 1 < self.panels.len() {
+            Some((i + 1, 0))
+        } else if i > 0 {
+            Some((i - 1, 0))
+        } else {
+            None
+        }
+    }
+
+    /// Copies or moves (`cut`) the selected entries of the active pane into the working directory
+    /// of the other pane, the clipboard is left alone. If names are taken there and the user has
+    /// to be asked about them, the focus moves to the other pane for the answers.
+    fn paste_into_other_pane(&mut self, cut: bool) {
+        let (focus_i, focus_j) = (self.panel_focus_i, self.panel_focus_j);
+        let Some((i, j)) = self.other_pane() else {
+            self.panels[focus_i][focus_j]
+                .errors
+                .push(WalkedError::Message("There is no other pane".to_string()));
+            return;
+        };
+        if self.panels[i][j].transfer.is_some() {
+            self.panels[focus_i][focus_j]
+                .errors
+                .push(WalkedError::Message(
+                    "Wait for the current paste to finish (or cancel it with Esc)".to_string(),
+                ));
+            return;
+        }
+        let source = &self.panels[focus_i][focus_j];
+        let paths = source.entries_at(&source.selected_indices());
+        if paths.is_empty() {
+            return;
+        }
+        let pending = PendingPaste {
+            cut,
+            remaining: paths.into(),
+            jobs: Vec::new(),
+            policy: None,
+        };
+        let target = &mut self.panels[i][j];
+        target.continue_paste(
+            pending,
+            None,
+            &mut PanelFrameData::default(),
+            &mut Clipboard::default(),
+            &mut self.undo_stack,
+            &self.config,
+        );
+        if target.mode == PanelMode::Confirm {
+            self.panel_focus_i = i;
+            self.panel_focus_j = j;
+        }
+        self.refresh_panels();
+    }
+
+    pub fn refresh_panels(&mut self) {
+        for row in self.panels.iter_mut() {
+            for panel in row.iter_mut() {
+                panel.read_working_dir();
+                panel.refresh_cursor();
+            }
+        }
+    }
+
     pub fn panel(&mut self) -> &Panel {
         &self.panels[self.panel_focus_i][self.panel_focus_j]
     }
@@ -127,53 +1035,289 @@ pub enum PanelMode {
     Prompt,
     Search,
     Insert,
+    Confirm,
+}
+
+/// A removal waiting for the user to confirm it.
+pub struct PendingOp {
+    /// Kept as paths since the listing can be read again or sorted differently while the prompt
+    /// is open.
+    pub entries: Vec<PathBuf>,
+    pub use_trash: bool,
+    /// Total size of the entries, including everything inside directories.
+    pub bytes: u64,
+}
+
+impl PendingOp {
+    pub fn new(entries: Vec<PathBuf>, use_trash: bool) -> Self {
+        let bytes = entries.iter().map(|entry| entry_size(entry)).sum();
+        Self {
+            entries,
+            use_trash,
+            bytes,
+        }
+    }
+
+    pub fn prompt(&self, size_format: SizeFormat) -> String {
+        let count = self.entries.len();
+        format!(
+            "{} {} {} ({})? [y/N]",
+            if self.use_trash { "Trash" } else { "Remove" },
+            count,
+            if count == 1 { "entry" } else { "entries" },
+            size_format.format(self.bytes)
+        )
+    }
+}
+
+/// Renames waiting for the user to confirm them after looking at the new names.
+pub struct PendingRename {
+    pub renames: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PendingRename {
+    pub fn prompt(&self) -> String {
+        let count = self.renames.len();
+        format!(
+            "Rename {} {}? [y/N]",
+            count,
+            if count == 1 { "entry" } else { "entries" }
+        )
+    }
+
+    /// The old and new names, as many of them as fit on a line.
+    pub fn preview(&self) -> String {
+        let name = |path: &Path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        self.renames
+            .iter()
+            .map(|(from, to)| format!("{} → {}", name(from), name(to)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// A paste waiting for the user to decide what happens to an entry whose name is already taken.
+pub struct PendingPaste {
+    cut: bool,
+    /// The entries that weren't looked at yet, the first one is the one the user is asked about.
+    remaining: VecDeque<PathBuf>,
+    /// Where each entry is pasted and whether the entry that's already there is replaced.
+    jobs: Vec<(PathBuf, PathBuf, bool)>,
+    /// The answer picked with "overwrite all" or "skip all".
+    policy: Option<PasteConflictPolicy>,
+}
+
+impl PendingPaste {
+    pub fn prompt(&self) -> String {
+        let name = self
+            .remaining
+            .front()
+            .and_then(|p| p.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        format!(
+            "'{name}' already exists: [o]verwrite, [s]kip, [r]ename, [O]verwrite all, [S]kip all?"
+        )
+    }
+}
+
+fn entry_size(path: &Path) -> u64 {
+    let Ok(metadata) = path.symlink_metadata() else {
+        return 0;
+    };
+    if metadata.is_dir() {
+        std::fs::read_dir(path)
+            .map(|dir| dir.flatten().map(|e| entry_size(&e.path())).sum())
+            .unwrap_or(0)
+    } else {
+        metadata.len()
+    }
 }
 
 pub struct Panel {
     pub errors: Vec<WalkedError>,
+    /// How many of `errors` were added to the error log already.
+    pub(crate) errors_logged: usize,
     pub table_state: TableState,
     pub mode: PanelMode,
-    pub left: u16,
-    pub top: u16,
-    pub bottom: u16,
+    pub(crate) left: u16,
+    pub(crate) top: u16,
+    pub(crate) bottom: u16,
+    /// How many entries fit in the panel, updated every time it is drawn.
+    pub(crate) view_height: u16,
     pub entries: Vec<PathBuf>,
+    /// Metadata of each entry in `entries`, fetched once when the directory is read.
+    pub metadata: Vec<Option<Metadata>>,
+    /// Where each entry in `entries` points if it's a symlink.
+    pub(crate) link_targets: Vec<Option<PathBuf>>,
     pub incremental_search_results: Vec<usize>,
-    pub current_incremental_search_result: usize,
+    pub(crate) current_incremental_search_result: usize,
     pub working_directory: PathBuf,
     pub edit_buffer: String,
-    pub cursor_offset: u16,
-    pub current_entry_length: usize,
-    pub header_width: u16,
+    pub(crate) cursor_offset: u16,
+    pub(crate) current_entry_length: usize,
+    pub(crate) header_width: u16,
+    /// How many characters are scrolled off the start of the names.
+    pub(crate) name_scroll: usize,
+    /// Where the range being extended with `select_up`/`select_down` started, the cursor is the
+    /// other end of it.
     pub selection_start: Option<usize>,
-    pub queue: Vec<Command>,
+    /// Whether `selection_start` is anchored, so moving the cursor in any way extends the range
+    /// instead of ending it. Left on an operation or `normal_mode`.
+    pub(crate) visual: bool,
+    /// Entries selected apart from that range, like the ones picked by inverting the selection.
+    pub selection: HashSet<usize>,
+    pub(crate) queue: Vec<Command>,
+    /// The last action that changed entries, done again by the `repeat` key.
+    pub(crate) last_action: Option<RepeatableAction>,
     pub command_prompt: Option<CommandKind>,
+    pub pending_confirmation: Option<PendingOp>,
+    pub(crate) pending_paste: Option<PendingPaste>,
+    pub pending_rename: Option<PendingRename>,
+    /// Waiting for the user to edit the names in their editor.
+    pub(crate) bulk_rename: Option<BulkRename>,
+    pub sort: SortMode,
+    pub sort_reverse: bool,
+    pub directories_first: bool,
+    pub natural_sort: bool,
+    pub case_insensitive_sort: bool,
+    /// Only entries whose names fuzzy match this are listed, the directory itself is untouched.
+    pub filter: String,
+    /// Visited directories, `history_index` points at the working directory.
+    pub history: Vec<PathBuf>,
+    pub history_index: usize,
+    /// Where the cursor was when each directory was last left.
+    pub(crate) cursor_positions: HashMap<PathBuf, usize>,
+    /// The first key of an unfinished two key sequence and when it was pressed.
+    pub(crate) pending_key: Option<(KeyEvent, Instant)>,
+    /// The entry number typed so far and when its last digit was typed.
+    pub(crate) number_prefix: Option<(usize, Instant)>,
+    /// Recursive sizes of the directories that were listed in this panel, kept across directories.
+    pub(crate) dir_sizes: DirSizes,
+    pub show_git_status: bool,
+    /// Git status of the entries by name, `None` outside of a working tree.
+    pub(crate) git_status: Option<HashMap<OsString, GitStatus>>,
+    /// A big paste that is still being copied in the background.
+    pub(crate) transfer: Option<Transfer>,
+    /// The digest of the file that was last hashed with the `checksum` key.
+    pub(crate) checksum: Option<Checksum>,
+    /// The results of `find`, shown over the panes while it's open.
+    pub(crate) find: Option<Find>,
+    /// The candidates for the path being typed into the prompt once tab was pressed.
+    pub(crate) completion: Option<Completion>,
+    /// Reads the rest of the working directory if it's too big to be read at once.
+    pub(crate) reader: Option<DirReader>,
+    pub(crate) disk_usage: DiskUsageCache,
 }
 
+#[derive(Default)]
 pub struct PanelFrameData {
     pub should_refresh: bool,
     pub quit: bool,
+    /// A shell command line that should be run with the terminal handed back to the shell.
+    pub shell_command: Option<String>,
+    /// A file that should be opened in the user's editor, [`Window::finish_editing`] is called
+    /// once it's closed.
+    pub edit_file: Option<PathBuf>,
+    /// Two files that should be compared with `diff_command` while the terminal is handed back.
+    pub diff_files: Option<(PathBuf, PathBuf)>,
+    /// A file that should be opened with a command (the first element) while the terminal is
+    /// handed back, for programs that run in the terminal.
+    pub open_suspended: Option<(String, PathBuf)>,
+    /// Set along with `quit` when the selected entries should be printed instead of the working
+    /// directory.
+    pub quit_selection: Option<Vec<PathBuf>>,
+    /// Set along with `quit` when walked should exit with a failure and print nothing.
+    pub cancelled: bool,
+    /// The directories whose entries changed, only the panes showing them, a directory they are in
+    /// or a directory inside them are read again. Every pane is if `should_refresh` is set and
+    /// this is empty.
+    pub changed_dirs: Vec<PathBuf>,
+}
+
+impl PanelFrameData {
+    /// Whether the caller has something to do, like quitting or running a command.
+    pub fn hands_back(&self) -> bool {
+        self.quit
+            || self.shell_command.is_some()
+            || self.edit_file.is_some()
+            || self.diff_files.is_some()
+            || self.open_suspended.is_some()
+    }
+
+    /// Marks the entries of `dir` as changed.
+    fn changed(&mut self, dir: &Path) {
+        self.should_refresh = true;
+        if !self.changed_dirs.iter().any(|d| d == dir) {
+            self.changed_dirs.push(dir.to_path_buf());
+        }
+    }
+}
+
+/// Names of the entries being renamed together, written to `file` for the user to edit.
+pub struct BulkRename {
+    pub file: PathBuf,
+    pub entries: Vec<PathBuf>,
 }
 
 impl Panel {
-    pub fn new(current_dir: PathBuf) -> Self {
+    pub fn new(current_dir: PathBuf, config: &Config) -> Self {
+        let (sort, sort_reverse) = config.sort_for(&current_dir);
+        let history = vec![current_dir.clone()];
         let mut panel = Self {
             errors: Vec::new(),
+            errors_logged: 0,
             table_state: TableState::default(),
             mode: PanelMode::Normal,
             left: 2,
             top: 2,
             bottom: 1,
+            view_height: 0,
             working_directory: current_dir,
             entries: vec![],
+            metadata: vec![],
+            link_targets: vec![],
             incremental_search_results: vec![],
             current_incremental_search_result: 0,
             edit_buffer: String::new(),
             cursor_offset: 0,
             current_entry_length: 0,
             header_width: TABLE_HEADER_MIN_WIDTH,
+            name_scroll: 0,
             selection_start: None,
+            visual: false,
+            selection: HashSet::new(),
             queue: Vec::new(),
+            last_action: None,
             command_prompt: None,
+            pending_confirmation: None,
+            pending_paste: None,
+            pending_rename: None,
+            bulk_rename: None,
+            disk_usage: DiskUsageCache::default(),
+            sort,
+            sort_reverse,
+            directories_first: config.directories_first,
+            natural_sort: config.natural_sort,
+            case_insensitive_sort: config.case_insensitive_sort,
+            filter: String::new(),
+            history,
+            history_index: 0,
+            cursor_positions: HashMap::new(),
+            pending_key: None,
+            number_prefix: None,
+            dir_sizes: DirSizes::default(),
+            show_git_status: config.show_git_status,
+            git_status: None,
+            transfer: None,
+            checksum: None,
+            find: None,
+            completion: None,
+            reader: None,
         };
         panel.read_working_dir();
         panel.table_state.select_first();
@@ -187,79 +1331,255 @@ impl Panel {
         self.edit_buffer.clear();
     }
 
-    pub fn process_command_queue(&mut self, result: &mut PanelFrameData) {
-        if self.queue.len() > 0 {
-            let queue = self.queue.drain(..).collect::<Vec<_>>();
-            for cmd in queue {
-                match cmd.kind {
-                    CommandKind::NewFile => {
-                        let new_file = new_path(self.working_directory.join(cmd.arg));
-                        if let Err(err) = std::fs::File::create(&new_file) {
-                            match err.kind() {
-                                std::io::ErrorKind::PermissionDenied => {
-                                    self.errors.push(WalkedError::PermissionDenied {
-                                        path: new_file.clone(),
-                                        path_kind: PathKind::File,
-                                    })
-                                }
-                                _ => self.errors.push(WalkedError::Message(format!(
-                                    "Couldn't create file '{}'",
-                                    new_file.display()
-                                ))),
-                            }
-                        } else {
-                            self.read_working_dir();
-                            result.should_refresh = true;
+    /// Completes the path being typed into a `goto` or `shell` prompt, or puts the next candidate
+    /// in its place if `completion` is what tab was pressed for last. With several candidates,
+    /// only the part they have in common is filled in at first.
+    fn complete(&mut self, completion: Option<Completion>) {
+        if let Some(mut completion) = completion.filter(|c| c.candidates.len() > 1) {
+            let next = completion
+                .current
+                .map_or(0, |i| (i + 1) % completion.candidates.len());
+            self.edit_buffer.truncate(completion.start);
+            self.edit_buffer.push_str(&completion.candidates[next]);
+            completion.current = Some(next);
+            self.completion = Some(completion);
+            return;
+        }
+        let (start, dirs_only) = match self.command_prompt {
+            Some(CommandKind::Goto) => (
+                self.edit_buffer.len() - self.edit_buffer.trim_start().len(),
+                true,
+            ),
+            // the last word of the command line
+            Some(CommandKind::Shell) => (
+                self.edit_buffer
+                    .trim_end_matches(|c: char| !c.is_whitespace())
+                    .len(),
+                false,
+            ),
+            _ => return,
+        };
+        let (candidates, dir_len) = completion::candidates(
+            &self.edit_buffer[start..],
+            &self.working_directory,
+            dirs_only,
+        );
+        if candidates.is_empty() {
+            return;
+        }
+        let completed = completion::common_prefix(&candidates).to_string();
+        self.edit_buffer.truncate(start);
+        self.edit_buffer.push_str(&completed);
+        if candidates.len() > 1 {
+            self.completion = Some(Completion {
+                start,
+                dir_len,
+                candidates,
+                current: None,
+            });
+        }
+    }
 
-                            for (i, entry) in self.entries.iter().enumerate() {
-                                if *entry == new_file {
-                                    self.table_state.select(Some(i));
-                                    self.cursor_offset = 0;
-                                    self.table_state.select_column(Some(1));
-                                }
-                            }
-                        }
-                    }
-                    CommandKind::NewDirectory => {
-                        let new_dir = new_path(self.working_directory.join(cmd.arg));
-                        if let Err(err) = std::fs::create_dir(&new_dir) {
-                            match err.kind() {
-                                std::io::ErrorKind::PermissionDenied => {
-                                    self.errors.push(WalkedError::PermissionDenied {
-                                        path: new_dir.clone(),
-                                        path_kind: PathKind::Dir,
-                                    })
-                                }
-                                _ => self.errors.push(WalkedError::Message(format!(
-                                    "Couldn't create directory '{}'",
-                                    new_dir.display()
-                                ))),
-                            }
-                        } else {
-                            self.read_working_dir();
-                            result.should_refresh = true;
+    /// Goes to the only directory matching the glob `pattern`, or lets the user pick one if
+    /// several do. `typed` is what was entered before it was expanded.
+    fn goto_glob(&mut self, pattern: &str, typed: &str, config: &Config) {
+        // the working directory could have glob characters in it too
+        let pattern = if Path::new(pattern).is_absolute() {
+            pattern.to_string()
+        } else {
+            Path::new(&glob::Pattern::escape(
+                &self.working_directory.to_string_lossy(),
+            ))
+            .join(pattern)
+            .to_string_lossy()
+            .to_string()
+        };
+        let mut dirs = match glob::glob(&pattern) {
+            Ok(paths) => paths
+                .flatten()
+                .filter(|path| path.is_dir())
+                .map(|path| normalize(&path))
+                .collect::<Vec<_>>(),
+            Err(err) => {
+                self.errors.push(WalkedError::Message(format!(
+                    "'{typed}' isn't a valid glob: {}",
+                    err.msg
+                )));
+                return;
+            }
+        };
+        if dirs.is_empty() {
+            self.errors.push(WalkedError::Message(format!(
+                "No directory matches '{typed}'"
+            )));
+        } else if dirs.len() == 1 {
+            self.change_directory(dirs.remove(0), config);
+        } else {
+            self.find = Some(Find::pick_directory(
+                self.working_directory.clone(),
+                typed,
+                dirs,
+            ));
+        }
+    }
 
-                            for (i, entry) in self.entries.iter().enumerate() {
-                                if *entry == new_dir {
-                                    self.table_state.select(Some(i));
-                                    self.cursor_offset = 0;
-                                    self.table_state.select_column(Some(1));
-                                }
-                            }
-                        }
-                    }
-                    CommandKind::IncrementalSearch => {
-                        self.incremental_search_results.clear();
-                        for (i, entry) in self.entries.iter().enumerate() {
-                            if let Some(name) = entry.file_name() {
-                                if let Some(name) = name.to_str() {
-                                    if name.starts_with(&cmd.arg) {
-                                        self.incremental_search_results.push(i);
-                                    }
-                                }
+    /// When the typed entry number should be jumped to if no more digits are typed.
+    pub fn number_prefix_deadline(&self) -> Option<Instant> {
+        self.number_prefix
+            .map(|(_, typed)| typed + NUMBER_PREFIX_TIMEOUT)
+    }
+
+    /// When the first key of a sequence stops waiting for the second one.
+    pub fn pending_key_deadline(&self) -> Option<Instant> {
+        self.pending_key
+            .map(|(_, pressed)| pressed + KEY_SEQUENCE_TIMEOUT)
+    }
+
+    /// The first key of a sequence once no second key followed in time, it has to be passed to
+    /// [`Window::process_key_event`] again to do what it's bound to on its own.
+    pub fn expired_key(&self) -> Option<KeyEvent> {
+        self.pending_key
+            .filter(|(_, pressed)| pressed.elapsed() >= KEY_SEQUENCE_TIMEOUT)
+            .map(|(key_event, _)| key_event)
+    }
+
+    /// Selects the entry whose number was typed.
+    pub fn apply_number_prefix(&mut self) {
+        if let Some((n, _)) = self.number_prefix.take() {
+            if n < self.entries.len() {
+                self.leave_range();
+                self.table_state.select(Some(n));
+                self.refresh_cursor();
+            } else {
+                self.errors
+                    .push(WalkedError::Message(format!("There is no entry {n}")));
+            }
+        }
+    }
+
+    /// Narrows the listing down to entries matching `filter`, an empty filter lists everything.
+    pub fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        self.read_working_dir();
+        if self.table_state.selected().is_none() {
+            self.table_state
+                .select(clamp_selection(self.entries.len(), 0));
+        }
+        self.refresh_cursor();
+    }
+
+    /// Backs out of whatever the panel is currently doing (an unfinished prompt, search, rename or
+    /// selection) and returns to `Normal` mode without applying anything.
+    pub fn cancel(&mut self) {
+        match self.mode {
+            PanelMode::Prompt => {
+                self.edit_buffer.clear();
+                if matches!(self.command_prompt, Some(CommandKind::Filter)) {
+                    self.set_filter(String::new());
+                }
+                self.command_prompt = None;
+            }
+            PanelMode::Search => {
+                self.incremental_search_results.clear();
+                self.current_incremental_search_result = 0;
+            }
+            PanelMode::Insert => {
+                self.edit_buffer.clear();
+                self.table_state.select_column(None);
+            }
+            PanelMode::Normal => {
+                self.clear_selection();
+            }
+            PanelMode::Confirm => {
+                self.pending_confirmation = None;
+                self.pending_paste = None;
+                self.pending_rename = None;
+            }
+        }
+        self.mode = PanelMode::Normal;
+    }
+
+    /// Handles `key_event` along with the commands it queued, like a finished prompt.
+    pub fn process_key_event(
+        &mut self,
+        key_event: KeyEvent,
+        clipboard: &mut Clipboard,
+        undo_stack: &mut UndoStack,
+        frecency: &mut Frecency,
+        config: &Config,
+    ) -> PanelFrameData {
+        let normal_mode = self.mode == PanelMode::Normal;
+        let mut res = if normal_mode && key_event == config.repeat {
+            match self.last_action.clone() {
+                Some(RepeatableAction::Key(key_event)) => {
+                    self.update(key_event, clipboard, undo_stack, config)
+                }
+                Some(RepeatableAction::Command(cmd)) => {
+                    self.queue.push(cmd);
+                    PanelFrameData::default()
+                }
+                None => PanelFrameData::default(),
+            }
+        } else {
+            let res = self.update(key_event, clipboard, undo_stack, config);
+            if normal_mode
+                && [
+                    &config.duplicate,
+                    &config.paste,
+                    &config.remove,
+                    &config.trash,
+                    &config.remove_permanently,
+                ]
+                .contains(&&key_event)
+            {
+                self.last_action = Some(RepeatableAction::Key(key_event));
+            }
+            res
+        };
+        if let Some(cmd) = self.queue.iter().rev().find(|cmd| {
+            matches!(
+                cmd.kind,
+                CommandKind::NewFile
+                    | CommandKind::NewDirectory
+                    | CommandKind::PatternRename
+                    | CommandKind::NumberRename
+            )
+        }) {
+            self.last_action = Some(RepeatableAction::Command(cmd.clone()));
+        }
+        self.process_command_queue(&mut res, undo_stack, frecency, config);
+        res
+    }
+
+    pub fn process_command_queue(
+        &mut self,
+        result: &mut PanelFrameData,
+        undo_stack: &mut UndoStack,
+        frecency: &mut Frecency,
+        config: &Config,
+    ) {
+        if !self.queue.is_empty() {
+            let queue = self.queue.drain(..).collect::<Vec<_>>();
+            for cmd in queue {
+                match cmd.kind {
+                    CommandKind::NewFile => {
+                        self.create_entry(&cmd.arg, PathKind::File, undo_stack, config);
+                        result.changed(&self.working_directory);
+                    }
+                    CommandKind::NewDirectory => {
+                        self.create_entry(&cmd.arg, PathKind::Dir, undo_stack, config);
+                        result.changed(&self.working_directory);
+                    }
+                    CommandKind::IncrementalSearch => {
+                        self.incremental_search_results.clear();
+                        for (i, entry) in self.entries.iter().enumerate() {
+                            if let Some(name) = entry.file_name()
+                                && name.to_string_lossy().starts_with(&cmd.arg)
+                            {
+                                self.incremental_search_results.push(i);
                             }
                         }
-                        if self.incremental_search_results.len() > 0 {
+                        if !self.incremental_search_results.is_empty() {
                             if let Some(selected) = self.table_state.selected() {
                                 if let Some((result_index, &entry_index)) = self
                                     .incremental_search_results
@@ -286,34 +1606,669 @@ impl Panel {
                             // TODO: Show some sort of message to inform the user that no matches were found
                         }
                     }
-                    CommandKind::Custom(_) => todo!(),
+                    CommandKind::Filter => self.set_filter(cmd.arg),
+                    CommandKind::Shell => {
+                        // nothing is selected without entries
+                        let paths = self
+                            .take_selection()
+                            .into_iter()
+                            .map(|i| shell_quote(&self.entries[i]))
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        result.shell_command = Some(cmd.arg.replace("%s", &paths));
+                    }
+                    CommandKind::Compress => {
+                        let name = cmd.arg.trim();
+                        if name.is_empty() || self.table_state.selected().is_none() {
+                            continue;
+                        }
+                        let mut archive = self.working_directory.join(name);
+                        if archive.extension().is_none_or(|e| e != "zip") {
+                            archive.as_mut_os_string().push(".zip");
+                        }
+                        let archive = new_path(archive, config);
+                        let indices = self.take_selection();
+                        let entries = self.entries_at(&indices);
+                        match archive::compress(&entries, &archive) {
+                            Ok(()) => undo_stack.push(Operation::Create {
+                                path: archive,
+                                path_kind: PathKind::File,
+                            }),
+                            Err(err) => self.errors.push(err),
+                        }
+                        self.read_working_dir();
+                        result.changed(&self.working_directory);
+                    }
+                    CommandKind::Find => {
+                        let pattern = cmd.arg.trim();
+                        if !pattern.is_empty() {
+                            self.find = Some(Find::start(
+                                self.working_directory.clone(),
+                                pattern,
+                                config.find_max_depth as usize,
+                            ));
+                        }
+                    }
+                    CommandKind::SelectGlob => {
+                        let pattern = match glob::Pattern::new(cmd.arg.trim()) {
+                            Ok(pattern) => pattern,
+                            Err(err) => {
+                                self.errors.push(WalkedError::Message(format!(
+                                    "'{}' isn't a valid glob: {}",
+                                    cmd.arg.trim(),
+                                    err.msg
+                                )));
+                                continue;
+                            }
+                        };
+                        let matches = (0..self.entries.len())
+                            .filter(|&i| {
+                                self.entries[i]
+                                    .file_name()
+                                    .is_some_and(|name| pattern.matches(&name.to_string_lossy()))
+                            })
+                            .collect::<Vec<_>>();
+                        if matches.is_empty() {
+                            self.errors.push(WalkedError::Message(format!(
+                                "No entry matches '{}'",
+                                cmd.arg.trim()
+                            )));
+                            continue;
+                        }
+                        if self.selection_start.is_some() {
+                            // the range being extended is kept along with the matches
+                            self.selection = self.selected_indices().into_iter().collect();
+                            self.selection_start = None;
+                        }
+                        self.selection.extend(matches);
+                    }
+                    CommandKind::PatternRename => {
+                        if self.table_state.selected().is_none() {
+                            continue;
+                        }
+                        let Some((search, replace)) = cmd.arg.split_once('/') else {
+                            self.errors.push(WalkedError::Message(format!(
+                                "'{}' isn't a search/replace expression",
+                                cmd.arg
+                            )));
+                            continue;
+                        };
+                        let regex = if config.pattern_rename_regex {
+                            match Regex::new(search) {
+                                Ok(regex) => Some(regex),
+                                Err(err) => {
+                                    self.errors.push(WalkedError::Message(format!(
+                                        "'{search}' isn't a valid regex: {err}"
+                                    )));
+                                    continue;
+                                }
+                            }
+                        } else {
+                            None
+                        };
+                        let rename = |name: &str| match &regex {
+                            Some(regex) => regex.replace_all(name, replace).to_string(),
+                            None if search.is_empty() => name.to_string(),
+                            None => name.replace(search, replace),
+                        };
+                        let errors_before = self.errors.len();
+                        let mut entries = Vec::new();
+                        let mut names = Vec::new();
+                        for entry in self.entries_at(&self.selected_indices()) {
+                            let Some(name) = entry.file_name() else {
+                                continue;
+                            };
+                            let Some(name) = name.to_str() else {
+                                // the replacement would only see a lossy copy of the name
+                                let lossy = name.to_string_lossy();
+                                if rename(&lossy) != lossy {
+                                    self.errors.push(WalkedError::Message(format!(
+                                        "'{}' isn't valid UTF-8, it's left as it is",
+                                        entry.display()
+                                    )));
+                                }
+                                continue;
+                            };
+                            let new_name = rename(name);
+                            if new_name != name {
+                                entries.push(entry);
+                                names.push(new_name);
+                            }
+                        }
+                        let Some(renames) = self.plan_renames(&entries, &names, config) else {
+                            continue;
+                        };
+                        if renames.is_empty() {
+                            if self.errors.len() == errors_before {
+                                self.errors.push(WalkedError::Message(format!(
+                                    "No names match '{search}'"
+                                )));
+                            }
+                            continue;
+                        }
+                        self.pending_rename = Some(PendingRename { renames });
+                        self.mode = PanelMode::Confirm;
+                    }
+                    CommandKind::NumberRename => {
+                        if self.table_state.selected().is_none() {
+                            continue;
+                        }
+                        let Some((template, start)) = parse_number_template(&cmd.arg) else {
+                            self.errors.push(WalkedError::Message(format!(
+                                "'{}' doesn't have a '#' to put the numbers in",
+                                cmd.arg
+                            )));
+                            continue;
+                        };
+                        let entries = self.entries_at(&self.selected_indices());
+                        let names = number_names(&entries, template, start);
+                        let Some(renames) = self.plan_renames(&entries, &names, config) else {
+                            continue;
+                        };
+                        self.clear_selection();
+                        let renamed = rename_entries(&renames, &mut self.errors);
+                        if !renamed.is_empty() {
+                            undo_stack.push(Operation::BulkRename { renames: renamed });
+                        }
+                        self.read_working_dir();
+                        result.changed(&self.working_directory);
+                    }
+                    CommandKind::Chmod => {
+                        let mode = match u32::from_str_radix(cmd.arg.trim(), 8) {
+                            Ok(mode) if mode <= 0o7777 => mode,
+                            _ => {
+                                self.errors.push(WalkedError::Message(format!(
+                                    "'{}' is not an octal mode like 644",
+                                    cmd.arg.trim()
+                                )));
+                                continue;
+                            }
+                        };
+                        let indices = self.take_selection();
+                        let errors_before = self.errors.len();
+                        for (n, &i) in indices.iter().enumerate() {
+                            if config.stop_on_first_error && self.errors.len() > errors_before {
+                                self.errors
+                                    .push(batch_stopped(&self.entries_at(&indices[n..])));
+                                break;
+                            }
+                            if let Err(err) = set_mode(&self.entries[i], mode) {
+                                self.errors.push(WalkedError::from_io(
+                                    &err,
+                                    &self.entries[i],
+                                    PathKind::Ambigious,
+                                    format!(
+                                        "Couldn't change the mode of '{}'",
+                                        self.entries[i].display()
+                                    ),
+                                ));
+                            }
+                        }
+                        self.read_working_dir();
+                        result.changed(&self.working_directory);
+                    }
+                    CommandKind::Goto => {
+                        let dir = match expand_path(cmd.arg.trim()) {
+                            Ok(dir) => dir,
+                            Err(err) => {
+                                self.errors.push(err);
+                                continue;
+                            }
+                        };
+                        // relative paths are relative to the working directory, `join` keeps
+                        // absolute ones as they are
+                        let pattern = dir.clone();
+                        let dir = normalize(&self.working_directory.join(dir));
+                        if !dir.is_dir() && pattern.contains(['*', '?', '[']) {
+                            self.goto_glob(&pattern, cmd.arg.trim(), config);
+                        } else if dir.is_dir() {
+                            self.change_directory(dir, config);
+                        } else if dir.exists() {
+                            self.errors.push(WalkedError::Message(format!(
+                                "'{}' is not a directory",
+                                dir.display()
+                            )));
+                        } else {
+                            self.errors.push(WalkedError::PathNotFound {
+                                path: dir,
+                                path_kind: PathKind::Dir,
+                            });
+                        }
+                    }
+                    CommandKind::Jump => {
+                        if let Some(dir) = frecency.best_match(&cmd.arg) {
+                            self.change_directory(dir, config);
+                        } else {
+                            self.errors.push(WalkedError::Message(format!(
+                                "No visited directory matches '{}'",
+                                cmd.arg
+                            )));
+                        }
+                    }
+                    CommandKind::Custom(name) => {
+                        self.errors.push(WalkedError::Message(format!(
+                            "There is no '{name}' command"
+                        )));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Decides where each entry of `pending` is pasted, starting with `answer` for the first one
+    /// if the user was asked about it. Pastes them once all of them are decided, or waits in
+    /// [`PanelMode::Confirm`] for the next answer.
+    fn continue_paste(
+        &mut self,
+        mut pending: PendingPaste,
+        mut answer: Option<PasteConflictPolicy>,
+        result: &mut PanelFrameData,
+        clipboard: &mut Clipboard,
+        undo_stack: &mut UndoStack,
+        config: &Config,
+    ) {
+        while let Some(src) = pending.remaining.front().cloned() {
+            if pending.cut && src.parent() == Some(&self.working_directory) {
+                // moving an entry into the directory it's already in does nothing
+                pending.remaining.pop_front();
+                continue;
+            }
+            let dest = self.working_directory.join(src.file_name().unwrap());
+            let taken = dest.symlink_metadata().is_ok();
+            let planned = pending.jobs.iter().any(|(_, d, _)| *d == dest);
+            let policy = if !taken && !planned {
+                pending.remaining.pop_front();
+                pending.jobs.push((src, dest, false));
+                continue;
+            } else if !taken || src.starts_with(&dest) || dest.is_dir() != src.is_dir() {
+                // replacing an entry pasted along with this one, or the entry itself (or what
+                // contains it), would lose data, and type conflicts have their own policy
+                PasteConflictPolicy::Rename
+            } else {
+                answer
+                    .take()
+                    .or(pending.policy)
+                    .unwrap_or(config.paste_conflict)
+            };
+            if policy == PasteConflictPolicy::Ask {
+                self.pending_paste = Some(pending);
+                self.mode = PanelMode::Confirm;
+                return;
+            }
+            pending.remaining.pop_front();
+            match policy {
+                PasteConflictPolicy::Ask | PasteConflictPolicy::Skip => (),
+                PasteConflictPolicy::Overwrite => pending.jobs.push((src, dest, true)),
+                PasteConflictPolicy::Rename => {
+                    let Some(mut dest) = resolve_conflict(&src, dest, config, &mut self.errors)
+                    else {
+                        continue;
+                    };
+                    // nothing is created before all entries are decided, so entries with the same
+                    // name have to be told apart here
+                    if pending.jobs.iter().any(|(_, d, _)| *d == dest) {
+                        dest = new_path_where(
+                            &dest,
+                            &config.collision_suffix,
+                            config.collision_before_extension && !src.is_dir(),
+                            |path| {
+                                path.symlink_metadata().is_ok()
+                                    || pending.jobs.iter().any(|(_, d, _)| d == path)
+                            },
+                        );
+                    }
+                    pending.jobs.push((src, dest, false));
+                }
+            }
+        }
+        self.paste(pending, result, clipboard, undo_stack, config);
+    }
+
+    fn paste(
+        &mut self,
+        pending: PendingPaste,
+        result: &mut PanelFrameData,
+        clipboard: &mut Clipboard,
+        undo_stack: &mut UndoStack,
+        config: &Config,
+    ) {
+        let PendingPaste { cut, jobs, .. } = pending;
+        if cut {
+            // the cut entries are gone from their old place, so there's nothing left to paste
+            *clipboard = Clipboard::default();
+        }
+        // moves are mostly renames, so only copies can take long
+        let (files, bytes) = if cut {
+            (0, 0)
+        } else {
+            jobs.iter()
+                .map(|(src, _, _)| transfer::count(src))
+                .fold((0, 0), |(files, bytes), (f, b)| (files + f, bytes + b))
+        };
+        let background = files > BACKGROUND_FILES || bytes > BACKGROUND_BYTES;
+        let mut refresh = false;
+        let mut done = Vec::new();
+        let mut copies = Vec::new();
+        let mut replaced = Vec::new();
+        let mut replaced_by_copies = Vec::new();
+        let errors_before = self.errors.len();
+        for (n, (src, dest, overwrite)) in jobs.iter().enumerate() {
+            if config.stop_on_first_error && self.errors.len() > errors_before {
+                let skipped = jobs[n..]
+                    .iter()
+                    .map(|(src, _, _)| src.clone())
+                    .collect::<Vec<_>>();
+                self.errors.push(batch_stopped(&skipped));
+                break;
+            }
+            refresh = true;
+            // what's overwritten is only trashed, so it can be put back if the paste fails or is
+            // undone
+            let existing = if *overwrite {
+                match trash::trash(dest) {
+                    Ok(entry) => Some(entry),
+                    Err(err) => {
+                        self.errors.push(err);
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+            if background {
+                copies.push((src.clone(), dest.clone()));
+                replaced_by_copies.extend(existing);
+                continue;
+            }
+            let pasted = if cut {
+                move_entry(src, dest, &mut self.errors, config.use_reflink)
+            } else {
+                copy_entry(src, dest, &mut self.errors, config.use_reflink).created()
+            };
+            if pasted {
+                done.push((src.clone(), dest.clone()));
+                replaced.extend(existing);
+            } else if let Some(entry) = existing {
+                put_back(&entry, &mut self.errors);
+            }
+        }
+        if !copies.is_empty() {
+            let mut transfer = Transfer::start(copies, files, bytes, config.use_reflink);
+            transfer.replaced = replaced_by_copies;
+            self.transfer = Some(transfer);
+        }
+        if !done.is_empty() {
+            undo_stack.push(if cut {
+                Operation::Move {
+                    moves: done,
+                    replaced,
+                }
+            } else {
+                Operation::Copy {
+                    copies: done,
+                    replaced,
+                    copied_at: SystemTime::now(),
+                }
+            });
+        }
+        if refresh {
+            self.read_working_dir();
+            result.changed(&self.working_directory);
+            if cut {
+                for dir in jobs.iter().filter_map(|(src, _, _)| src.parent()) {
+                    result.changed(dir);
+                }
+            }
+        }
+    }
+
+    /// Called when the cursor moves, which ends the range being extended unless it's anchored in
+    /// visual mode.
+    fn leave_range(&mut self) {
+        if !self.visual {
+            self.selection_start = None;
+        }
+    }
+
+    /// Whether anything is selected, otherwise operations act on the entry under the cursor.
+    pub fn has_selection(&self) -> bool {
+        self.selection_start.is_some() || !self.selection.is_empty()
+    }
+
+    /// The selected entries in order, or the one under the cursor if nothing is selected.
+    pub fn selected_indices(&self) -> Vec<usize> {
+        let Some(current_entry) = self.table_state.selected() else {
+            return Vec::new();
+        };
+        let mut indices = self.selection.iter().copied().collect::<Vec<_>>();
+        if self.selection_start.is_some() || self.selection.is_empty() {
+            indices.extend(selection_range(self.selection_start, current_entry));
+        }
+        indices.sort_unstable();
+        indices.dedup();
+        indices.retain(|&i| i < self.entries.len());
+        indices
+    }
+
+    /// Like [`Panel::selected_indices`], but the selection is cleared too.
+    pub fn take_selection(&mut self) -> Vec<usize> {
+        let indices = self.selected_indices();
+        self.clear_selection();
+        indices
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection_start = None;
+        self.visual = false;
+        self.selection.clear();
+    }
+
+    fn entries_at(&self, indices: &[usize]) -> Vec<PathBuf> {
+        indices.iter().map(|&i| self.entries[i].clone()).collect()
+    }
+
+    /// Renames the entries of the bulk rename to the names that were edited, nothing is renamed
+    /// unless every name is valid. Returns the renames that were done.
+    pub fn finish_bulk_rename(
+        &mut self,
+        edited: bool,
+        config: &Config,
+    ) -> Option<Vec<(PathBuf, PathBuf)>> {
+        let BulkRename { file, entries } = self.bulk_rename.take()?;
+        let content = std::fs::read_to_string(&file);
+        let _ = std::fs::remove_file(&file);
+        if !edited {
+            return None;
+        }
+        let content = match content {
+            Ok(content) => content,
+            Err(err) => {
+                self.errors.push(WalkedError::from_io(
+                    &err,
+                    &file,
+                    PathKind::File,
+                    format!("Couldn't read '{}'", file.display()),
+                ));
+                return None;
+            }
+        };
+        let names = content.lines().map(str::to_string).collect::<Vec<_>>();
+        if names.len() != entries.len() {
+            self.errors.push(WalkedError::Message(format!(
+                "Expected {} names but got {}, nothing was renamed",
+                entries.len(),
+                names.len()
+            )));
+            return None;
+        }
+        let renames = self.plan_renames(&entries, &names, config)?;
+        let renamed = rename_entries(&renames, &mut self.errors);
+        (!renamed.is_empty()).then_some(renamed)
+    }
+
+    /// Pairs each entry with its new name, leaving out the ones that keep their name. Returns
+    /// `None` if any of the names is invalid or taken, everything is checked before the first
+    /// rename so a mistake doesn't leave a half done rename behind.
+    fn plan_renames(
+        &mut self,
+        entries: &[PathBuf],
+        names: &[String],
+        config: &Config,
+    ) -> Option<Vec<(PathBuf, PathBuf)>> {
+        let sources = entries.iter().collect::<HashSet<_>>();
+        let mut targets = HashSet::new();
+        let mut renames = Vec::new();
+        let errors_before = self.errors.len();
+        for (entry, name) in entries.iter().zip(names) {
+            let Some(dir) = entry.parent() else {
+                continue;
+            };
+            let target = dir.join(name);
+            if let Err(err) = check_name(name, config) {
+                self.errors.push(err);
+            } else if !targets.insert(target.clone()) {
+                self.errors.push(WalkedError::Message(format!(
+                    "'{name}' is given to more than one entry"
+                )));
+            } else if target != *entry
+                && target.symlink_metadata().is_ok()
+                && !sources.contains(&target)
+            {
+                self.errors.push(WalkedError::Message(format!(
+                    "'{}' already exists",
+                    target.display()
+                )));
+            } else if target != *entry {
+                renames.push((entry.clone(), target));
+            }
+        }
+        if self.errors.len() > errors_before {
+            self.errors
+                .push(WalkedError::Message("Nothing was renamed".to_string()));
+            return None;
+        }
+        Some(renames)
+    }
+
+    /// Removes (or moves to the trash) `entries`, returns true if anything changed.
+    fn remove_entries(
+        &mut self,
+        entries: &[PathBuf],
+        use_trash: bool,
+        undo_stack: &mut UndoStack,
+        config: &Config,
+    ) -> bool {
+        let errors_before = self.errors.len();
+        let mut refresh = false;
+        let mut trashed = Vec::new();
+
+        for (n, entry) in entries.iter().enumerate() {
+            if config.stop_on_first_error && self.errors.len() > errors_before {
+                self.errors.push(batch_stopped(&entries[n..]));
+                break;
+            }
+            // it may have been removed by something else while the removal was being confirmed
+            let Ok(metadata) = entry.symlink_metadata() else {
+                self.errors.push(WalkedError::PathNotFound {
+                    path: entry.clone(),
+                    path_kind: PathKind::Ambigious,
+                });
+                continue;
+            };
+            if use_trash {
+                match trash::trash(entry) {
+                    Ok(trashed_entry) => trashed.push(trashed_entry),
+                    Err(err) => self.errors.push(err),
+                }
+                refresh = true;
+            } else if metadata.is_dir() {
+                // empty directories are fine too, so there's no need to look inside first
+                if let Err(err) = std::fs::remove_dir_all(entry) {
+                    self.errors.push(WalkedError::from_io(
+                        &err,
+                        entry,
+                        PathKind::Dir,
+                        format!("Couldn't remove directory '{}'", entry.display()),
+                    ));
+                }
+                refresh = true;
+            } else {
+                // symlinks (broken ones too), FIFOs and sockets are removed like files
+                if let Err(err) = std::fs::remove_file(entry) {
+                    match err.kind() {
+                        std::io::ErrorKind::NotFound => {
+                            self.errors.push(WalkedError::PathNotFound {
+                                path: entry.clone(),
+                                path_kind: PathKind::File,
+                            })
+                        }
+                        std::io::ErrorKind::PermissionDenied => {
+                            self.errors.push(WalkedError::PermissionDenied {
+                                path: entry.clone(),
+                                path_kind: PathKind::File,
+                            })
+                        }
+                        _ => self.errors.push(WalkedError::Message(format!(
+                            "Couldn't remove file '{}'",
+                            entry.display()
+                        ))),
+                    }
                 }
+                refresh = true;
             }
         }
+
+        if !trashed.is_empty() {
+            undo_stack.push(Operation::Trash { entries: trashed });
+        }
+        refresh
     }
 
     /// Returns false if quit was pressed
     pub fn update(
         &mut self,
         key_event: KeyEvent,
-        clipboard: &mut Vec<PathBuf>,
+        clipboard: &mut Clipboard,
+        undo_stack: &mut UndoStack,
         config: &Config,
     ) -> PanelFrameData {
         let mut result = PanelFrameData {
             quit: false,
             should_refresh: false,
+            shell_command: None,
+            edit_file: None,
+            diff_files: None,
+            open_suspended: None,
+            quit_selection: None,
+            cancelled: false,
+            changed_dirs: Vec::new(),
         };
 
-        if self.errors.len() > 0 {
+        // works in every mode, walked is supposed to be left right away
+        if key_event == config.cancel {
+            result.quit = true;
+            result.cancelled = true;
+            return result;
+        }
+        if !self.errors.is_empty() {
             if key_event.kind == KeyEventKind::Press {
                 self.errors.clear();
+                self.errors_logged = 0;
             }
         } else {
             match self.mode {
                 PanelMode::Prompt => {
-                    if key_event == config.quit {
+                    // any key other than tab starts completing over
+                    let completion = if key_event.is_press() {
+                        self.completion.take()
+                    } else {
+                        None
+                    };
+                    if key_event == config.quit && !config.normal_mode_cancels {
                         result.quit = true;
                         return result;
+                    } else if config.normal_mode_cancels && key_event == config.normal_mode {
+                        self.cancel();
                     } else if key_event.code == KeyCode::Enter && key_event.is_press() {
                         self.queue.push(Command {
                             kind: self.command_prompt.clone().unwrap(),
@@ -323,26 +2278,37 @@ impl Panel {
                         self.command_prompt = None;
                         self.mode = PanelMode::Normal;
                     } else if key_event.code == KeyCode::Esc && key_event.is_press() {
-                        self.edit_buffer.clear();
-                        self.command_prompt = None;
-                        self.mode = PanelMode::Normal;
+                        self.cancel();
+                    } else if key_event.code == KeyCode::Tab && key_event.is_press() {
+                        self.complete(completion);
                     } else if key_event.code == KeyCode::Backspace && key_event.is_press() {
                         self.edit_buffer.pop();
+                        if matches!(self.command_prompt, Some(CommandKind::Filter)) {
+                            self.set_filter(self.edit_buffer.clone());
+                        }
                     } else if let KeyCode::Char(c) = key_event.code
                         && key_event.is_press()
                     {
                         self.edit_buffer.push(c);
+                        if matches!(self.command_prompt, Some(CommandKind::Filter)) {
+                            self.set_filter(self.edit_buffer.clone());
+                        }
                     }
                 }
                 PanelMode::Search => {
-                    if key_event == config.quit {
+                    if config.normal_mode_cancels
+                        && (key_event == config.normal_mode || key_event == config.quit)
+                    {
+                        self.cancel();
+                    } else if key_event == config.quit {
                         result.quit = true;
                         return result;
                     } else if key_event.code == KeyCode::Esc {
-                        self.mode = PanelMode::Normal;
+                        self.cancel();
                     } else if key_event == config.dir_walk {
                         if self.walk(
                             self.incremental_search_results[self.current_incremental_search_result],
+                            config,
                         ) {
                             self.table_state.select_first();
                             self.refresh_cursor();
@@ -362,7 +2328,7 @@ impl Panel {
                         self.cursor_offset = 0;
                         self.table_state.select_column(Some(1));
                     } else if key_event == config.prev_search_result {
-                        if self.current_incremental_search_result <= 0 {
+                        if self.current_incremental_search_result == 0 {
                             self.current_incremental_search_result =
                                 self.incremental_search_results.len() - 1;
                         } else {
@@ -376,34 +2342,137 @@ impl Panel {
                     }
                 }
                 PanelMode::Normal => {
-                    if key_event == config.dir_walk {
-                        if let Some(i) = self.table_state.selected() {
-                            if self.walk(i) {
-                                self.table_state.select_first();
-                                self.refresh_cursor();
-                            }
+                    // any other key abandons a typed entry number
+                    let number_prefix = if key_event.is_press() {
+                        self.number_prefix.take()
+                    } else {
+                        self.number_prefix
+                    };
+                    if key_event.code == KeyCode::Enter
+                        && key_event.is_press()
+                        && number_prefix.is_some()
+                    {
+                        self.number_prefix = number_prefix;
+                        self.apply_number_prefix();
+                    } else if let Some(transfer) = self.transfer.as_ref().filter(|t| {
+                        key_event.code == KeyCode::Esc && key_event.is_press() && !t.is_cancelled()
+                    }) {
+                        transfer.cancel();
+                    } else if self.visual && key_event == config.normal_mode {
+                        self.visual = false;
+                        self.selection_start = None;
+                    } else if config.normal_mode_cancels && key_event == config.normal_mode {
+                        self.cancel();
+                    } else if key_event == config.dir_walk {
+                        if let Some(i) = self.table_state.selected()
+                            && self.walk(i, config)
+                        {
+                            self.table_state.select_first();
+                            self.refresh_cursor();
                         }
                     } else if key_event == config.dir_up {
-                        if self.parent() {
+                        if self.parent(config) {
                             self.table_state.select_first();
                             self.refresh_cursor();
                         }
+                    } else if key_event == config.back {
+                        self.back(config);
+                    } else if key_event == config.forward {
+                        self.forward(config);
                     } else if key_event == config.up {
-                        self.selection_start = None;
+                        self.leave_range();
                         self.table_state.scroll_up_by(1);
                         self.refresh_cursor();
                     } else if key_event == config.select_up {
-                        if let None = self.selection_start {
+                        if self.selection_start.is_none() {
                             self.selection_start = self.table_state.selected();
                         }
                         self.table_state.scroll_up_by(1);
                         self.refresh_cursor();
+                    } else if key_event == config.scroll_left {
+                        self.name_scroll = self.name_scroll.saturating_sub(NAME_SCROLL_STEP);
+                    } else if key_event == config.scroll_right {
+                        // how far names can be scrolled is only known once they're drawn
+                        self.name_scroll = self.name_scroll.saturating_add(NAME_SCROLL_STEP);
+                    } else if key_event == config.goto_top {
+                        self.leave_range();
+                        self.table_state
+                            .select(clamp_selection(self.entries.len(), 0));
+                        self.refresh_cursor();
+                    } else if key_event == config.goto_bottom {
+                        self.leave_range();
+                        self.table_state
+                            .select(clamp_selection(self.entries.len(), usize::MAX));
+                        *self.table_state.offset_mut() =
+                            self.entries.len().saturating_sub(self.view_height as usize);
+                        self.refresh_cursor();
+                    } else if key_event == config.page_up || key_event == config.half_page_up {
+                        let distance = if key_event == config.page_up {
+                            self.view_height
+                        } else {
+                            self.view_height / 2
+                        };
+                        self.leave_range();
+                        self.table_state.scroll_up_by(distance.max(1));
+                        self.refresh_cursor();
+                    } else if key_event == config.page_down || key_event == config.half_page_down {
+                        let distance = if key_event == config.page_down {
+                            self.view_height
+                        } else {
+                            self.view_height / 2
+                        };
+                        self.leave_range();
+                        if let Some(i) = self.table_state.selected() {
+                            self.table_state.select(clamp_selection(
+                                self.entries.len(),
+                                i + distance.max(1) as usize,
+                            ));
+                        }
+                        self.refresh_cursor();
                     } else if key_event == config.down {
-                        self.selection_start = None;
+                        self.leave_range();
                         self.table_state.scroll_down_by(1);
                         self.refresh_cursor();
+                    } else if key_event == config.select_all && !self.entries.is_empty() {
+                        self.selection_start = Some(0);
+                        self.table_state.select(Some(self.entries.len() - 1));
+                        self.refresh_cursor();
+                    } else if key_event == config.invert_selection && !self.entries.is_empty() {
+                        let selected = self.selected_indices();
+                        self.selection = (0..self.entries.len())
+                            .filter(|i| selected.binary_search(i).is_err())
+                            .collect();
+                        self.selection_start = None;
+                    } else if key_event == config.toggle_select && !self.entries.is_empty() {
+                        if let Some(current_entry) = self.table_state.selected() {
+                            if self.selection_start.is_some() {
+                                // the range being extended would be dropped once the cursor moves
+                                // on, so it's kept as it is
+                                self.selection = self.selected_indices().into_iter().collect();
+                                self.selection_start = None;
+                            } else if !self.selection.remove(&current_entry) {
+                                self.selection.insert(current_entry);
+                            }
+                            self.table_state.scroll_down_by(1);
+                            self.refresh_cursor();
+                        }
+                    } else if key_event == config.find {
+                        self.prompt(CommandKind::Find);
+                    } else if key_event == config.select_glob {
+                        self.prompt(CommandKind::SelectGlob);
+                    } else if key_event == config.clear_selection {
+                        self.clear_selection();
+                    } else if key_event == config.visual_mode {
+                        if self.visual {
+                            self.visual = false;
+                            self.selection_start = None;
+                        } else if let Some(cursor) = self.table_state.selected() {
+                            // a range started with `select_up`/`select_down` stays as it is
+                            self.selection_start.get_or_insert(cursor);
+                            self.visual = true;
+                        }
                     } else if key_event == config.select_down {
-                        if let None = self.selection_start {
+                        if self.selection_start.is_none() {
                             self.selection_start = self.table_state.selected();
                         }
                         self.table_state.scroll_down_by(1);
@@ -416,292 +2485,388 @@ impl Panel {
                         if self.cursor_offset < self.current_entry_length as u16 {
                             self.cursor_offset += 1;
                         }
+                    } else if key_event == config.shell {
+                        self.prompt(CommandKind::Shell);
+                    } else if key_event == config.chmod && !self.entries.is_empty() {
+                        if cfg!(unix) {
+                            self.prompt(CommandKind::Chmod);
+                            // starts from the current mode of the entry under the cursor
+                            if let Some(mode) = self
+                                .table_state
+                                .selected()
+                                .and_then(|i| self.metadata[i].as_ref())
+                                .and_then(mode)
+                            {
+                                self.edit_buffer = format!("{:o}", mode & 0o7777);
+                            }
+                        } else {
+                            self.errors.push(WalkedError::Message(
+                                "Changing permissions is only supported on Unix".to_string(),
+                            ));
+                        }
+                    } else if key_event == config.compress && !self.entries.is_empty() {
+                        self.prompt(CommandKind::Compress);
+                        // a single entry gives its name to the archive, a selection the directory's
+                        if let Some(current_entry) = self.table_state.selected() {
+                            let named = if self.has_selection() {
+                                &self.working_directory
+                            } else {
+                                &self.entries[current_entry]
+                            };
+                            if let Some(name) = named.file_name() {
+                                self.edit_buffer = format!("{}.zip", name.to_string_lossy());
+                            }
+                        }
+                    } else if key_event == config.pattern_rename && !self.entries.is_empty() {
+                        self.prompt(CommandKind::PatternRename);
+                    } else if key_event == config.number_rename && !self.entries.is_empty() {
+                        self.prompt(CommandKind::NumberRename);
+                    } else if key_event == config.goto {
+                        self.prompt(CommandKind::Goto);
+                    } else if key_event == config.filter {
+                        self.prompt(CommandKind::Filter);
+                        self.edit_buffer = self.filter.clone();
                     } else if key_event == config.incremental_search {
                         self.prompt(CommandKind::IncrementalSearch);
-                    } else if key_event == config.new_file {
-                        self.prompt(CommandKind::NewFile);
-                    } else if key_event == config.new_directory {
-                        self.prompt(CommandKind::NewDirectory);
-                    } else if key_event == config.duplicate && self.entries.len() > 0 {
-                        if let Some(current_entry) = self.table_state.selected() {
-                            let selection_start =
-                                if let Some(selection_start) = self.selection_start {
-                                    self.selection_start = None;
-                                    selection_start
-                                } else {
-                                    current_entry
-                                };
+                    } else if key_event == config.open && !self.entries.is_empty() {
+                        if let Some(i) = self.table_state.selected() {
+                            let path = &self.entries[i];
+                            match config.open_with_for(path) {
+                                Some(open_with) if open_with.suspend => {
+                                    result.open_suspended =
+                                        Some((open_with.command.clone(), path.clone()));
+                                }
+                                open_with => {
+                                    let command = open_with
+                                        .map_or(&config.open_command, |open_with| {
+                                            &open_with.command
+                                        });
+                                    if let Err(err) = spawn_detached(command, path) {
+                                        self.errors.push(err);
+                                    }
+                                }
+                            }
+                        }
+                    } else if key_event == config.diff && !self.entries.is_empty() {
+                        let is_file =
+                            |i: usize| !self.metadata[i].as_ref().is_some_and(|m| m.is_dir());
+                        match self.selected_indices()[..] {
+                            [a, b] if is_file(a) && is_file(b) => {
+                                self.clear_selection();
+                                result.diff_files =
+                                    Some((self.entries[a].clone(), self.entries[b].clone()));
+                            }
+                            _ => self.errors.push(WalkedError::Message(
+                                "Select exactly two files to compare them".to_string(),
+                            )),
+                        }
+                    } else if key_event == config.dir_size && !self.entries.is_empty() {
+                        if let Some(i) = self.table_state.selected()
+                            && self.metadata[i].as_ref().is_some_and(|m| m.is_dir())
+                        {
+                            self.dir_sizes.compute(&self.entries[i]);
+                        }
+                    } else if key_event == config.open_file_manager {
+                        if let Err(err) =
+                            spawn_detached(&config.file_manager_command, &self.working_directory)
+                        {
+                            self.errors.push(err);
+                        }
+                    } else if key_event == config.jump {
+                        self.prompt(CommandKind::Jump);
+                    } else if key_event == config.new_file || key_event == config.new_directory {
+                        let (kind, path_kind, placeholder) = if key_event == config.new_file {
+                            (CommandKind::NewFile, PathKind::File, &config.new_file_name)
+                        } else {
+                            (
+                                CommandKind::NewDirectory,
+                                PathKind::Dir,
+                                &config.new_dir_name,
+                            )
+                        };
+                        if config.inline_new_entries {
+                            if self.create_entry(placeholder, path_kind, undo_stack, config) {
+                                result.changed(&self.working_directory);
+                                self.start_rename(config);
+                                // the placeholder is typed over rather than edited
+                                self.edit_buffer.clear();
+                                self.cursor_offset = 0;
+                            }
+                        } else {
+                            self.prompt(kind);
+                        }
+                    } else if key_event == config.bulk_rename && !self.entries.is_empty() {
+                        if self.table_state.selected().is_some() {
+                            let indices = self.take_selection();
+                            let entries = self.entries_at(&indices);
+                            match write_names(&entries) {
+                                Ok(file) => {
+                                    result.edit_file = Some(file.clone());
+                                    self.bulk_rename = Some(BulkRename { file, entries });
+                                }
+                                Err(err) => self.errors.push(err),
+                            }
+                        }
+                    } else if key_event == config.duplicate && !self.entries.is_empty() {
+                        if self.table_state.selected().is_some() {
+                            let indices = self.take_selection();
+                            let errors_before = self.errors.len();
                             let mut refresh = false;
+                            let mut copies = Vec::new();
 
-                            for i in current_entry.min(selection_start)
-                                ..=current_entry.max(selection_start)
-                            {
+                            for (n, &i) in indices.iter().enumerate() {
+                                if config.stop_on_first_error && self.errors.len() > errors_before {
+                                    self.errors
+                                        .push(batch_stopped(&self.entries_at(&indices[n..])));
+                                    break;
+                                }
                                 let entry_path = &self.entries[i];
-                                let new_entry_path = new_path(entry_path);
-
-                                if entry_path.is_file() {
-                                    if let Err(err) = std::fs::copy(entry_path, &new_entry_path) {
-                                        match err.kind() {
-                                            std::io::ErrorKind::NotFound => {
-                                                self.errors.push(WalkedError::PathNotFound {
-                                                    path: entry_path.clone(),
-                                                    path_kind: PathKind::File,
-                                                })
-                                            }
-                                            std::io::ErrorKind::PermissionDenied => {
-                                                self.errors.push(WalkedError::PermissionDenied {
-                                                    path: new_entry_path,
-                                                    path_kind: PathKind::File,
-                                                })
-                                            }
-                                            _ => self.errors.push(WalkedError::Message(format!(
-                                                "Couldn't copy file from '{}' to '{}'",
-                                                entry_path.display(),
-                                                new_entry_path.display()
-                                            ))),
-                                        }
-                                    }
-
-                                    refresh = true;
-                                } else if entry_path.is_dir() {
-                                    let new_dir = new_path(entry_path);
-                                    if let Err(err) = std::fs::create_dir(&new_dir) {
-                                        match err.kind() {
-                                            std::io::ErrorKind::PermissionDenied => {
-                                                self.errors.push(WalkedError::PermissionDenied {
-                                                    path: new_dir,
-                                                    path_kind: PathKind::Dir,
-                                                })
-                                            }
-                                            _ => self.errors.push(WalkedError::Message(format!(
-                                                "Couldn't create directory '{}'",
-                                                new_dir.display()
-                                            ))),
-                                        }
-                                    } else {
-                                        copy_recursively(entry_path, &new_dir, &mut self.errors);
-                                    }
-                                    refresh = true;
+                                let new_entry_path = new_path(entry_path, config);
+                                if copy_entry(
+                                    entry_path,
+                                    &new_entry_path,
+                                    &mut self.errors,
+                                    config.use_reflink,
+                                )
+                                .created()
+                                {
+                                    copies.push((entry_path.clone(), new_entry_path));
                                 }
+                                refresh = true;
+                            }
+                            if !copies.is_empty() {
+                                undo_stack.push(Operation::Copy {
+                                    copies,
+                                    replaced: Vec::new(),
+                                    copied_at: SystemTime::now(),
+                                });
                             }
                             if refresh {
                                 self.read_working_dir();
-                                result.should_refresh = true;
+                                result.changed(&self.working_directory);
                             }
                         }
-                    } else if key_event == config.copy && self.entries.len() > 0 {
-                        if let Some(current_entry) = self.table_state.selected() {
-                            clipboard.clear();
-                            if let Some(selection_start) = self.selection_start {
-                                for i in current_entry.min(selection_start)
-                                    ..=current_entry.max(selection_start)
-                                {
-                                    clipboard.push(self.entries[i].clone());
-                                }
+                    } else if (key_event == config.copy || key_event == config.cut)
+                        && !self.entries.is_empty()
+                    {
+                        if self.table_state.selected().is_some() {
+                            let paths = self.entries_at(&self.selected_indices());
+                            *clipboard = if key_event == config.cut {
+                                Clipboard::Cut(paths)
                             } else {
-                                clipboard.push(self.entries[current_entry].clone());
-                            }
+                                Clipboard::Copy(paths)
+                            };
                         }
+                    } else if key_event == config.paste && self.transfer.is_some() {
+                        self.errors.push(WalkedError::Message(
+                            "Wait for the current paste to finish (or cancel it with Esc)"
+                                .to_string(),
+                        ));
                     } else if key_event == config.paste {
+                        let pending = PendingPaste {
+                            cut: matches!(clipboard, Clipboard::Cut(_)),
+                            remaining: clipboard.paths().iter().cloned().collect(),
+                            jobs: Vec::new(),
+                            policy: None,
+                        };
+                        self.continue_paste(
+                            pending,
+                            None,
+                            &mut result,
+                            clipboard,
+                            undo_stack,
+                            config,
+                        );
+                    } else if key_event == config.cycle_sort {
+                        self.sort = self.sort.next();
+                        self.read_working_dir();
+                    } else if key_event == config.reverse_sort {
+                        self.sort_reverse = !self.sort_reverse;
+                        self.read_working_dir();
+                    } else if key_event == config.toggle_directories_first {
+                        self.directories_first = !self.directories_first;
+                        self.read_working_dir();
+                    } else if key_event == config.toggle_case_insensitive_sort {
+                        self.case_insensitive_sort = !self.case_insensitive_sort;
+                        self.read_working_dir();
+                    } else if key_event == config.symlink {
                         let mut refresh = false;
-                        for entry_path in clipboard.iter() {
-                            let new_entry_path = new_path(
-                                self.working_directory.join(entry_path.file_name().unwrap()),
+                        for target in clipboard.paths() {
+                            let link = new_path(
+                                self.working_directory.join(target.file_name().unwrap()),
+                                config,
                             );
-
-                            if entry_path.is_file() {
-                                if let Err(err) = std::fs::copy(entry_path, &new_entry_path) {
-                                    match err.kind() {
-                                        std::io::ErrorKind::NotFound => {
-                                            self.errors.push(WalkedError::PathNotFound {
-                                                path: entry_path.clone(),
-                                                path_kind: PathKind::File,
-                                            })
-                                        }
-                                        std::io::ErrorKind::PermissionDenied => {
-                                            self.errors.push(WalkedError::PermissionDenied {
-                                                path: new_entry_path,
-                                                path_kind: PathKind::File,
-                                            })
-                                        }
-                                        _ => self.errors.push(WalkedError::Message(format!(
-                                            "Couldn't copy file from '{}' to '{}'",
-                                            entry_path.display(),
-                                            new_entry_path.display()
-                                        ))),
-                                    }
-                                }
-                                refresh = true;
-                            } else if entry_path.is_dir() {
-                                if let Err(err) = std::fs::create_dir(&new_entry_path) {
-                                    match err.kind() {
-                                        std::io::ErrorKind::PermissionDenied => {
-                                            self.errors.push(WalkedError::PermissionDenied {
-                                                path: new_entry_path,
-                                                path_kind: PathKind::Dir,
-                                            })
-                                        }
-                                        _ => self.errors.push(WalkedError::Message(format!(
-                                            "Couldn't create directory '{}'",
-                                            new_entry_path.display()
-                                        ))),
-                                    }
-                                } else {
-                                    copy_recursively(entry_path, &new_entry_path, &mut self.errors);
-                                }
-                                refresh = true;
+                            if create_symlink(target, &link, &mut self.errors) {
+                                undo_stack.push(Operation::Symlink {
+                                    target: target.clone(),
+                                    link,
+                                });
                             }
+                            refresh = true;
                         }
                         if refresh {
                             self.read_working_dir();
-                            result.should_refresh = true;
+                            result.changed(&self.working_directory);
                         }
-                    } else if key_event == config.remove && self.entries.len() > 0 {
-                        if let Some(current_entry) = self.table_state.selected() {
-                            let selection_start =
-                                if let Some(selection_start) = self.selection_start {
-                                    self.selection_start = None;
-                                    selection_start
-                                } else {
-                                    current_entry
-                                };
-                            let mut refresh = false;
-
-                            for i in current_entry.min(selection_start)
-                                ..=current_entry.max(selection_start)
-                            {
-                                let entry = &self.entries[i];
-                                if entry.is_file() {
-                                    if let Err(err) = std::fs::remove_file(entry) {
-                                        match err.kind() {
-                                            std::io::ErrorKind::NotFound => {
-                                                self.errors.push(WalkedError::PathNotFound {
-                                                    path: entry.clone(),
-                                                    path_kind: PathKind::File,
-                                                })
-                                            }
-                                            std::io::ErrorKind::PermissionDenied => {
-                                                self.errors.push(WalkedError::PermissionDenied {
-                                                    path: entry.clone(),
-                                                    path_kind: PathKind::File,
-                                                })
-                                            }
-                                            _ => self.errors.push(WalkedError::Message(format!(
-                                                "Couldn't remove file '{}'",
-                                                entry.display()
-                                            ))),
-                                        }
-                                    }
-                                    refresh = true;
-                                } else if entry.is_dir() {
-                                    if let Ok(dir) = std::fs::read_dir(entry) {
-                                        if let Err(err) = if dir.count() > 0 {
-                                            std::fs::remove_dir_all(entry)
-                                        } else {
-                                            std::fs::remove_dir(entry)
-                                        } {
-                                            match err.kind() {
-                                                std::io::ErrorKind::NotFound => {
-                                                    self.errors.push(WalkedError::PathNotFound {
-                                                        path: entry.clone(),
-                                                        path_kind: PathKind::Dir,
-                                                    })
-                                                }
-                                                std::io::ErrorKind::PermissionDenied => self
-                                                    .errors
-                                                    .push(WalkedError::PermissionDenied {
-                                                        path: entry.clone(),
-                                                        path_kind: PathKind::Dir,
-                                                    }),
-                                                _ => {
-                                                    self.errors.push(WalkedError::Message(format!(
-                                                        "Couldn't remove directory '{}'",
-                                                        entry.display()
-                                                    )))
-                                                }
-                                            }
-                                        }
-
-                                        refresh = true;
-                                    }
+                    } else if (key_event == config.remove
+                        || key_event == config.trash
+                        || key_event == config.remove_permanently)
+                        && !self.entries.is_empty()
+                    {
+                        if self.table_state.selected().is_some() {
+                            let entries = self.entries_at(&self.selected_indices());
+                            let use_trash = key_event == config.trash
+                                || (key_event == config.remove && config.use_trash_by_default);
+                            if config.confirm_delete {
+                                self.pending_confirmation =
+                                    Some(PendingOp::new(entries, use_trash));
+                                self.mode = PanelMode::Confirm;
+                            } else {
+                                self.clear_selection();
+                                if self.remove_entries(&entries, use_trash, undo_stack, config) {
+                                    self.read_working_dir();
+                                    result.changed(&self.working_directory);
                                 }
                             }
-
-                            if refresh {
-                                self.read_working_dir();
-                                result.should_refresh = true;
-                            }
                         }
                     } else if key_event == config.insert_mode {
-                        if self.entries.len() > 0 {
-                            self.mode = PanelMode::Insert;
-                            if let Some(i) = self.table_state.selected() {
-                                self.edit_buffer = {
-                                    if let Some(p) = self.entries[i].file_name() {
-                                        p.to_str().unwrap().to_string()
-                                    } else {
-                                        "".to_string()
-                                    }
-                                };
-                            }
-                            self.table_state.select_column(Some(1));
-                        }
+                        self.start_rename(config);
                     } else if key_event == config.quit {
                         result.quit = true;
                         return result;
+                    } else if key_event == config.quit_with_selection {
+                        result.quit = true;
+                        result.quit_selection = Some(self.entries_at(&self.selected_indices()));
+                        return result;
+                    } else if let KeyCode::Char(c @ '0'..='9') = key_event.code
+                        && key_event.modifiers.is_empty()
+                        && key_event.is_press()
+                    {
+                        // digits only get here if they aren't bound to anything else
+                        let n = number_prefix.map_or(0, |(n, _)| n);
+                        let digit = c.to_digit(10).unwrap() as usize;
+                        self.number_prefix =
+                            Some((n.saturating_mul(10).saturating_add(digit), Instant::now()));
                     }
                     self.refresh_cursor();
                 }
+                PanelMode::Confirm => {
+                    if key_event.is_press() {
+                        if let Some(mut pending) = self.pending_paste.take() {
+                            self.mode = PanelMode::Normal;
+                            let answer = match key_event.code {
+                                KeyCode::Char('o') => Some((PasteConflictPolicy::Overwrite, false)),
+                                KeyCode::Char('s') => Some((PasteConflictPolicy::Skip, false)),
+                                KeyCode::Char('r') => Some((PasteConflictPolicy::Rename, false)),
+                                KeyCode::Char('O') => Some((PasteConflictPolicy::Overwrite, true)),
+                                KeyCode::Char('S') => Some((PasteConflictPolicy::Skip, true)),
+                                _ => None,
+                            };
+                            if let Some((answer, all)) = answer {
+                                if all {
+                                    pending.policy = Some(answer);
+                                }
+                                self.continue_paste(
+                                    pending,
+                                    Some(answer),
+                                    &mut result,
+                                    clipboard,
+                                    undo_stack,
+                                    config,
+                                );
+                            } else if key_event == config.normal_mode
+                                || key_event.code == KeyCode::Esc
+                            {
+                                // nothing was pasted yet, so cancelling drops the whole paste
+                            } else {
+                                self.pending_paste = Some(pending);
+                                self.mode = PanelMode::Confirm;
+                            }
+                        } else if let Some(pending) = self.pending_rename.take() {
+                            if key_event.code == KeyCode::Char('y') {
+                                self.clear_selection();
+                                let renamed = rename_entries(&pending.renames, &mut self.errors);
+                                if !renamed.is_empty() {
+                                    undo_stack.push(Operation::BulkRename { renames: renamed });
+                                    self.read_working_dir();
+                                    result.changed(&self.working_directory);
+                                }
+                            }
+                            self.mode = PanelMode::Normal;
+                        } else {
+                            if let Some(op) = self.pending_confirmation.take()
+                                && key_event.code == KeyCode::Char('y')
+                            {
+                                self.clear_selection();
+                                if self.remove_entries(
+                                    &op.entries,
+                                    op.use_trash,
+                                    undo_stack,
+                                    config,
+                                ) {
+                                    self.read_working_dir();
+                                    result.changed(&self.working_directory);
+                                }
+                            }
+                            self.mode = PanelMode::Normal;
+                        }
+                    }
+                }
                 PanelMode::Insert => {
-                    if key_event == config.normal_mode
+                    if config.normal_mode_cancels && key_event == config.normal_mode {
+                        self.cancel();
+                    } else if key_event == config.normal_mode
                         || (key_event.code == KeyCode::Enter
                             && key_event.kind == KeyEventKind::Press)
                     {
                         let mut denied = false;
-                        if let Some(i) = self.table_state.selected() {
-                            if self.edit_buffer.len() > 0 && self.entries.len() > 0 {
-                                let mut dist = self.working_directory.clone();
-                                dist.push(&self.edit_buffer);
-                                let disallowed_chars =
-                                    ['\\', '/', ':', '*', '?', '"', '<', '>', '|'];
-                                if self.edit_buffer.contains(&disallowed_chars) {
+                        if let Some(i) = self.table_state.selected()
+                            && !self.edit_buffer.is_empty()
+                            && !self.entries.is_empty()
+                        {
+                            let mut dist = self.working_directory.clone();
+                            dist.push(&self.edit_buffer);
+                            if let Err(err) = check_name(&self.edit_buffer, config) {
+                                self.mode = PanelMode::Insert;
+                                denied = true;
+                                self.errors.push(err);
+                            } else if dist.exists() {
+                                if dist != self.entries[i] {
                                     self.mode = PanelMode::Insert;
                                     denied = true;
-                                    self.errors.push(WalkedError::Message(format!("Paths can't contain the following characters: {disallowed_chars:?}")));
-                                } else if dist.exists() {
-                                    if dist != self.entries[i] {
-                                        self.mode = PanelMode::Insert;
-                                        denied = true;
-                                        self.errors.push(WalkedError::Message(format!(
-                                            "'{}' already exists",
+                                    self.errors.push(WalkedError::Message(format!(
+                                        "'{}' already exists",
+                                        dist.display()
+                                    )));
+                                }
+                            } else {
+                                if let Err(err) = std::fs::rename(&self.entries[i], &dist) {
+                                    match err.kind() {
+                                        std::io::ErrorKind::NotFound => {
+                                            self.errors.push(WalkedError::PathNotFound {
+                                                path: self.entries[i].clone(),
+                                                path_kind: PathKind::Ambigious,
+                                            })
+                                        }
+                                        std::io::ErrorKind::PermissionDenied => {
+                                            self.errors.push(WalkedError::PermissionDenied {
+                                                path: self.entries[i].clone(),
+                                                path_kind: PathKind::Ambigious,
+                                            })
+                                        }
+                                        _ => self.errors.push(WalkedError::Message(format!(
+                                            "Couldn't rename '{}' to '{}'",
+                                            self.entries[i].display(),
                                             dist.display()
-                                        )));
+                                        ))),
                                     }
                                 } else {
-                                    if let Err(err) = std::fs::rename(&self.entries[i], &dist) {
-                                        match err.kind() {
-                                            std::io::ErrorKind::NotFound => {
-                                                self.errors.push(WalkedError::PathNotFound {
-                                                    path: self.entries[i].clone(),
-                                                    path_kind: PathKind::Ambigious,
-                                                })
-                                            }
-                                            std::io::ErrorKind::PermissionDenied => {
-                                                self.errors.push(WalkedError::PermissionDenied {
-                                                    path: self.entries[i].clone(),
-                                                    path_kind: PathKind::Ambigious,
-                                                })
-                                            }
-                                            _ => self.errors.push(WalkedError::Message(format!(
-                                                "Couldn't rename '{}' to '{}'",
-                                                self.entries[i].display(),
-                                                dist.display()
-                                            ))),
-                                        }
-                                    } else {
-                                        self.read_working_dir();
-                                        result.should_refresh = true;
-                                    }
+                                    undo_stack.push(Operation::Rename {
+                                        from: self.entries[i].clone(),
+                                        to: dist,
+                                    });
+                                    self.read_working_dir();
+                                    result.changed(&self.working_directory);
                                 }
                             }
                         }
@@ -743,124 +2908,1459 @@ impl Panel {
         result
     }
 
-    pub fn refresh_cursor(&mut self) {
+    /// Switches to Insert mode to rename the entry under the cursor.
+    fn start_rename(&mut self, config: &Config) {
+        if self.entries.is_empty() {
+            return;
+        }
+        self.mode = PanelMode::Insert;
         if let Some(i) = self.table_state.selected() {
-            if i < self.entries.len() {
-                let name = {
-                    if let Some(l) = self.entries[i].file_name() {
-                        l.to_str().unwrap().to_string()
-                    } else {
-                        String::new()
-                    }
-                };
-                self.current_entry_length = name.chars().count();
-                self.cursor_offset = self.cursor_offset.min(self.current_entry_length as u16)
+            self.edit_buffer = {
+                if let Some(p) = self.entries[i].file_name() {
+                    p.to_string_lossy().to_string()
+                } else {
+                    "".to_string()
+                }
+            };
+            // dotfiles like ".bashrc" have no extension, so the cursor stays where it was
+            let entry = &self.entries[i];
+            if config.rename_select_stem
+                && !entry.is_dir()
+                && entry.extension().is_some()
+                && let Some(stem) = entry.file_stem()
+            {
+                self.cursor_offset = stem.to_string_lossy().chars().count() as u16;
             }
         }
+        self.table_state.select_column(Some(1));
     }
-    pub fn walk(&mut self, current_entry: usize) -> bool {
+
+    /// Creates a file or directory at `name`, which may be a path like "a/b/c.txt" whose missing
+    /// directories are created too (any of the platform's separators can be used). The working
+    /// directory is read again and the cursor moved to the created entry, returns true if it was
+    /// created.
+    fn create_entry(
+        &mut self,
+        name: &str,
+        path_kind: PathKind,
+        undo_stack: &mut UndoStack,
+        config: &Config,
+    ) -> bool {
+        let name = name.trim_end_matches(std::path::is_separator);
+        if name.trim().is_empty() {
+            return false;
+        }
+        let components = name.split(std::path::is_separator).collect::<Vec<_>>();
+        if let Some(err) = components.iter().find_map(|c| check_name(c, config).err()) {
+            self.errors.push(err);
+            return false;
+        }
+
+        let path = self.working_directory.join(name);
+        let mut created = Vec::new();
+        if let Some(parent) = path.parent() {
+            // the directories that don't exist yet, outermost first
+            let mut missing = parent
+                .ancestors()
+                .take_while(|dir| dir.symlink_metadata().is_err())
+                .map(Path::to_path_buf)
+                .collect::<Vec<_>>();
+            missing.reverse();
+            if let Err(err) = std::fs::create_dir_all(parent) {
+                self.errors.push(WalkedError::from_io(
+                    &err,
+                    parent,
+                    PathKind::Dir,
+                    format!("Couldn't create directory '{}'", parent.display()),
+                ));
+                return false;
+            }
+            created.extend(missing.into_iter().map(|dir| (dir, PathKind::Dir)));
+        }
+        let path = new_path(path, config);
+        let res = match path_kind {
+            PathKind::Dir => std::fs::create_dir(&path),
+            _ => std::fs::File::create_new(&path).map(|_| ()),
+        };
+        let ok = match res {
+            Ok(()) => {
+                created.push((path, path_kind));
+                true
+            }
+            Err(err) => {
+                self.errors.push(WalkedError::from_io(
+                    &err,
+                    &path,
+                    path_kind,
+                    format!(
+                        "Couldn't create {} '{}'",
+                        if path_kind == PathKind::Dir {
+                            "directory"
+                        } else {
+                            "file"
+                        },
+                        path.display()
+                    ),
+                ));
+                false
+            }
+        };
+        let Some((first, _)) = created.first() else {
+            return false;
+        };
+        // the entry in the working directory the created path starts with
+        let listed = self.working_directory.join(
+            first
+                .strip_prefix(&self.working_directory)
+                .ok()
+                .and_then(|rel| rel.components().next())
+                .map(|c| c.as_os_str())
+                .unwrap_or_default(),
+        );
+        for (path, path_kind) in created {
+            undo_stack.push(Operation::Create { path, path_kind });
+        }
+        self.read_working_dir();
+        if let Some(i) = self.entries.iter().position(|entry| *entry == listed) {
+            self.table_state.select(Some(i));
+            self.cursor_offset = 0;
+            self.table_state.select_column(Some(1));
+        }
+        ok
+    }
+
+    pub fn refresh_cursor(&mut self) {
+        if let Some(i) = self.table_state.selected()
+            && i < self.entries.len()
+        {
+            let name = {
+                if let Some(l) = self.entries[i].file_name() {
+                    l.to_string_lossy().to_string()
+                } else {
+                    String::new()
+                }
+            };
+            self.current_entry_length = name.chars().count();
+            self.cursor_offset = self.cursor_offset.min(self.current_entry_length as u16)
+        }
+    }
+    pub fn walk(&mut self, current_entry: usize, config: &Config) -> bool {
         if self.entries.is_empty() {
             return false;
         }
-        let selected = &self.entries[current_entry];
+        let mut selected = self.entries[current_entry].clone();
+        if selected.is_symlink() {
+            if !config.follow_symlinks {
+                return false;
+            }
+            // a link to a directory containing it would otherwise make the path grow each time
+            // it's followed, so the real path is used instead
+            if let Ok(target) = selected.canonicalize()
+                && self
+                    .working_directory
+                    .canonicalize()
+                    .is_ok_and(|dir| dir.starts_with(&target))
+            {
+                selected = target;
+            }
+        }
         if selected.is_dir() {
-            self.working_directory = selected.clone();
+            self.save_cursor_position();
+            self.working_directory = selected;
+            self.name_scroll = 0;
+            self.push_history();
+            self.reset_sort(config);
             self.read_working_dir();
             return true;
         }
         false
     }
-    pub fn parent(&mut self) -> bool {
-        if let Some(p) = self.working_directory.parent() {
-            self.working_directory = p.to_path_buf();
+    pub fn change_directory(&mut self, dir: PathBuf, config: &Config) {
+        self.save_cursor_position();
+        self.working_directory = dir;
+        self.name_scroll = 0;
+        self.push_history();
+        self.clear_selection();
+        self.reset_sort(config);
+        self.read_working_dir();
+        self.table_state.select_first();
+        self.refresh_cursor();
+    }
+    pub fn parent(&mut self, config: &Config) -> bool {
+        if let Some(p) = self.working_directory.parent().map(Path::to_path_buf) {
+            self.save_cursor_position();
+            self.working_directory = p;
+            self.name_scroll = 0;
+            self.push_history();
+            self.reset_sort(config);
             self.read_working_dir();
             return true;
         }
         false
     }
+
+    /// Goes to the directory visited before the working directory, returns false if there is none.
+    pub fn back(&mut self, config: &Config) -> bool {
+        if self.history_index == 0 {
+            return false;
+        }
+        self.go_to_history(self.history_index - 1, config)
+    }
+
+    /// Goes to the directory visited after the working directory, returns false if there is none.
+    pub fn forward(&mut self, config: &Config) -> bool {
+        if self.history_index + 1 >= self.history.len() {
+            return false;
+        }
+        self.go_to_history(self.history_index + 1, config)
+    }
+
+    fn go_to_history(&mut self, index: usize, config: &Config) -> bool {
+        let dir = self.history[index].clone();
+        if !dir.is_dir() {
+            self.errors.push(WalkedError::PathNotFound {
+                path: dir,
+                path_kind: PathKind::Dir,
+            });
+            return false;
+        }
+        self.save_cursor_position();
+        self.history_index = index;
+        self.working_directory = dir;
+        self.name_scroll = 0;
+        self.clear_selection();
+        self.reset_sort(config);
+        self.read_working_dir();
+        let position = self
+            .cursor_positions
+            .get(&self.working_directory)
+            .copied()
+            .unwrap_or(0);
+        self.table_state
+            .select(clamp_selection(self.entries.len(), position));
+        self.refresh_cursor();
+        true
+    }
+
+    /// Records the working directory as the newest visited one, forgetting the directories that
+    /// were gone back from, like a browser does.
+    fn push_history(&mut self) {
+        self.history.truncate(self.history_index + 1);
+        if self.history.last() != Some(&self.working_directory) {
+            self.history.push(self.working_directory.clone());
+        }
+        self.history_index = self.history.len() - 1;
+    }
+
+    fn save_cursor_position(&mut self) {
+        if let Some(i) = self.table_state.selected() {
+            self.cursor_positions
+                .insert(self.working_directory.clone(), i);
+        }
+    }
+
+    /// Goes back to the sort mode configured for the working directory, forgetting the one
+    /// chosen at runtime.
+    fn reset_sort(&mut self, config: &Config) {
+        (self.sort, self.sort_reverse) = config.sort_for(&self.working_directory);
+        // a filter typed for one directory rarely makes sense in another
+        self.filter.clear();
+    }
+
+    /// Reads the working directory again. Only the first [`READ_BATCH`] entries are read right
+    /// away, the rest of a huge directory arrives through [`Panel::receive_entries`].
     pub fn read_working_dir(&mut self) {
-        if let Ok(dir) = std::fs::read_dir(&self.working_directory) {
-            self.entries.clear();
-            for d in dir {
-                if let Ok(d) = d {
-                    let p = d.path();
-                    self.entries.push(p);
-                }
+        // a read that is still going on would mix in stale entries
+        self.reader = None;
+        if let Ok(mut dir) = std::fs::read_dir(&self.working_directory) {
+            let entries = dir
+                .by_ref()
+                .flatten()
+                .take(READ_BATCH)
+                .map(|d| read_entry(d.path()))
+                .collect::<Vec<_>>();
+            if entries.len() == READ_BATCH {
+                self.reader = Some(DirReader::start(dir));
             }
-            // TODO: `ls` is not case-sensitive while the `Sort` implementation for `PathBuf` IS case-sensitive
-            self.entries.sort_unstable();
+            self.set_entries(entries);
+            self.git_status = if self.show_git_status {
+                git::status(&self.working_directory)
+            } else {
+                None
+            };
             self.header_width = TABLE_HEADER_MIN_WIDTH;
+            if let Some(i) = self.table_state.selected() {
+                self.table_state
+                    .select(clamp_selection(self.entries.len(), i));
+            }
+        }
+    }
+
+    /// Adds the entries a background read came up with since the last call, the cursor stays on
+    /// the entry it was on. Returns whether there were any.
+    pub fn receive_entries(&mut self) -> bool {
+        let Some(reader) = &self.reader else {
+            return false;
+        };
+        let (new_entries, done) = reader.receive();
+        if done {
+            self.reader = None;
+        }
+        if new_entries.is_empty() {
+            return done;
+        }
+        let selected = self
+            .table_state
+            .selected()
+            .and_then(|i| self.entries.get(i))
+            .cloned();
+        let mut entries = std::mem::take(&mut self.entries)
+            .into_iter()
+            .zip(std::mem::take(&mut self.metadata))
+            .zip(std::mem::take(&mut self.link_targets))
+            .map(|((path, metadata), link_target)| (path, metadata, link_target))
+            .collect::<Vec<_>>();
+        entries.extend(new_entries);
+        self.set_entries(entries);
+        if let Some(i) = selected.and_then(|s| self.entries.iter().position(|e| *e == s)) {
+            self.table_state.select(Some(i));
+        }
+        true
+    }
+
+    /// Sorts and filters `entries` before they replace the listed ones.
+    fn set_entries(&mut self, mut entries: Vec<Entry>) {
+        let selected = self
+            .selection
+            .iter()
+            .filter_map(|&i| self.entries.get(i).cloned())
+            .collect::<HashSet<_>>();
+        entries.sort_unstable_by(|a, b| {
+            compare_names(&a.0, &b.0, self.natural_sort, self.case_insensitive_sort)
+        });
+        sort_entries(&mut entries, self.sort, self.sort_reverse);
+        if self.directories_first {
+            // the sort is stable, so both groups stay in the order chosen above
+            entries.sort_by_key(|(_, m, _)| !m.as_ref().is_some_and(|m| m.is_dir()));
+        }
+        if !self.filter.is_empty() {
+            entries.retain(|(p, _, _)| {
+                p.file_name()
+                    .is_some_and(|n| fuzzy_match(&self.filter, &n.to_string_lossy()).is_some())
+            });
+        }
+        self.entries.clear();
+        self.metadata.clear();
+        self.link_targets.clear();
+        for (path, metadata, link_target) in entries {
+            self.entries.push(path);
+            self.metadata.push(metadata);
+            self.link_targets.push(link_target);
         }
+        self.selection = (0..self.entries.len())
+            .filter(|&i| selected.contains(&self.entries[i]))
+            .collect();
     }
 }
 
-fn new_path<T: AsRef<std::path::Path>>(p: T) -> PathBuf {
-    let mut res = PathBuf::from(p.as_ref());
-    let mut res_string = res.to_str().unwrap().to_string();
-    while res.exists() {
-        res_string += ".1";
-        res = PathBuf::from_str(&res_string).unwrap()
+/// The Unix permission bits of an entry, `None` on other platforms.
+#[cfg(unix)]
+pub fn mode(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.mode())
+}
+
+#[cfg(not(unix))]
+pub fn mode(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Formats permission bits like `ls -l` does, `rwxr-xr-x` for example.
+pub fn permissions_string(mode: u32) -> String {
+    let mut res = String::with_capacity(9);
+    for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = mode >> shift;
+        res.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        res.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        res.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
     }
     res
 }
 
-/// `dest` folder should already exist.
-fn copy_recursively(src: &PathBuf, dest: &PathBuf, errors: &mut Vec<WalkedError>) {
-    if let Ok(dir) = std::fs::read_dir(src) {
-        for d in dir {
-            if let Ok(d) = d {
-                let p = d.path();
-                if p.is_file() {
-                    let file = p.file_name().unwrap();
-                    let new_file = dest.join(file);
-                    if let Err(err) = std::fs::copy(&p, &new_file) {
-                        match err.kind() {
-                            std::io::ErrorKind::NotFound => {
-                                errors.push(WalkedError::PathNotFound {
-                                    path: p,
-                                    path_kind: PathKind::File,
-                                })
-                            }
-                            std::io::ErrorKind::PermissionDenied => {
-                                errors.push(WalkedError::PermissionDenied {
-                                    path: new_file,
-                                    path_kind: PathKind::File,
-                                })
-                            }
-                            _ => errors.push(WalkedError::Message(format!(
-                                "Couldn't copy file from '{}' to '{}'",
-                                p.display(),
-                                new_file.display()
-                            ))),
-                        }
+/// Returns the range of entries covered by a selection anchored at `start`, with the cursor at
+/// `cur`. Without an anchor, only the entry under the cursor is covered.
+pub fn selection_range(start: Option<usize>, cur: usize) -> RangeInclusive<usize> {
+    match start {
+        Some(start) => cur.min(start)..=cur.max(start),
+        None => cur..=cur,
+    }
+}
+
+/// Resolves `.` and `..` components without touching the filesystem, so symlinks in `path` are
+/// kept as they are.
+pub fn normalize(path: &Path) -> PathBuf {
+    use std::path::Component;
+
+    let mut res = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                res.pop();
+            }
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// Returns the positions (in chars) of the characters of `name` that match the characters of
+/// `pattern` in order, ignoring case. `None` if `pattern` doesn't match.
+pub fn fuzzy_match(pattern: &str, name: &str) -> Option<Vec<usize>> {
+    let mut pattern = pattern.chars().flat_map(char::to_lowercase).peekable();
+    let mut positions = Vec::new();
+    for (i, c) in name.chars().enumerate() {
+        let Some(&p) = pattern.peek() else {
+            break;
+        };
+        if c.to_lowercase().next() == Some(p) {
+            positions.push(i);
+            pattern.next();
+        }
+    }
+    pattern.peek().is_none().then_some(positions)
+}
+
+/// Clamps `idx` so that it points at one of `len` entries, `None` if there are no entries.
+pub fn clamp_selection(len: usize, idx: usize) -> Option<usize> {
+    if len == 0 {
+        None
+    } else {
+        Some(idx.min(len - 1))
+    }
+}
+
+/// The order of entries when sorting by name. With `case_insensitive` set, names that only differ
+/// in case are still ordered by it so the order doesn't depend on how the directory was read.
+pub fn compare_names(a: &Path, b: &Path, natural: bool, case_insensitive: bool) -> Ordering {
+    let cmp = |a: &Path, b: &Path| {
+        if natural { natural_cmp(a, b) } else { a.cmp(b) }
+    };
+    if case_insensitive {
+        let lowercase = |p: &Path| PathBuf::from(p.to_string_lossy().to_lowercase());
+        cmp(&lowercase(a), &lowercase(b)).then_with(|| cmp(a, b))
+    } else {
+        cmp(a, b)
+    }
+}
+
+/// Compares paths like `cmp` does, except that runs of digits are compared by their value, so
+/// `a2` comes before `a10`. Paths that only differ in leading zeros fall back to `cmp`.
+pub fn natural_cmp(a: &Path, b: &Path) -> Ordering {
+    let (a_str, b_str) = (a.to_string_lossy(), b.to_string_lossy());
+    let (mut a_chars, mut b_chars) = (a_str.chars().peekable(), b_str.chars().peekable());
+    loop {
+        let ord = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = digit_run(&mut a_chars);
+                let y = digit_run(&mut b_chars);
+                let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+            }
+            (Some(x), Some(y)) => {
+                let ord = x.cmp(y);
+                a_chars.next();
+                b_chars.next();
+                ord
+            }
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+}
+
+fn digit_run(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut run = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        run.push(c);
+    }
+    run
+}
+
+/// Sorts `entries` by `sort`, entries that compare equal keep their current (name) order.
+fn sort_entries(entries: &mut [Entry], sort: SortMode, reverse: bool) {
+    match sort {
+        SortMode::Name => {
+            if reverse {
+                entries.reverse();
+            }
+        }
+        SortMode::Size => sort_entries_by_key(entries, reverse, |_, m| {
+            m.filter(|m| m.is_file()).map(|m| m.len()).unwrap_or(0)
+        }),
+        SortMode::Modified => sort_entries_by_key(entries, reverse, |_, m| {
+            m.and_then(|m| m.modified().ok())
+                .unwrap_or(std::time::UNIX_EPOCH)
+        }),
+        SortMode::Extension => sort_entries_by_key(entries, reverse, |p, _| {
+            p.extension().map(|e| e.to_string_lossy().to_lowercase())
+        }),
+    }
+}
+
+/// Only the keys are reversed when `reverse` is set, so entries with equal keys stay in name order.
+fn sort_entries_by_key<K: Ord>(
+    entries: &mut [Entry],
+    reverse: bool,
+    key: impl Fn(&Path, Option<&Metadata>) -> K,
+) {
+    if reverse {
+        entries.sort_by_cached_key(|(p, m, _)| std::cmp::Reverse(key(p, m.as_ref())));
+    } else {
+        entries.sort_by_cached_key(|(p, m, _)| key(p, m.as_ref()));
+    }
+}
+
+/// Reports that a batch operation stopped because of an error before getting to `skipped`.
+fn batch_stopped(skipped: &[PathBuf]) -> WalkedError {
+    let names = skipped
+        .iter()
+        .map(|p| {
+            p.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| p.display().to_string())
+        })
+        .collect::<Vec<_>>();
+    WalkedError::Message(format!(
+        "Stopped after an error, {} {} processed: {}",
+        names.len(),
+        if names.len() == 1 {
+            "entry wasn't"
+        } else {
+            "entries weren't"
+        },
+        names.join(", ")
+    ))
+}
+
+/// Quotes `path` so that the shell passes it on as a single argument.
+fn shell_quote(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(windows) {
+        format!("\"{path}\"")
+    } else {
+        format!("'{}'", path.replace('\'', "'\\''"))
+    }
+}
+
+/// Builds the process for `command` (a program followed by optional arguments). `%s` in an
+/// argument is replaced with `path`, which is added as the last argument if there's no `%s`. An
+/// argument written as `""` is passed as an empty argument.
+fn command_for(command: &str, path: &Path) -> Result<std::process::Command, WalkedError> {
+    let mut parts = command.split_whitespace();
+    let Some(program) = parts.next() else {
+        return Err(WalkedError::Message("No command configured".to_string()));
+    };
+    let mut res = std::process::Command::new(program);
+    let mut has_placeholder = false;
+    for arg in parts {
+        if arg == "%s" {
+            // passed as it is, the path doesn't have to be valid UTF-8
+            res.arg(path);
+            has_placeholder = true;
+        } else if arg.contains("%s") {
+            res.arg(arg.replace("%s", &path.to_string_lossy()));
+            has_placeholder = true;
+        } else {
+            res.arg(if arg == "\"\"" { "" } else { arg });
+        }
+    }
+    if !has_placeholder {
+        res.arg(path);
+    }
+    Ok(res)
+}
+
+fn run_error(command: &std::process::Command, err: std::io::Error) -> WalkedError {
+    let program = command.get_program().to_string_lossy();
+    match err.kind() {
+        std::io::ErrorKind::NotFound => {
+            WalkedError::Message(format!("Couldn't find '{program}', is it installed?"))
+        }
+        _ => WalkedError::Message(format!("Couldn't run '{program}': {err}")),
+    }
+}
+
+/// Runs `command` on `path` (see [`command_for`]) without waiting for it to finish.
+pub fn spawn_detached(command: &str, path: &Path) -> Result<(), WalkedError> {
+    let mut command = command_for(command, path)?;
+    command
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map(|mut child| {
+            // reap the child once it exits so it doesn't linger as a zombie
+            std::thread::spawn(move || child.wait());
+        })
+        .map_err(|err| run_error(&command, err))
+}
+
+/// Runs `command` on `path` (see [`command_for`]) in the terminal and waits for it to exit.
+pub fn run_in_terminal(
+    command: &str,
+    path: &Path,
+) -> Result<std::process::ExitStatus, WalkedError> {
+    let mut command = command_for(command, path)?;
+    command.status().map_err(|err| run_error(&command, err))
+}
+
+/// Takes an entry that was trashed to make room for a paste out of the trash again, after the
+/// paste failed.
+fn put_back(entry: &TrashedEntry, errors: &mut Vec<WalkedError>) {
+    if let Err(err) = entry.restore() {
+        errors.push(err);
+        errors.push(WalkedError::Message(format!(
+            "'{}' was replaced and is still in the trash",
+            entry.original.display()
+        )));
+    }
+}
+
+/// Decides where `src` should be pasted when its desired destination is `dest`.
+/// Returns `None` if it shouldn't be pasted at all.
+fn resolve_conflict(
+    src: &Path,
+    dest: PathBuf,
+    config: &Config,
+    errors: &mut Vec<WalkedError>,
+) -> Option<PathBuf> {
+    if dest.exists() && dest.is_dir() != src.is_dir() {
+        match config.type_conflict {
+            TypeConflictPolicy::Rename => (),
+            TypeConflictPolicy::Refuse => {
+                errors.push(WalkedError::Message(format!(
+                    "Can't paste {} '{}' onto {} '{}'",
+                    if src.is_dir() { "directory" } else { "file" },
+                    src.display(),
+                    if dest.is_dir() { "directory" } else { "file" },
+                    dest.display()
+                )));
+                return None;
+            }
+        }
+    }
+    Some(new_path(dest, config))
+}
+
+/// `path` relative to `base`, going up with ".." where they differ. Both should be absolute.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        // different roots (or drives on Windows) have nothing in common
+        return path.to_path_buf();
+    }
+    let mut res = PathBuf::new();
+    for _ in base.components().skip(common) {
+        res.push("..");
+    }
+    res.extend(path.components().skip(common));
+    res
+}
+
+/// Checks that `name` can be given to an entry: it isn't empty, `.` or `..`, has none of the
+/// disallowed characters and isn't reserved by the platform.
+pub fn check_name(name: &str, config: &Config) -> Result<(), WalkedError> {
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(WalkedError::Message(format!("'{name}' isn't a valid name")));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|&c| std::path::is_separator(c) || config.disallowed_characters.contains(c))
+    {
+        return Err(WalkedError::Message(format!(
+            "'{name}' isn't a valid name, names can't contain {c:?}"
+        )));
+    }
+    if is_reserved_name(name) {
+        return Err(WalkedError::Message(format!(
+            "'{name}' is reserved by the system"
+        )));
+    }
+    Ok(())
+}
+
+/// Device names like `CON` or `COM1` can't be used as names on Windows, not even with an
+/// extension.
+#[cfg(windows)]
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    let stem = stem.to_ascii_uppercase();
+    matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || (stem.len() == 4
+            && (stem.starts_with("COM") || stem.starts_with("LPT"))
+            && matches!(stem.as_bytes()[3], b'1'..=b'9'))
+}
+
+#[cfg(not(windows))]
+fn is_reserved_name(_name: &str) -> bool {
+    false
+}
+
+/// Writes the names of `entries` to a temporary file, one per line, for them to be edited.
+fn write_names(entries: &[PathBuf]) -> Result<PathBuf, WalkedError> {
+    let mut names = String::new();
+    for entry in entries {
+        let name = entry.file_name().unwrap_or(entry.as_os_str());
+        let Some(name) = name.to_str() else {
+            return Err(WalkedError::Message(format!(
+                "'{}' isn't valid UTF-8, so it can't be renamed in an editor",
+                entry.display()
+            )));
+        };
+        names.push_str(name);
+        names.push('\n');
+    }
+    let file =
+        temporary_path(std::env::temp_dir().join(format!("walked-rename-{}", std::process::id())));
+    std::fs::write(&file, names).map_err(|err| {
+        WalkedError::from_io(
+            &err,
+            &file,
+            PathKind::File,
+            format!("Couldn't write '{}'", file.display()),
+        )
+    })?;
+    Ok(file)
+}
+
+/// Splits a numbering template like "img_### 10" into the template and the number to start from,
+/// which is 1 unless given after the template. Returns `None` without a '#' in the template.
+fn parse_number_template(arg: &str) -> Option<(&str, usize)> {
+    let arg = arg.trim();
+    let (template, start) = match arg.rsplit_once(' ') {
+        Some((template, start)) if template.contains('#') && start.parse::<usize>().is_ok() => {
+            (template.trim_end(), start.parse().unwrap_or(1))
+        }
+        _ => (arg, 1),
+    };
+    template.contains('#').then_some((template, start))
+}
+
+/// Numbers `entries` in order by putting the numbers in place of the first run of '#' in
+/// `template`, keeping the extension of each file. The numbers are padded with zeros to the length
+/// of that run, or to the length of the biggest number if it's longer.
+fn number_names(entries: &[PathBuf], template: &str, start: usize) -> Vec<String> {
+    let run_start = template.find('#').unwrap_or(template.len());
+    let run_len = template[run_start..]
+        .chars()
+        .take_while(|c| *c == '#')
+        .count();
+    let (prefix, suffix) = (&template[..run_start], &template[run_start + run_len..]);
+    let last = start + entries.len().saturating_sub(1);
+    let width = run_len.max(last.to_string().len());
+    entries
+        .iter()
+        .enumerate()
+        .map(|(n, entry)| {
+            let mut name = format!("{prefix}{:0width$}{suffix}", start + n);
+            if !entry.is_dir()
+                && let Some(file_name) = entry.file_name()
+            {
+                name.push_str(extension(&file_name.to_string_lossy()));
+            }
+            name
+        })
+        .collect()
+}
+
+/// Renames each `(from, to)` pair, the targets may be the names of other entries that are renamed
+/// too (swapping two names, for example) since those are moved out of the way first. Returns the
+/// pairs that were renamed.
+pub fn rename_entries(
+    renames: &[(PathBuf, PathBuf)],
+    errors: &mut Vec<WalkedError>,
+) -> Vec<(PathBuf, PathBuf)> {
+    let sources = renames.iter().map(|(from, _)| from).collect::<HashSet<_>>();
+    let mut staged = Vec::new();
+    for (from, to) in renames {
+        if !sources.contains(to) {
+            staged.push((from.clone(), from.clone(), to.clone()));
+            continue;
+        }
+        let mut temporary = from.clone().into_os_string();
+        temporary.push(".walked-rename");
+        let temporary = temporary_path(PathBuf::from(temporary));
+        if let Err(err) = std::fs::rename(from, &temporary) {
+            errors.push(WalkedError::from_io(
+                &err,
+                from,
+                PathKind::Ambigious,
+                format!("Couldn't rename '{}' to '{}'", from.display(), to.display()),
+            ));
+        } else {
+            staged.push((from.clone(), temporary, to.clone()));
+        }
+    }
+
+    let mut renamed = Vec::new();
+    for (from, current, to) in staged {
+        let res = if to.symlink_metadata().is_ok() {
+            Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists))
+        } else {
+            std::fs::rename(&current, &to)
+        };
+        match res {
+            Ok(()) => renamed.push((from, to)),
+            Err(err) => {
+                errors.push(WalkedError::from_io(
+                    &err,
+                    &from,
+                    PathKind::Ambigious,
+                    format!("Couldn't rename '{}' to '{}'", from.display(), to.display()),
+                ));
+                if current != from {
+                    // the entry was moved out of the way, so it gets its old name back if it can
+                    let _ = std::fs::rename(&current, &from);
+                }
+            }
+        }
+    }
+    renamed
+}
+
+/// `p` if nothing is there yet, otherwise `p` with `collision_suffix` added and the lowest number
+/// that makes it free in place of `#`.
+fn new_path<T: AsRef<std::path::Path>>(p: T, config: &Config) -> PathBuf {
+    new_path_where(
+        p.as_ref(),
+        &config.collision_suffix,
+        config.collision_before_extension,
+        |path| path.symlink_metadata().is_ok(),
+    )
+}
+
+/// A free path for a file walked only needs for a while, next to `p`.
+fn temporary_path(p: PathBuf) -> PathBuf {
+    new_path_where(&p, DEFAULT_COLLISION_SUFFIX, false, |path| {
+        path.symlink_metadata().is_ok()
+    })
+}
+
+/// Like [`new_path`], with `taken` deciding which paths can't be used. With `before_extension`,
+/// the suffix goes in front of the extension of files. A name that already ends in a numbered
+/// suffix counts up from its number, so `foo.1` becomes `foo.2` rather than `foo.1.1`.
+fn new_path_where(
+    p: &Path,
+    suffix: &str,
+    before_extension: bool,
+    taken: impl Fn(&Path) -> bool,
+) -> PathBuf {
+    if !taken(p) {
+        return p.to_path_buf();
+    }
+    let (before, after) = suffix.split_once('#').unwrap_or((suffix, ""));
+    let Some(name) = p.file_name().and_then(|name| name.to_str()) else {
+        // names that aren't valid UTF-8 just get the suffix added
+        return (1..)
+            .map(|n| {
+                let mut path = p.as_os_str().to_os_string();
+                path.push(format!("{before}{n}{after}"));
+                PathBuf::from(path)
+            })
+            .find(|path| !taken(path))
+            .expect("there's always a free number");
+    };
+    let numbered = |stem| split_number(stem, before, after);
+    // `foo.1` counts up as a whole, its number isn't taken for an extension
+    let (base, first, extension) = match numbered(name) {
+        Some((base, number)) => (base, number + 1, ""),
+        None => {
+            let extension_start = name
+                .len()
+                .checked_sub(extension(name).len())
+                .filter(|_| before_extension && !p.is_dir())
+                .unwrap_or(name.len());
+            let (stem, extension) = name.split_at(extension_start);
+            match numbered(stem) {
+                Some((base, number)) => (base, number + 1, extension),
+                None => (stem, 1, extension),
+            }
+        }
+    };
+    (first..)
+        .map(|n| p.with_file_name(format!("{base}{before}{n}{after}{extension}")))
+        .find(|path| !taken(path))
+        .expect("there's always a free number")
+}
+
+/// Splits `name` into what comes before a suffix like `.1` (`before`, a number, `after`) and the
+/// number, if it ends in one.
+fn split_number<'a>(name: &'a str, before: &str, after: &str) -> Option<(&'a str, u64)> {
+    let rest = name.strip_suffix(after)?;
+    let digits = rest.len() - rest.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (rest, number) = rest.split_at(rest.len() - digits);
+    Some((rest.strip_suffix(before)?, number.parse().ok()?))
+}
+
+/// The extension of `name` with its dot, including `.tar` in front of compressed tarballs like
+/// `archive.tar.gz`. Dotfiles like `.bashrc` don't have one.
+pub fn extension(name: &str) -> &str {
+    let Some(dot) = name
+        .rfind('.')
+        .filter(|&dot| dot > 0 && dot + 1 < name.len())
+    else {
+        return "";
+    };
+    match name[..dot].len().checked_sub(4) {
+        Some(tar)
+            if tar > 0
+                && name
+                    .get(tar..dot)
+                    .is_some_and(|s| s.eq_ignore_ascii_case(".tar")) =>
+        {
+            &name[tar..]
+        }
+        _ => &name[dot..],
+    }
+}
+
+/// Creates a symlink at `link` pointing to `target`, returns true if it was created.
+pub fn create_symlink(target: &Path, link: &Path, errors: &mut Vec<WalkedError>) -> bool {
+    #[cfg(unix)]
+    let res = std::os::unix::fs::symlink(target, link);
+    #[cfg(windows)]
+    let res = if target.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    };
+    if let Err(err) = res {
+        errors.push(WalkedError::from_io(
+            &err,
+            link,
+            PathKind::Ambigious,
+            format!(
+                "Couldn't create a symlink to '{}' at '{}'",
+                target.display(),
+                link.display()
+            ),
+        ));
+        return false;
+    }
+    true
+}
+
+/// How much of an entry [`copy_entry`] managed to copy.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Copied {
+    Nothing,
+    /// `dest` was created, but some of the entries inside it couldn't be copied.
+    Partly,
+    Fully,
+}
+
+impl Copied {
+    /// True if `dest` was created, even if not everything could be copied into it.
+    pub fn created(self) -> bool {
+        self != Copied::Nothing
+    }
+}
+
+/// Copies the file or directory at `src` to `dest`, which shouldn't exist yet. Files are
+/// reflinked when `reflink` is set, if that isn't supported they're copied normally.
+pub fn copy_entry(src: &Path, dest: &Path, errors: &mut Vec<WalkedError>, reflink: bool) -> Copied {
+    copy_entry_with(src, dest, errors, &mut |src, dest| {
+        if reflink {
+            reflink_copy::reflink_or_copy(src, dest).map(|_| ())
+        } else {
+            std::fs::copy(src, dest).map(|_| ())
+        }
+    })
+}
+
+/// Like `copy_entry`, but files are copied with `copy_file`.
+pub fn copy_entry_with(
+    src: &Path,
+    dest: &Path,
+    errors: &mut Vec<WalkedError>,
+    copy_file: &mut dyn FnMut(&Path, &Path) -> std::io::Result<()>,
+) -> Copied {
+    if src.is_file() {
+        if let Err(err) = copy_file(src, dest) {
+            match err.kind() {
+                std::io::ErrorKind::NotFound => errors.push(WalkedError::PathNotFound {
+                    path: src.to_path_buf(),
+                    path_kind: PathKind::File,
+                }),
+                std::io::ErrorKind::PermissionDenied => {
+                    errors.push(WalkedError::PermissionDenied {
+                        path: dest.to_path_buf(),
+                        path_kind: PathKind::File,
+                    })
+                }
+                _ => errors.push(WalkedError::Message(format!(
+                    "Couldn't copy file from '{}' to '{}'",
+                    src.display(),
+                    dest.display()
+                ))),
+            }
+            return Copied::Nothing;
+        }
+        Copied::Fully
+    } else if src.is_dir() {
+        if let Err(err) = std::fs::create_dir(dest) {
+            errors.push(WalkedError::from_io(
+                &err,
+                dest,
+                PathKind::Dir,
+                format!("Couldn't create directory '{}'", dest.display()),
+            ));
+            return Copied::Nothing;
+        }
+        if copy_recursively(src, dest, errors, copy_file) {
+            Copied::Fully
+        } else {
+            Copied::Partly
+        }
+    } else {
+        errors.push(not_copyable(src));
+        Copied::Nothing
+    }
+}
+
+/// The error for entries that are neither files nor directories, like broken symlinks, FIFOs and
+/// sockets.
+fn not_copyable(p: &Path) -> WalkedError {
+    WalkedError::Message(format!(
+        "Couldn't copy '{}', only files and directories can be copied",
+        p.display()
+    ))
+}
+
+/// Moves the file or directory at `src` to `dest`, falling back to copying and removing the
+/// original when they are on different filesystems. Returns true if `dest` was created.
+pub fn move_entry(src: &Path, dest: &Path, errors: &mut Vec<WalkedError>, reflink: bool) -> bool {
+    match std::fs::rename(src, dest) {
+        Ok(()) => true,
+        Err(err) if err.kind() == std::io::ErrorKind::CrossesDevices => {
+            move_by_copying(src, dest, errors, reflink)
+        }
+        Err(err) => {
+            errors.push(WalkedError::from_io(
+                &err,
+                src,
+                PathKind::Ambigious,
+                format!("Couldn't move '{}' to '{}'", src.display(), dest.display()),
+            ));
+            false
+        }
+    }
+}
+
+/// Copies `src` to `dest` and removes `src`. The original is only removed once everything in it
+/// was copied, otherwise the incomplete copy is removed instead. Returns true if `dest` was
+/// created.
+fn move_by_copying(src: &Path, dest: &Path, errors: &mut Vec<WalkedError>, reflink: bool) -> bool {
+    match copy_entry(src, dest, errors, reflink) {
+        Copied::Nothing => return false,
+        Copied::Partly => {
+            let _ = std::fs::remove_dir_all(dest);
+            errors.push(WalkedError::Message(format!(
+                "Couldn't move '{}' to '{}', it was left where it was",
+                src.display(),
+                dest.display()
+            )));
+            return false;
+        }
+        Copied::Fully => (),
+    }
+    let res = if src.is_dir() {
+        std::fs::remove_dir_all(src)
+    } else {
+        std::fs::remove_file(src)
+    };
+    if let Err(err) = res {
+        errors.push(WalkedError::from_io(
+            &err,
+            src,
+            PathKind::Ambigious,
+            format!("Couldn't remove '{}' after moving it", src.display()),
+        ));
+    }
+    true
+}
+
+/// `dest` folder should already exist. Returns true if everything in `src` was copied.
+fn copy_recursively(
+    src: &Path,
+    dest: &Path,
+    errors: &mut Vec<WalkedError>,
+    copy_file: &mut dyn FnMut(&Path, &Path) -> std::io::Result<()>,
+) -> bool {
+    let dir = match std::fs::read_dir(src) {
+        Ok(dir) => dir,
+        Err(err) => {
+            errors.push(WalkedError::from_io(
+                &err,
+                src,
+                PathKind::Dir,
+                format!("Couldn't read directory '{}'", src.display()),
+            ));
+            return false;
+        }
+    };
+    let mut complete = true;
+    for d in dir {
+        let Ok(d) = d else {
+            errors.push(WalkedError::Message(format!(
+                "Couldn't read an entry of '{}'",
+                src.display()
+            )));
+            complete = false;
+            continue;
+        };
+        let p = d.path();
+        if p.is_file() {
+            let file = p.file_name().unwrap();
+            let new_file = dest.join(file);
+            if let Err(err) = copy_file(&p, &new_file) {
+                match err.kind() {
+                    std::io::ErrorKind::NotFound => errors.push(WalkedError::PathNotFound {
+                        path: p,
+                        path_kind: PathKind::File,
+                    }),
+                    std::io::ErrorKind::PermissionDenied => {
+                        errors.push(WalkedError::PermissionDenied {
+                            path: new_file,
+                            path_kind: PathKind::File,
+                        })
                     }
-                } else if p.is_dir() {
-                    let dir = p.file_name().unwrap();
-                    let new_dir = dest.join(dir);
-                    if let Err(err) = std::fs::create_dir(&new_dir) {
-                        match err.kind() {
-                            std::io::ErrorKind::NotFound => {
-                                errors.push(WalkedError::PathNotFound {
-                                    path: new_dir,
-                                    path_kind: PathKind::Dir,
-                                })
-                            }
-                            std::io::ErrorKind::PermissionDenied => {
-                                errors.push(WalkedError::PermissionDenied {
-                                    path: new_dir,
-                                    path_kind: PathKind::Dir,
-                                })
-                            }
-                            _ => errors.push(WalkedError::Message(format!(
-                                "Couldn't create directory '{}'",
-                                new_dir.display()
-                            ))),
-                        }
-                    } else {
-                        copy_recursively(&p, &new_dir, errors);
+                    _ => errors.push(WalkedError::Message(format!(
+                        "Couldn't copy file from '{}' to '{}'",
+                        p.display(),
+                        new_file.display()
+                    ))),
+                }
+                complete = false;
+            }
+        } else if p.is_dir() {
+            let dir = p.file_name().unwrap();
+            let new_dir = dest.join(dir);
+            if let Err(err) = std::fs::create_dir(&new_dir) {
+                match err.kind() {
+                    std::io::ErrorKind::NotFound => errors.push(WalkedError::PathNotFound {
+                        path: new_dir,
+                        path_kind: PathKind::Dir,
+                    }),
+                    std::io::ErrorKind::PermissionDenied => {
+                        errors.push(WalkedError::PermissionDenied {
+                            path: new_dir,
+                            path_kind: PathKind::Dir,
+                        })
                     }
+                    _ => errors.push(WalkedError::Message(format!(
+                        "Couldn't create directory '{}'",
+                        new_dir.display()
+                    ))),
                 }
+                complete = false;
+            } else if !copy_recursively(&p, &new_dir, errors, copy_file) {
+                complete = false;
             }
+        } else {
+            errors.push(not_copyable(&p));
+            complete = false;
         }
     }
+    complete
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_range_covers_both_ends() {
+        assert_eq!(selection_range(None, 3), 3..=3);
+        assert_eq!(selection_range(Some(1), 3), 1..=3);
+        assert_eq!(selection_range(Some(5), 3), 3..=5);
+        assert_eq!(selection_range(Some(3), 3), 3..=3);
+    }
+
+    #[test]
+    fn clamp_selection_stays_in_bounds() {
+        assert_eq!(clamp_selection(0, 0), None);
+        assert_eq!(clamp_selection(0, 4), None);
+        assert_eq!(clamp_selection(3, 1), Some(1));
+        assert_eq!(clamp_selection(3, 3), Some(2));
+        assert_eq!(clamp_selection(3, usize::MAX), Some(2));
+    }
+
+    fn sorted(names: &[&str], natural: bool, case_insensitive: bool) -> Vec<String> {
+        let mut paths = names.iter().map(PathBuf::from).collect::<Vec<_>>();
+        paths.sort_by(|a, b| compare_names(a, b, natural, case_insensitive));
+        paths
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn natural_order_compares_numbers_by_value() {
+        let names = [
+            "img10.png",
+            "img2.png",
+            "img1.png",
+            "img02.png",
+            "img",
+            "img1a.png",
+        ];
+        assert_eq!(
+            sorted(&names, true, false),
+            [
+                "img",
+                "img1.png",
+                "img1a.png",
+                "img02.png",
+                "img2.png",
+                "img10.png"
+            ]
+        );
+        assert_eq!(
+            sorted(&names, false, false),
+            [
+                "img",
+                "img02.png",
+                "img1.png",
+                "img10.png",
+                "img1a.png",
+                "img2.png"
+            ]
+        );
+    }
+
+    #[test]
+    fn natural_order_handles_huge_numbers() {
+        let names = [
+            "a99999999999999999999999",
+            "a100000000000000000000000",
+            "a1",
+        ];
+        assert_eq!(
+            sorted(&names, true, false),
+            [
+                "a1",
+                "a99999999999999999999999",
+                "a100000000000000000000000"
+            ]
+        );
+    }
+
+    #[test]
+    fn case_insensitive_order_ignores_case() {
+        let names = ["b", "B", "a", "C", "A"];
+        assert_eq!(sorted(&names, false, false), ["A", "B", "C", "a", "b"]);
+        // names that only differ in case still have a fixed order
+        assert_eq!(sorted(&names, false, true), ["A", "a", "B", "b", "C"]);
+        assert_eq!(
+            sorted(&["File10", "file9", "FILE1"], true, true),
+            ["FILE1", "file9", "File10"]
+        );
+    }
+
+    #[test]
+    fn stopped_batches_count_what_was_skipped() {
+        let message = |skipped: &[&str]| {
+            let skipped = skipped.iter().map(PathBuf::from).collect::<Vec<_>>();
+            batch_stopped(&skipped).to_string()
+        };
+        assert_eq!(
+            message(&["/tmp/a"]),
+            "Stopped after an error, 1 entry wasn't processed: a"
+        );
+        assert_eq!(
+            message(&["/tmp/a", "b"]),
+            "Stopped after an error, 2 entries weren't processed: a, b"
+        );
+    }
+
+    #[test]
+    fn number_templates_are_parsed() {
+        assert_eq!(parse_number_template("img_#"), Some(("img_#", 1)));
+        assert_eq!(parse_number_template(" img_### 7 "), Some(("img_###", 7)));
+        // a trailing number only counts as the start if the rest is a template
+        assert_eq!(parse_number_template("# 0"), Some(("#", 0)));
+        assert_eq!(parse_number_template("img 7"), None);
+        assert_eq!(parse_number_template("img_# x"), Some(("img_# x", 1)));
+        assert_eq!(parse_number_template(""), None);
+    }
+
+    #[test]
+    fn numbered_names_are_padded() {
+        let entries = (0..3)
+            .map(|n| PathBuf::from(format!("{n}")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            number_names(&entries, "img_###", 1),
+            ["img_001", "img_002", "img_003"]
+        );
+        assert_eq!(number_names(&entries, "#x", 9), ["09x", "10x", "11x"]);
+        let entries = vec![PathBuf::from("a"); 12];
+        assert_eq!(number_names(&entries, "#", 1)[0], "01");
+    }
+
+    #[test]
+    fn numbered_names_keep_the_extension_of_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let photos = dir.path().join("my.photos");
+        std::fs::create_dir(&photos).unwrap();
+        let entries = [
+            PathBuf::from("archive.tar.gz"),
+            PathBuf::from("photo.JPG"),
+            PathBuf::from(".bashrc"),
+            photos,
+        ];
+        assert_eq!(
+            number_names(&entries, "img_#", 1),
+            ["img_1.tar.gz", "img_2.JPG", "img_3", "img_4"]
+        );
+    }
+
+    /// A directory "src" with a file and a directory inside, in `dir`.
+    fn tree(dir: &Path) -> PathBuf {
+        let src = dir.join("src");
+        std::fs::create_dir_all(src.join("inner")).unwrap();
+        std::fs::write(src.join("file"), "file").unwrap();
+        std::fs::write(src.join("inner").join("file"), "inner").unwrap();
+        src
+    }
+
+    #[test]
+    fn moving_by_copying_removes_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src, dest) = (tree(dir.path()), dir.path().join("dest"));
+        let mut errors = Vec::new();
+        assert!(move_by_copying(&src, &dest, &mut errors, false));
+        assert!(errors.is_empty());
+        assert!(!src.exists());
+        assert_eq!(
+            std::fs::read_to_string(dest.join("inner").join("file")).unwrap(),
+            "inner"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn incomplete_copies_dont_remove_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src, dest) = (tree(dir.path()), dir.path().join("dest"));
+        std::os::unix::fs::symlink("missing", src.join("inner").join("broken")).unwrap();
+        let mut errors = Vec::new();
+        assert_eq!(
+            copy_entry(&src, &dir.path().join("copy"), &mut errors, false),
+            Copied::Partly
+        );
+        assert_eq!(errors.len(), 1);
+
+        let mut errors = Vec::new();
+        assert!(!move_by_copying(&src, &dest, &mut errors, false));
+        assert_eq!(errors.len(), 2);
+        assert!(!dest.exists());
+        assert!(src.join("inner").join("broken").symlink_metadata().is_ok());
+        assert_eq!(std::fs::read_to_string(src.join("file")).unwrap(), "file");
+    }
+
+    /// The name `new_path_where` picks for `name` when the names in `taken` are in use.
+    fn free_name(name: &str, taken: &[&str], suffix: &str, before_extension: bool) -> String {
+        let taken: Vec<_> = [name].iter().chain(taken).map(PathBuf::from).collect();
+        new_path_where(Path::new(name), suffix, before_extension, |p| {
+            taken.iter().any(|t| t == p)
+        })
+        .to_string_lossy()
+        .into_owned()
+    }
+
+    #[test]
+    fn collision_suffix_is_configurable() {
+        assert_eq!(free_name("foo.png", &[], " (#)", true), "foo (1).png");
+        assert_eq!(free_name("foo.png", &[], " (#)", false), "foo.png (1)");
+        assert_eq!(free_name("foo (1)", &[], " (#)", true), "foo (2)");
+        assert_eq!(
+            free_name("foo.png", &["foo (1).png"], " (#)", true),
+            "foo (2).png"
+        );
+        assert_eq!(free_name("foo", &[], "_#", true), "foo_1");
+        // a suffix without '#' gets the number after it
+        assert_eq!(free_name("foo", &[], "-copy", true), "foo-copy1");
+    }
+
+    #[test]
+    fn free_names_are_used_as_they_are() {
+        let free = new_path_where(Path::new("foo.png"), ".#", true, |_| false);
+        assert_eq!(free, Path::new("foo.png"));
+    }
+
+    #[test]
+    fn numbers_go_in_front_of_the_extension() {
+        assert_eq!(free_name("foo.png", &[], ".#", true), "foo.1.png");
+        assert_eq!(free_name("foo.png", &[], ".#", false), "foo.png.1");
+        assert_eq!(free_name("foo", &[], ".#", true), "foo.1");
+        assert_eq!(free_name(".bashrc", &[], ".#", true), ".bashrc.1");
+        assert_eq!(
+            free_name("archive.tar.gz", &[], ".#", true),
+            "archive.1.tar.gz"
+        );
+        assert_eq!(
+            free_name("archive.tar.gz", &[], ".#", false),
+            "archive.tar.gz.1"
+        );
+    }
+
+    #[test]
+    fn numbered_names_count_up() {
+        assert_eq!(free_name("foo.1", &[], ".#", true), "foo.2");
+        assert_eq!(free_name("foo.1.png", &[], ".#", true), "foo.2.png");
+        assert_eq!(
+            free_name("foo.png", &["foo.1.png", "foo.2.png"], ".#", true),
+            "foo.3.png"
+        );
+        assert_eq!(free_name("foo", &["foo.1", "foo.3"], ".#", true), "foo.2");
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(extension("foo.png"), ".png");
+        assert_eq!(extension("archive.tar.gz"), ".tar.gz");
+        assert_eq!(extension("archive.TAR.xz"), ".TAR.xz");
+        assert_eq!(extension("foo.gz"), ".gz");
+        assert_eq!(extension(".tar.gz"), ".gz");
+        assert_eq!(extension(".bashrc"), "");
+        assert_eq!(extension("foo"), "");
+        assert_eq!(extension("foo."), "");
+    }
 }
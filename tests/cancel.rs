@@ -0,0 +1,115 @@
+mod common;
+
+use common::{focused, names, press, press_with, select, type_text, window};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::path::Path;
+use walked::{Config, PanelMode, Window};
+
+fn cancelling_window(dir: &Path) -> Window {
+    let config = Config {
+        normal_mode_cancels: true,
+        ..Config::default()
+    };
+    window(dir, config)
+}
+
+fn dir_with(names: &[&str]) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for name in names {
+        std::fs::write(dir.path().join(name), "").unwrap();
+    }
+    dir
+}
+
+#[test]
+fn esc_leaves_a_prompt_without_running_it() {
+    let dir = dir_with(&["apple", "banana"]);
+    let mut window = window(dir.path(), Config::default());
+    press(&mut window, KeyCode::Char('f'));
+    type_text(&mut window, "ban");
+    assert_eq!(names(&window), ["banana"]);
+    press(&mut window, KeyCode::Esc);
+    let panel = focused(&window);
+    assert!(panel.mode == PanelMode::Normal);
+    assert!(panel.command_prompt.is_none());
+    assert!(panel.edit_buffer.is_empty());
+    assert_eq!(names(&window), ["apple", "banana"]);
+}
+
+#[test]
+fn quit_is_typed_into_a_prompt_when_normal_mode_cancels() {
+    let dir = dir_with(&["a"]);
+    let mut window = cancelling_window(dir.path());
+    press(&mut window, KeyCode::Char('g'));
+    let res = window.process_key_event(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE));
+    assert!(!res.quit);
+    assert_eq!(focused(&window).edit_buffer, "q");
+    press(&mut window, KeyCode::Esc);
+    assert!(focused(&window).mode == PanelMode::Normal);
+    assert!(focused(&window).edit_buffer.is_empty());
+}
+
+#[test]
+fn esc_leaves_a_search() {
+    let dir = dir_with(&["banana", "band"]);
+    let mut window = cancelling_window(dir.path());
+    press(&mut window, KeyCode::Char('/'));
+    type_text(&mut window, "ban");
+    press(&mut window, KeyCode::Enter);
+    assert!(focused(&window).mode == PanelMode::Search);
+    press(&mut window, KeyCode::Esc);
+    let panel = focused(&window);
+    assert!(panel.mode == PanelMode::Normal);
+    assert!(panel.incremental_search_results.is_empty());
+}
+
+#[test]
+fn esc_leaves_a_rename_without_renaming_when_normal_mode_cancels() {
+    let dir = dir_with(&["a"]);
+    let mut window = cancelling_window(dir.path());
+    press(&mut window, KeyCode::Char('i'));
+    assert!(focused(&window).mode == PanelMode::Insert);
+    type_text(&mut window, "bc");
+    press(&mut window, KeyCode::Esc);
+    assert!(focused(&window).mode == PanelMode::Normal);
+    assert_eq!(names(&window), ["a"]);
+}
+
+#[test]
+fn esc_finishes_a_rename_by_default() {
+    let dir = dir_with(&["a"]);
+    let mut window = window(dir.path(), Config::default());
+    select(&mut window, "a");
+    // the cursor is moved past the name before typing
+    press(&mut window, KeyCode::Char('l'));
+    press(&mut window, KeyCode::Char('i'));
+    type_text(&mut window, "bc");
+    press(&mut window, KeyCode::Esc);
+    assert!(focused(&window).mode == PanelMode::Normal);
+    assert_eq!(names(&window), ["abc"]);
+}
+
+#[test]
+fn esc_clears_the_selection_when_normal_mode_cancels() {
+    let dir = dir_with(&["a", "b", "c"]);
+    let mut window = cancelling_window(dir.path());
+    press(&mut window, KeyCode::Char('J'));
+    assert!(focused(&window).selection_start.is_some());
+    press(&mut window, KeyCode::Esc);
+    assert!(focused(&window).selection_start.is_none());
+}
+
+#[test]
+fn esc_answers_no_to_a_confirmation() {
+    let dir = dir_with(&["a", "b"]);
+    let mut window = cancelling_window(dir.path());
+    select(&mut window, "b");
+    press_with(&mut window, KeyCode::Char('x'), KeyModifiers::CONTROL);
+    assert!(focused(&window).mode == PanelMode::Confirm);
+    assert!(focused(&window).pending_confirmation.is_some());
+    press(&mut window, KeyCode::Esc);
+    let panel = focused(&window);
+    assert!(panel.mode == PanelMode::Normal);
+    assert!(panel.pending_confirmation.is_none());
+    assert!(dir.path().join("b").exists());
+}
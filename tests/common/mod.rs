@@ -0,0 +1,57 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::path::Path;
+use walked::{Config, Panel, Window, frecency::Frecency};
+
+/// A window with a single pane in `dir`.
+pub fn window(dir: &Path, config: Config) -> Window {
+    Window::new(config, dir.to_path_buf(), Frecency::default())
+}
+
+pub fn press(window: &mut Window, code: KeyCode) {
+    press_with(window, code, KeyModifiers::NONE);
+}
+
+pub fn press_with(window: &mut Window, code: KeyCode, modifiers: KeyModifiers) {
+    window.process_key_event(KeyEvent::new(code, modifiers));
+}
+
+/// Types `text` one character at a time.
+pub fn type_text(window: &mut Window, text: &str) {
+    for c in text.chars() {
+        press(window, KeyCode::Char(c));
+    }
+}
+
+/// Makes `dir` the working directory of the focused pane. Typing it into the goto prompt could
+/// quit, since temporary directories can have a 'q' in their name.
+pub fn goto(window: &mut Window, dir: &Path) {
+    window.panels[window.panel_focus_i][window.panel_focus_j]
+        .change_directory(dir.to_path_buf(), &window.config);
+}
+
+pub fn focused(window: &Window) -> &Panel {
+    &window.panels[window.panel_focus_i][window.panel_focus_j]
+}
+
+/// Names of the entries listed in the focused pane, in the order they are listed.
+pub fn names(window: &Window) -> Vec<String> {
+    focused(window)
+        .entries
+        .iter()
+        .map(|entry| entry.file_name().unwrap().to_string_lossy().to_string())
+        .collect()
+}
+
+/// Moves the cursor of the focused pane onto the entry called `name`.
+pub fn select(window: &mut Window, name: &str) {
+    let i = names(window)
+        .iter()
+        .position(|n| n == name)
+        .unwrap_or_else(|| panic!("'{name}' isn't listed"));
+    let panel = &mut window.panels[window.panel_focus_i][window.panel_focus_j];
+    panel.table_state.select(Some(i));
+    panel.refresh_cursor();
+}
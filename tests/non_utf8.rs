@@ -0,0 +1,76 @@
+//! Names on Unix are bytes, they don't have to be valid UTF-8.
+#![cfg(unix)]
+
+mod common;
+
+use common::{names, press, press_with, select, type_text, window};
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::{Terminal, backend::TestBackend};
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt, path::PathBuf};
+use walked::Config;
+
+fn bad_name(suffix: &str) -> PathBuf {
+    PathBuf::from(OsStr::from_bytes(
+        &[b"bad\xff".as_slice(), suffix.as_bytes()].concat(),
+    ))
+}
+
+#[test]
+fn entries_are_listed_and_drawn() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join(bad_name("")), "").unwrap();
+    let mut window = window(dir.path(), Config::default());
+    assert_eq!(names(&window), ["bad\u{fffd}"]);
+    let mut terminal = Terminal::new(TestBackend::new(40, 5)).unwrap();
+    terminal.draw(|f| walked::render(f, &mut window)).unwrap();
+}
+
+#[test]
+fn duplicates_keep_the_name_bytes() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join(bad_name("")), "content").unwrap();
+    let mut window = window(dir.path(), Config::default());
+    press_with(&mut window, KeyCode::Char('d'), KeyModifiers::CONTROL);
+    let copy = dir.path().join(bad_name(".1"));
+    assert_eq!(std::fs::read_to_string(copy).unwrap(), "content");
+}
+
+#[test]
+fn directories_are_walked_into_and_out_of() {
+    let dir = tempfile::tempdir().unwrap();
+    let sub = dir.path().join(bad_name(""));
+    std::fs::create_dir(&sub).unwrap();
+    std::fs::write(sub.join("inner"), "").unwrap();
+    let mut window = window(dir.path(), Config::default());
+    select(&mut window, "bad\u{fffd}");
+    press(&mut window, KeyCode::Char(' '));
+    assert_eq!(window.panel().working_directory, sub);
+    assert_eq!(names(&window), ["inner"]);
+    press(&mut window, KeyCode::Char('x'));
+    assert_eq!(window.panel().working_directory, dir.path());
+}
+
+#[test]
+fn pattern_renames_leave_them_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join(bad_name("")), "").unwrap();
+    std::fs::write(dir.path().join("good"), "").unwrap();
+    let mut window = window(dir.path(), Config::default());
+    press(&mut window, KeyCode::Char('A'));
+    press(&mut window, KeyCode::Char('E'));
+    type_text(&mut window, "good/fine");
+    press(&mut window, KeyCode::Enter);
+    press(&mut window, KeyCode::Char('y'));
+    assert!(dir.path().join(bad_name("")).exists());
+    assert!(dir.path().join("fine").exists());
+    assert!(window.panel().errors.is_empty());
+
+    // a search that only matches the lossy name is refused rather than mangling the name
+    press(&mut window, KeyCode::Char('A'));
+    press(&mut window, KeyCode::Char('E'));
+    type_text(&mut window, "bad/worse");
+    press(&mut window, KeyCode::Enter);
+    assert!(window.panel().pending_rename.is_none());
+    assert!(dir.path().join(bad_name("")).exists());
+    assert_eq!(window.panel().errors.len(), 1);
+}
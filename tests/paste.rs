@@ -0,0 +1,106 @@
+mod common;
+
+use common::{focused, goto, names, press, press_with, select, window};
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::path::Path;
+use walked::{
+    Config, WalkedError, Window,
+    config::{PasteConflictPolicy, TypeConflictPolicy},
+};
+
+/// Copies `name` from `from` and pastes it into `to`.
+fn copy_paste(window: &mut Window, from: &Path, name: &str, to: &Path) {
+    goto(window, from);
+    select(window, name);
+    press_with(window, KeyCode::Char('y'), KeyModifiers::CONTROL);
+    goto(window, to);
+    press_with(window, KeyCode::Char('p'), KeyModifiers::CONTROL);
+}
+
+/// A file and a directory both called "x", in "files" and "dirs".
+fn conflicting_entries() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("files")).unwrap();
+    std::fs::write(dir.path().join("files").join("x"), "file").unwrap();
+    std::fs::create_dir_all(dir.path().join("dirs").join("x")).unwrap();
+    dir
+}
+
+#[test]
+fn file_onto_directory_is_renamed() {
+    let dir = conflicting_entries();
+    let dirs = dir.path().join("dirs");
+    let mut window = window(dir.path(), Config::default());
+    copy_paste(&mut window, &dir.path().join("files"), "x", &dirs);
+    assert_eq!(names(&window), ["x", "x.1"]);
+    assert!(dirs.join("x").is_dir());
+    assert_eq!(std::fs::read_to_string(dirs.join("x.1")).unwrap(), "file");
+}
+
+#[test]
+fn directory_onto_file_is_renamed() {
+    let dir = conflicting_entries();
+    let files = dir.path().join("files");
+    let mut window = window(dir.path(), Config::default());
+    copy_paste(&mut window, &dir.path().join("dirs"), "x", &files);
+    assert_eq!(names(&window), ["x", "x.1"]);
+    assert!(files.join("x").is_file());
+    assert!(files.join("x.1").is_dir());
+}
+
+#[test]
+fn type_conflicts_are_refused() {
+    let dir = conflicting_entries();
+    let dirs = dir.path().join("dirs");
+    let config = Config {
+        type_conflict: TypeConflictPolicy::Refuse,
+        ..Config::default()
+    };
+    let mut window = window(dir.path(), config);
+    copy_paste(&mut window, &dir.path().join("files"), "x", &dirs);
+    assert_eq!(names(&window), ["x"]);
+    assert!(dirs.join("x").is_dir());
+    assert!(matches!(
+        &focused(&window).errors[..],
+        [WalkedError::Message(msg)] if msg.starts_with("Can't paste file")
+    ));
+}
+
+#[test]
+fn overwriting_doesnt_replace_an_entry_of_another_type() {
+    let dir = conflicting_entries();
+    let dirs = dir.path().join("dirs");
+    let config = Config {
+        paste_conflict: PasteConflictPolicy::Overwrite,
+        ..Config::default()
+    };
+    let mut window = window(dir.path(), config);
+    copy_paste(&mut window, &dir.path().join("files"), "x", &dirs);
+    assert_eq!(names(&window), ["x", "x.1"]);
+    assert!(dirs.join("x").is_dir());
+}
+
+#[test]
+fn cut_entries_are_moved_and_moved_back_by_undo() {
+    let dir = tempfile::tempdir().unwrap();
+    let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+    std::fs::create_dir_all(from.join("x")).unwrap();
+    std::fs::write(from.join("x").join("file"), "file").unwrap();
+    std::fs::create_dir(&to).unwrap();
+    let mut window = window(dir.path(), Config::default());
+    goto(&mut window, &from);
+    select(&mut window, "x");
+    press(&mut window, KeyCode::Char('d'));
+    goto(&mut window, &to);
+    press_with(&mut window, KeyCode::Char('p'), KeyModifiers::CONTROL);
+    assert_eq!(names(&window), ["x"]);
+    assert!(!from.join("x").exists());
+    assert_eq!(
+        std::fs::read_to_string(to.join("x").join("file")).unwrap(),
+        "file"
+    );
+
+    press(&mut window, KeyCode::Char('u'));
+    assert!(names(&window).is_empty());
+    assert!(from.join("x").join("file").exists());
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use common::{focused, names, press, press_with, window};
+use crossterm::event::{KeyCode, KeyModifiers};
+use walked::{Config, WalkedError};
+
+/// Removes "a", "b" and "c" together, with "b" vanishing while the removal is confirmed. Returns
+/// the entries that are left and the errors.
+fn remove_with_failure(stop_on_first_error: bool) -> (Vec<String>, Vec<WalkedError>) {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["a", "b", "c"] {
+        std::fs::write(dir.path().join(name), "").unwrap();
+    }
+    let config = Config {
+        stop_on_first_error,
+        ..Config::default()
+    };
+    let mut window = window(dir.path(), config);
+    press(&mut window, KeyCode::Char('A'));
+    press_with(&mut window, KeyCode::Char('x'), KeyModifiers::CONTROL);
+    std::fs::remove_file(dir.path().join("b")).unwrap();
+    press(&mut window, KeyCode::Char('y'));
+    assert!(focused(&window).pending_confirmation.is_none());
+    let errors = std::mem::take(&mut window.panels[0][0].errors);
+    (names(&window), errors)
+}
+
+#[test]
+fn batch_goes_on_after_an_error() {
+    let (left, errors) = remove_with_failure(false);
+    assert!(left.is_empty());
+    assert!(matches!(&errors[..], [WalkedError::PathNotFound { .. }]));
+}
+
+#[test]
+fn batch_stops_on_the_first_error() {
+    let (left, errors) = remove_with_failure(true);
+    assert_eq!(left, ["c"]);
+    assert!(matches!(
+        &errors[..],
+        [WalkedError::PathNotFound { .. }, WalkedError::Message(msg)]
+            if msg.ends_with("1 entry wasn't processed: c")
+    ));
+}
+
+/// Removes the entry called `name` in `dir`, returns the errors.
+fn remove(dir: &std::path::Path, name: &str) -> Vec<WalkedError> {
+    let mut window = window(dir, Config::default());
+    common::select(&mut window, name);
+    press_with(&mut window, KeyCode::Char('x'), KeyModifiers::CONTROL);
+    press(&mut window, KeyCode::Char('y'));
+    std::mem::take(&mut window.panels[0][0].errors)
+}
+
+#[test]
+fn removes_empty_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("empty")).unwrap();
+    assert!(remove(dir.path(), "empty").is_empty());
+    assert!(!dir.path().join("empty").exists());
+}
+
+#[test]
+fn removes_directories_with_their_contents() {
+    let dir = tempfile::tempdir().unwrap();
+    let full = dir.path().join("full");
+    std::fs::create_dir_all(full.join("inner")).unwrap();
+    std::fs::write(full.join("file"), "").unwrap();
+    std::fs::write(full.join("inner").join("file"), "").unwrap();
+    assert!(remove(dir.path(), "full").is_empty());
+    assert!(!full.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_directories_are_removed_or_reported() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::tempdir().unwrap();
+    let locked = dir.path().join("locked");
+    std::fs::create_dir(&locked).unwrap();
+    std::fs::write(locked.join("file"), "").unwrap();
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+    let errors = remove(dir.path(), "locked");
+    // root can still read it, everyone else gets an error instead of it being skipped silently
+    if locked.exists() {
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(!errors.is_empty());
+    } else {
+        assert!(errors.is_empty());
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn removes_broken_symlinks() {
+    let dir = tempfile::tempdir().unwrap();
+    let link = dir.path().join("link");
+    std::os::unix::fs::symlink("missing", &link).unwrap();
+    assert!(remove(dir.path(), "link").is_empty());
+    assert!(link.symlink_metadata().is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn removes_symlinks_to_directories_but_not_the_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    std::fs::create_dir(&target).unwrap();
+    std::fs::write(target.join("file"), "").unwrap();
+    std::os::unix::fs::symlink(&target, dir.path().join("link")).unwrap();
+    assert!(remove(dir.path(), "link").is_empty());
+    assert!(dir.path().join("link").symlink_metadata().is_err());
+    assert!(target.join("file").exists());
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::{focused, names, press, type_text, window};
+use crossterm::event::KeyCode;
+use std::path::Path;
+use walked::{Config, WalkedError, Window};
+
+fn files(dir: &Path, names: &[&str]) {
+    for name in names {
+        std::fs::write(dir.join(name), *name).unwrap();
+    }
+}
+
+/// Selects every entry and runs a search/replace rename with `expression` on them.
+fn pattern_rename(window: &mut Window, expression: &str) {
+    press(window, KeyCode::Char('A'));
+    press(window, KeyCode::Char('E'));
+    type_text(window, expression);
+    press(window, KeyCode::Enter);
+}
+
+#[test]
+fn literal_search_is_replaced() {
+    let dir = tempfile::tempdir().unwrap();
+    files(dir.path(), &["notes.txt", "photo_1.jpg", "photo_2.jpg"]);
+    let mut window = window(dir.path(), Config::default());
+    pattern_rename(&mut window, "photo_/img_");
+    press(&mut window, KeyCode::Char('y'));
+    assert_eq!(names(&window), ["img_1.jpg", "img_2.jpg", "notes.txt"]);
+    assert!(focused(&window).errors.is_empty());
+}
+
+#[test]
+fn regex_groups_are_replaced() {
+    let dir = tempfile::tempdir().unwrap();
+    files(dir.path(), &["a1", "b2", "c"]);
+    let config = Config {
+        pattern_rename_regex: true,
+        ..Config::default()
+    };
+    let mut window = window(dir.path(), config);
+    pattern_rename(&mut window, "([a-z])([0-9])/$2$1");
+    press(&mut window, KeyCode::Char('y'));
+    assert_eq!(names(&window), ["1a", "2b", "c"]);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("2b")).unwrap(),
+        "b2"
+    );
+}
+
+#[test]
+fn colliding_names_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    files(dir.path(), &["x1", "x2"]);
+    let config = Config {
+        pattern_rename_regex: true,
+        ..Config::default()
+    };
+    let mut window = window(dir.path(), config);
+    pattern_rename(&mut window, "[0-9]/");
+    assert!(focused(&window).pending_rename.is_none());
+    assert_eq!(names(&window), ["x1", "x2"]);
+    assert!(matches!(
+        &focused(&window).errors[..],
+        [WalkedError::Message(given), WalkedError::Message(nothing)]
+            if given.contains("more than one entry") && nothing == "Nothing was renamed"
+    ));
+}
+
+#[test]
+fn names_taken_by_unchanged_entries_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    files(dir.path(), &["cat", "dog"]);
+    let mut window = window(dir.path(), Config::default());
+    pattern_rename(&mut window, "cat/dog");
+    assert!(focused(&window).pending_rename.is_none());
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("dog")).unwrap(),
+        "dog"
+    );
+}
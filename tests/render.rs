@@ -0,0 +1,99 @@
+mod common;
+
+use common::{focused, names, press, press_with, select, window};
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::{Terminal, backend::TestBackend};
+use walked::{Config, Window};
+
+/// Draws `window` into a `width` by `height` terminal and returns its lines.
+fn draw(window: &mut Window, width: u16, height: u16) -> Vec<String> {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|f| walked::render(f, window)).unwrap();
+    let buffer = terminal.backend().buffer();
+    (0..height)
+        .map(|y| (0..width).map(|x| buffer[(x, y)].symbol()).collect())
+        .collect()
+}
+
+#[test]
+fn type_indicators_are_only_drawn() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    std::fs::write(dir.path().join("file"), "").unwrap();
+    let config = Config {
+        show_type_indicator: true,
+        ..Config::default()
+    };
+    let mut window = window(dir.path(), config);
+    let lines = draw(&mut window, 60, 10);
+    assert!(lines.iter().any(|line| line.contains("sub/")));
+    assert!(!lines.iter().any(|line| line.contains("file*")));
+
+    // renaming starts from the name without the indicator
+    select(&mut window, "sub");
+    press(&mut window, KeyCode::Char('i'));
+    assert_eq!(focused(&window).edit_buffer, "sub");
+    press(&mut window, KeyCode::Esc);
+    assert_eq!(names(&window), ["file", "sub"]);
+    assert!(dir.path().join("sub").is_dir());
+}
+
+#[test]
+fn tiny_areas_dont_panic() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    std::fs::write(dir.path().join("file"), "some text").unwrap();
+    let config = Config {
+        show_preview: true,
+        show_status_bar: true,
+        ..Config::default()
+    };
+    let mut window = window(dir.path(), config);
+    let sizes = [
+        (0, 0),
+        (1, 1),
+        (0, 10),
+        (10, 0),
+        (1, 10),
+        (10, 1),
+        (2, 2),
+        (3, 3),
+    ];
+    for (width, height) in sizes {
+        draw(&mut window, width, height);
+    }
+    // panes are only split if there's room for them, so it's done at a usual size
+    draw(&mut window, 80, 24);
+    for key in ['l', 'j'] {
+        press_with(&mut window, KeyCode::Char(key), KeyModifiers::ALT);
+    }
+    press_with(&mut window, KeyCode::Char('k'), KeyModifiers::CONTROL);
+    press_with(&mut window, KeyCode::Char('j'), KeyModifiers::ALT);
+    assert_eq!(window.panels.iter().flatten().count(), 4);
+    for (width, height) in sizes {
+        draw(&mut window, width, height);
+    }
+    press(&mut window, KeyCode::Char('?'));
+    assert!(window.help_scroll.is_some());
+    for (width, height) in sizes {
+        draw(&mut window, width, height);
+    }
+}
+
+#[test]
+fn goto_bottom_scrolls_the_last_entry_into_view() {
+    let dir = tempfile::tempdir().unwrap();
+    for n in 0..50 {
+        std::fs::write(dir.path().join(format!("file{n:02}")), "").unwrap();
+    }
+    let mut window = window(dir.path(), Config::default());
+    assert!(!draw(&mut window, 60, 12).concat().contains("file49"));
+    press(&mut window, KeyCode::Char('G'));
+    assert_eq!(focused(&window).table_state.selected(), Some(49));
+    assert!(focused(&window).table_state.offset() > 0);
+    assert!(draw(&mut window, 60, 12).concat().contains("file49"));
+
+    press(&mut window, KeyCode::Home);
+    assert_eq!(focused(&window).table_state.selected(), Some(0));
+    assert!(draw(&mut window, 60, 12).concat().contains("file00"));
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::{names, press, press_with, type_text, window};
+use crossterm::event::{KeyCode, KeyModifiers};
+use walked::Config;
+
+#[test]
+fn duplicates_are_repeated() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a"), "").unwrap();
+    let mut window = window(dir.path(), Config::default());
+    press_with(&mut window, KeyCode::Char('d'), KeyModifiers::CONTROL);
+    assert_eq!(names(&window), ["a", "a.1"]);
+    press(&mut window, KeyCode::Char('.'));
+    assert_eq!(names(&window), ["a", "a.1", "a.2"]);
+}
+
+#[test]
+fn pattern_renames_are_repeated() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("v1"), "").unwrap();
+    let mut window = window(dir.path(), Config::default());
+    press(&mut window, KeyCode::Char('E'));
+    type_text(&mut window, "v/vv");
+    press(&mut window, KeyCode::Enter);
+    press(&mut window, KeyCode::Char('y'));
+    assert_eq!(names(&window), ["vv1"]);
+    // asks again before renaming, like the first time
+    press(&mut window, KeyCode::Char('.'));
+    assert!(window.panel().pending_rename.is_some());
+    press(&mut window, KeyCode::Char('y'));
+    assert_eq!(names(&window), ["vvvv1"]);
+}
+
+#[test]
+fn navigation_isnt_repeated() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["a", "b"] {
+        std::fs::write(dir.path().join(name), "").unwrap();
+    }
+    let mut window = window(dir.path(), Config::default());
+    press(&mut window, KeyCode::Char('.'));
+    assert_eq!(names(&window), ["a", "b"]);
+
+    press_with(&mut window, KeyCode::Char('d'), KeyModifiers::CONTROL);
+    press(&mut window, KeyCode::Char('G'));
+    press(&mut window, KeyCode::Char('k'));
+    press(&mut window, KeyCode::Home);
+    assert_eq!(window.panel().table_state.selected(), Some(0));
+    // the duplicate is what's repeated, not the moves since
+    press(&mut window, KeyCode::Char('.'));
+    assert_eq!(names(&window), ["a", "a.1", "a.2", "b"]);
+    assert_eq!(window.panel().table_state.selected(), Some(0));
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::{names, press, select, window};
+use crossterm::event::KeyCode;
+use std::time::Duration;
+use walked::{
+    Config, Window,
+    window::{CommandKind, KEY_SEQUENCE_TIMEOUT},
+};
+
+/// A window over "a", "b" and "c" with `goto_top` bound to "g g", so g starts a sequence and opens
+/// the goto prompt on its own. The cursor is on "c".
+fn gg_window(dir: &tempfile::TempDir) -> Window {
+    for name in ["a", "b", "c"] {
+        std::fs::write(dir.path().join(name), "").unwrap();
+    }
+    let mut config = Config::default();
+    config.apply_toml(toml::from_str("goto_top = \"g g\"").unwrap());
+    let mut window = window(dir.path(), config);
+    assert_eq!(names(&window), ["a", "b", "c"]);
+    select(&mut window, "c");
+    window
+}
+
+#[test]
+fn finished_sequences_do_their_action() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut window = gg_window(&dir);
+    press(&mut window, KeyCode::Char('g'));
+    assert!(window.panel().command_prompt.is_none());
+    press(&mut window, KeyCode::Char('g'));
+    assert!(window.panel().command_prompt.is_none());
+    assert_eq!(window.panel().table_state.selected(), Some(0));
+}
+
+#[test]
+fn unfinished_sequences_fall_back_to_the_first_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut window = gg_window(&dir);
+    press(&mut window, KeyCode::Char('g'));
+    press(&mut window, KeyCode::Char('x'));
+    // g opened the goto prompt on its own and x was typed into it
+    assert!(matches!(
+        window.panel().command_prompt,
+        Some(CommandKind::Goto)
+    ));
+    assert_eq!(window.panel().edit_buffer, "x");
+    assert_eq!(window.panel().table_state.selected(), Some(2));
+}
+
+#[test]
+fn first_keys_act_on_their_own_once_nothing_follows() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut window = gg_window(&dir);
+    press(&mut window, KeyCode::Char('g'));
+    assert!(window.panel().expired_key().is_none());
+    std::thread::sleep(KEY_SEQUENCE_TIMEOUT + Duration::from_millis(50));
+    let key_event = window.panel().expired_key().unwrap();
+    window.process_key_event(key_event);
+    assert!(matches!(
+        window.panel().command_prompt,
+        Some(CommandKind::Goto)
+    ));
+    assert!(window.panel().edit_buffer.is_empty());
+}
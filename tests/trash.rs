@@ -0,0 +1,39 @@
+//! The trash is looked up through `XDG_DATA_HOME`, which is set for the whole process, so this
+//! file holds a single test.
+#![cfg(all(
+    unix,
+    not(target_os = "macos"),
+    not(target_os = "ios"),
+    not(target_os = "android")
+))]
+
+mod common;
+
+use common::{names, press, press_with, select, window};
+use crossterm::event::{KeyCode, KeyModifiers};
+use walked::Config;
+
+#[test]
+fn trashing_is_undone_and_redone() {
+    let dir = tempfile::tempdir().unwrap();
+    // SAFETY: no other thread reads the environment yet
+    unsafe { std::env::set_var("XDG_DATA_HOME", dir.path().join("data")) };
+    let files = dir.path().join("files");
+    std::fs::create_dir(&files).unwrap();
+    std::fs::write(files.join("a"), "content").unwrap();
+    std::fs::write(files.join("b"), "").unwrap();
+    let mut window = window(&files, Config::default());
+
+    select(&mut window, "a");
+    press_with(&mut window, KeyCode::Char('t'), KeyModifiers::CONTROL);
+    press(&mut window, KeyCode::Char('y'));
+    assert_eq!(names(&window), ["b"]);
+
+    press(&mut window, KeyCode::Char('u'));
+    assert_eq!(names(&window), ["a", "b"]);
+    assert_eq!(std::fs::read_to_string(files.join("a")).unwrap(), "content");
+
+    press_with(&mut window, KeyCode::Char('r'), KeyModifiers::CONTROL);
+    assert_eq!(names(&window), ["b"]);
+    assert!(window.panel().errors.is_empty());
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use common::{names, press, select, type_text, window};
+use crossterm::event::KeyCode;
+use walked::{Config, WalkedError, window::CommandKind};
+
+#[test]
+fn lists_the_working_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("b")).unwrap();
+    std::fs::write(dir.path().join("a"), "").unwrap();
+    std::fs::write(dir.path().join("c"), "").unwrap();
+    let window = window(dir.path(), Config::default());
+    assert_eq!(names(&window), ["a", "b", "c"]);
+}
+
+#[test]
+fn walks_into_a_directory_and_back_up() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    std::fs::write(dir.path().join("sub").join("inner"), "").unwrap();
+    let mut window = window(dir.path(), Config::default());
+    select(&mut window, "sub");
+    press(&mut window, KeyCode::Char(' '));
+    assert_eq!(window.panel().working_directory, dir.path().join("sub"));
+    assert_eq!(names(&window), ["inner"]);
+    press(&mut window, KeyCode::Char('x'));
+    assert_eq!(window.panel().working_directory, dir.path());
+}
+
+#[test]
+fn cursor_stays_in_the_listing_when_entries_disappear() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["a", "b", "c"] {
+        std::fs::write(dir.path().join(name), "").unwrap();
+    }
+    let mut window = window(dir.path(), Config::default());
+    select(&mut window, "c");
+    std::fs::remove_file(dir.path().join("b")).unwrap();
+    std::fs::remove_file(dir.path().join("c")).unwrap();
+    let panel = &mut window.panels[0][0];
+    panel.read_working_dir();
+    assert_eq!(panel.table_state.selected(), Some(0));
+}
+
+#[test]
+fn case_insensitive_sorting_is_toggled() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["b", "A", "a", "B"] {
+        std::fs::write(dir.path().join(name), "").unwrap();
+    }
+    let mut window = window(dir.path(), Config::default());
+    assert_eq!(names(&window), ["A", "B", "a", "b"]);
+    press(&mut window, KeyCode::Char('T'));
+    assert_eq!(names(&window), ["A", "a", "B", "b"]);
+    press(&mut window, KeyCode::Char('T'));
+    assert_eq!(names(&window), ["A", "B", "a", "b"]);
+}
+
+#[test]
+fn unknown_commands_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut window = window(dir.path(), Config::default());
+    window.panels[0][0].prompt(CommandKind::Custom("plugin".to_string()));
+    press(&mut window, KeyCode::Enter);
+    assert!(matches!(
+        &window.panel().errors[..],
+        [WalkedError::Message(msg)] if msg == "There is no 'plugin' command"
+    ));
+}
+
+#[test]
+fn goto_top_and_bottom_leave_the_range() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["a", "b", "c", "d"] {
+        std::fs::write(dir.path().join(name), "").unwrap();
+    }
+    let mut window = window(dir.path(), Config::default());
+    select(&mut window, "b");
+    press(&mut window, KeyCode::Char('J'));
+    assert!(window.panel().selection_start.is_some());
+    press(&mut window, KeyCode::Char('G'));
+    assert_eq!(window.panel().table_state.selected(), Some(3));
+    assert!(window.panel().selection_start.is_none());
+
+    press(&mut window, KeyCode::Char('J'));
+    press(&mut window, KeyCode::Home);
+    assert_eq!(window.panel().table_state.selected(), Some(0));
+    assert!(window.panel().selection_start.is_none());
+}
+
+#[test]
+fn typed_numbers_jump_to_their_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    for n in 0..20 {
+        std::fs::write(dir.path().join(format!("file{n:02}")), "").unwrap();
+    }
+    let mut window = window(dir.path(), Config::default());
+    type_text(&mut window, "12");
+    assert_eq!(window.panel().table_state.selected(), Some(0));
+    press(&mut window, KeyCode::Enter);
+    assert_eq!(window.panel().table_state.selected(), Some(12));
+    assert_eq!(window.panel().entries[12], dir.path().join("file12"));
+    assert!(window.panel().errors.is_empty());
+}
+
+#[test]
+fn typed_numbers_past_the_end_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("file"), "").unwrap();
+    let mut window = window(dir.path(), Config::default());
+    type_text(&mut window, "99");
+    press(&mut window, KeyCode::Enter);
+    assert_eq!(window.panel().table_state.selected(), Some(0));
+    assert!(matches!(
+        &window.panel().errors[..],
+        [WalkedError::Message(msg)] if msg == "There is no entry 99"
+    ));
+}